@@ -0,0 +1,61 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rocketsim_rs::{
+    autocxx::WithinUniquePtr,
+    sim::{Arena, ArenaMemWeightMode, CarConfig, GameMode, Team},
+};
+use stat_final_data::TICK_RATE;
+use std::{fs, hint::black_box, path::Path};
+
+const ASSETS_DIR: &str = "collision_meshes";
+
+/// Every game mode, with the folder of its collision meshes if it needs any
+const GAME_MODES: [(&str, GameMode, Option<&str>); 5] = [
+    ("void", GameMode::THE_VOID, None),
+    ("soccar", GameMode::SOCCAR, Some("soccar")),
+    ("hoops", GameMode::HOOPS, Some("hoops")),
+    ("heatseeker", GameMode::HEATSEEKER, Some("soccar")),
+    ("snowday", GameMode::SNOWDAY, Some("soccar")),
+];
+
+const MEM_WEIGHT_MODES: [(&str, ArenaMemWeightMode); 2] =
+    [("light", ArenaMemWeightMode::LIGHT), ("heavy", ArenaMemWeightMode::HEAVY)];
+
+fn has_meshes(folder: &str) -> bool {
+    fs::read_dir(Path::new(ASSETS_DIR).join(folder)).is_ok_and(|mut entries| {
+        entries.any(|entry| entry.is_ok_and(|entry| entry.path().extension().is_some_and(|ext| ext == "cmf")))
+    })
+}
+
+/// The same construction and one-time setup as a worker's `Simulation::new`
+fn arena_construction(c: &mut Criterion) {
+    rocketsim_rs::init(Some(ASSETS_DIR));
+
+    let mut group = c.benchmark_group("arena_construction");
+
+    for (mode_name, game_mode, meshes_folder) in GAME_MODES {
+        if meshes_folder.is_some_and(|folder| !has_meshes(folder)) {
+            println!("Skipping {mode_name}, no collision meshes found in {ASSETS_DIR}");
+            continue;
+        }
+
+        for (weight_name, mem_weight_mode) in MEM_WEIGHT_MODES {
+            group.bench_function(BenchmarkId::new(mode_name, weight_name), |b| {
+                b.iter(|| {
+                    let mut arena = Arena::new(game_mode, mem_weight_mode, TICK_RATE).within_unique_ptr();
+
+                    let mut mutators = arena.get_mutator_config();
+                    mutators.gravity.z = -f32::EPSILON;
+                    arena.pin_mut().set_mutator_config(mutators);
+
+                    arena.pin_mut().add_car(Team::BLUE, CarConfig::octane());
+                    black_box(arena)
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, arena_construction);
+criterion_main!(benches);
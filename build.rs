@@ -0,0 +1,19 @@
+use std::{env, fs, path::Path};
+
+/// Exposes the version of rocketsim_rs in Cargo.lock as `ROCKETSIM_RS_VERSION`, since the dynamics can change between versions
+fn main() {
+    let lock_file = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+
+    let lock = fs::read_to_string(&lock_file).unwrap_or_default();
+    let mut lines = lock.lines();
+
+    let version = lines
+        .find(|line| line.trim() == "name = \"rocketsim_rs\"")
+        .and_then(|_| lines.next())
+        .and_then(|line| line.trim().strip_prefix("version = \""))
+        .and_then(|version| version.strip_suffix('"'))
+        .unwrap_or("unknown");
+
+    println!("cargo:rustc-env=ROCKETSIM_RS_VERSION={version}");
+}
@@ -0,0 +1,45 @@
+use crate::{format::Header, simulation::SimResult, table};
+use arrow::{
+    array::{ArrayRef, Float32Array, Int32Array},
+    datatypes::{DataType, Field, Schema},
+    error::Result,
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+use std::{collections::HashMap, io::Write, sync::Arc};
+
+/// Writes an uncompressed Arrow IPC file, so readers can memory-map it, with a column for every `table::columns`
+/// and the header as JSON in the `header` key of its schema's metadata, returning the size of the values
+pub fn write<W: Write>(writer: W, header: &Header, results: &[SimResult]) -> Result<u64> {
+    let columns = table::columns(header);
+    let fields = columns
+        .iter()
+        .map(|column| {
+            let data_type = if column.integer { DataType::Int32 } else { DataType::Float32 };
+            Field::new(column.name, data_type, false)
+        })
+        .collect::<Vec<_>>();
+    let metadata = HashMap::from([("header".to_string(), serde_json::to_string(header).unwrap())]);
+    let schema = Arc::new(Schema::new_with_metadata(fields, metadata));
+
+    let rows = results.iter().map(|result| table::row(header, result)).collect::<Vec<_>>();
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| -> ArrayRef {
+            if column.integer {
+                Arc::new(Int32Array::from_iter_values(rows.iter().map(|row| row[i] as i32)))
+            } else {
+                Arc::new(Float32Array::from_iter_values(rows.iter().map(|row| row[i])))
+            }
+        })
+        .collect();
+
+    // every file is one batch of results, so it's one record batch
+    let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+    let mut file = FileWriter::try_new(writer, &schema)?;
+    file.write(&batch)?;
+    file.finish()?;
+
+    Ok((rows.len() * columns.len() * 4) as u64)
+}
@@ -0,0 +1,107 @@
+use crate::{format::Header, simulation::SimResult, table};
+
+/// The `table::columns` of every field of `Record` in `proto/results.capnp`, in the order of their 32-bit slots
+const RECORD_FIELDS: [&str; 27] = [
+    "ang_vel_x",
+    "ang_vel_y",
+    "ang_vel_z",
+    "target_pitch",
+    "target_yaw",
+    "target_roll",
+    "time",
+    "response_control",
+    "response_ang_accel",
+    "local_target_x",
+    "local_target_y",
+    "local_target_z",
+    "difficulty_class",
+    "car_config_id",
+    "initial_pitch",
+    "initial_yaw",
+    "initial_roll",
+    "itae",
+    "controller_id",
+    "world_ang_vel_x",
+    "world_ang_vel_y",
+    "world_ang_vel_z",
+    "world_target_pitch",
+    "world_target_yaw",
+    "world_target_x",
+    "world_target_y",
+    "world_target_z",
+];
+/// The size of the data section of every `Record`, in words
+const RECORD_WORDS: usize = RECORD_FIELDS.len().div_ceil(2);
+/// The size of the data section of `Header`, in words
+const HEADER_WORDS: usize = 3;
+/// The element size of a list of bytes
+const ELEMENT_BYTE: u64 = 2;
+/// The element size of a list of structs, which is preceded by a tag
+const ELEMENT_COMPOSITE: u64 = 7;
+
+/// A single-segment Cap'n Proto message of the `ResultBatch` of `proto/results.capnp`, framed like
+/// `capnp::serialize::write_message`, scaled like `format::serialize` with the seconds time codec.
+/// The omitted inputs and the optional fields that aren't present are left 0
+pub fn serialize(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let columns = table::columns(header);
+    let slots = columns
+        .iter()
+        .map(|column| RECORD_FIELDS.iter().position(|&name| name == column.name).unwrap())
+        .collect::<Vec<_>>();
+
+    let metadata = serde_json::to_string(&header.metadata).unwrap();
+    let metadata_words = (metadata.len() + 1).div_ceil(8);
+
+    // the root pointer, `ResultBatch`, `Header` and its metadata, then the tag and the elements of the records list
+    let segment_words = 1 + 2 + HEADER_WORDS + 1 + metadata_words + 1 + results.len() * RECORD_WORDS;
+    let mut bytes = Vec::with_capacity(8 + segment_words * 8);
+
+    // the segment table, the number of segments minus 1 and the size of the only one
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend((segment_words as u32).to_le_bytes());
+
+    // the pointers are relative to the word after them
+    bytes.extend(struct_pointer(0, 0, 2).to_le_bytes());
+    bytes.extend(struct_pointer(1, HEADER_WORDS as u16, 1).to_le_bytes());
+    bytes.extend(list_pointer(4 + metadata_words, ELEMENT_COMPOSITE, results.len() * RECORD_WORDS).to_le_bytes());
+
+    for value in [
+        header.fields.bits(),
+        header.tick_rate.to_bits(),
+        header.ang_vel_scale.to_bits(),
+        header.angle_scale.to_bits(),
+        header.time_scale.to_bits(),
+        header.omitted_inputs.bits(),
+    ] {
+        bytes.extend(value.to_le_bytes());
+    }
+
+    // text is a list of bytes ending with a NUL
+    bytes.extend(list_pointer(0, ELEMENT_BYTE, metadata.len() + 1).to_le_bytes());
+    bytes.extend(metadata.as_bytes());
+    bytes.resize(bytes.len() + metadata_words * 8 - metadata.len(), 0);
+
+    // a composite list starts with a tag in the format of a struct pointer, with the number of elements as the offset
+    bytes.extend(struct_pointer(results.len(), RECORD_WORDS as u16, 0).to_le_bytes());
+
+    for result in results {
+        let mut record = [0u32; RECORD_WORDS * 2];
+        for ((column, value), &slot) in columns.iter().zip(table::row(header, result)).zip(&slots) {
+            record[slot] = if column.integer { value as u32 } else { value.to_bits() };
+        }
+
+        bytes.extend(record.iter().flat_map(|value| value.to_le_bytes()));
+    }
+
+    debug_assert_eq!(bytes.len(), 8 + segment_words * 8);
+
+    bytes
+}
+
+fn struct_pointer(offset: usize, data_words: u16, pointers: u16) -> u64 {
+    (offset as u64) << 2 | u64::from(data_words) << 32 | u64::from(pointers) << 48
+}
+
+fn list_pointer(offset: usize, element_size: u64, count: usize) -> u64 {
+    1 | (offset as u64) << 2 | element_size << 32 | (count as u64) << 35
+}
@@ -0,0 +1,107 @@
+use crate::{
+    format::{self, Compression, Header, Layout, Precision, TimeCodec},
+    reader::ResultsFile,
+    simulation::SimResult,
+    writer::write_serialized,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// Written to the output folder after every file, so a restarted run continues its totals instead of starting from zero
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+/// The pending records alternate between these, so the file of the last checkpoint stays intact until the next one.
+/// They aren't `.bin` files, which would be read as part of the dataset
+const PENDING_FILES: [&str; 2] = ["pending-0.records", "pending-1.records"];
+
+/// The totals of a run and the state of its workers' RNGs when its last file was written
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Checkpoint {
+    /// Seconds simulated to reach the target, over every record written
+    pub total_time: f32,
+    /// Every sample drawn, recorded or not
+    pub num_samples: u64,
+    pub num_files: u64,
+    pub total_records: u64,
+    pub total_uncompressed_size: u64,
+    pub total_compressed_size: u64,
+    /// The `--seed` of the run, the RNG states are only resumed with the same one
+    pub seed: Option<u64>,
+    /// Every worker's RNG state after its last batch, in worker order
+    pub rng_states: Vec<u64>,
+    /// The records every worker of a `--deterministic` run has written, in worker order
+    pub worker_records: Vec<u64>,
+    /// The number of the next `{n}.bin` file in the output folder
+    pub next_file: usize,
+    /// The file of the records already counted in the totals and past the RNG states, but not written to a results file yet,
+    /// like the rest of a batch that didn't fill a file of --records-per-file
+    pub pending_file: Option<String>,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint of `dir`, if a run already wrote one
+    pub fn read_from(dir: &Path) -> io::Result<Option<Self>> {
+        match fs::read(dir.join(CHECKPOINT_FILE)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes `pending` to the pending file of `dir` the checkpoint doesn't refer to yet and refers to that one instead,
+    /// or to none without any records. The records are stored exactly, whatever the options of `header`
+    pub fn set_pending(&mut self, dir: &Path, header: &Header, pending: &[SimResult]) -> io::Result<()> {
+        if pending.is_empty() {
+            self.pending_file = None;
+            return Ok(());
+        }
+
+        let pending_file = if self.pending_file.as_deref() == Some(PENDING_FILES[0]) {
+            PENDING_FILES[1]
+        } else {
+            PENDING_FILES[0]
+        };
+
+        let header = Header {
+            time_codec: TimeCodec::Seconds,
+            layout: Layout::Rows,
+            precision: Precision::F32,
+            compression: Compression::None,
+            ang_vel_scale: 1.,
+            angle_scale: 1.,
+            time_scale: 1.,
+            ..header.clone()
+        };
+        let mut header_bytes = Vec::new();
+        header.write_to(&mut header_bytes)?;
+
+        let records = format::serialize(&header, pending, None);
+        write_serialized(
+            dir.join(pending_file).to_str().unwrap(),
+            &header_bytes,
+            Compression::None,
+            &records,
+            pending.len(),
+        )?;
+        self.pending_file = Some(pending_file.to_string());
+
+        Ok(())
+    }
+
+    /// The records of the pending file of `dir` the checkpoint refers to, empty if it doesn't
+    pub fn read_pending(&self, dir: &Path) -> io::Result<Vec<SimResult>> {
+        match &self.pending_file {
+            Some(pending_file) => Ok(ResultsFile::open(dir.join(pending_file))?.results()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Replaces the checkpoint of `dir`, renaming over the old one so a crash never leaves half of it
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        let path = dir.join(CHECKPOINT_FILE);
+        let tmp_path = dir.join("checkpoint.tmp.json");
+
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, path)
+    }
+}
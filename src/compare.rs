@@ -0,0 +1,117 @@
+use crate::reader;
+use std::{io, path::Path};
+
+/// Counts of values in equal-width buckets starting at 0
+pub struct Histogram {
+    pub bin_width: f32,
+    pub counts: Vec<u64>,
+    pub total: u64,
+}
+
+impl Histogram {
+    pub fn new(values: &[f32], bin_width: f32) -> Self {
+        let mut counts = Vec::new();
+
+        for &value in values {
+            let bin = (value / bin_width) as usize;
+            if bin >= counts.len() {
+                counts.resize(bin + 1, 0);
+            }
+
+            counts[bin] += 1;
+        }
+
+        Self {
+            bin_width,
+            counts,
+            total: values.len() as u64,
+        }
+    }
+
+    /// The fraction of all values in `bin`, 0 past the last bucket
+    pub fn fraction(&self, bin: usize) -> f64 {
+        self.counts.get(bin).map_or(0., |&count| count as f64 / self.total as f64)
+    }
+}
+
+pub fn mean(values: &[f32]) -> f64 {
+    values.iter().map(|&v| f64::from(v)).sum::<f64>() / values.len() as f64
+}
+
+/// The Kolmogorov-Smirnov statistic of two sorted samples,
+/// the largest distance between their empirical cumulative distributions
+pub fn ks_statistic(a: &[f32], b: &[f32]) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut max_distance = 0f64;
+
+    while i < a.len() && j < b.len() {
+        // step past every copy of the smallest value in both samples at once
+        let value = a[i].min(b[j]);
+        while i < a.len() && a[i] <= value {
+            i += 1;
+        }
+        while j < b.len() && b[j] <= value {
+            j += 1;
+        }
+
+        let distance = (i as f64 / a.len() as f64 - j as f64 / b.len() as f64).abs();
+        max_distance = max_distance.max(distance);
+    }
+
+    max_distance
+}
+
+/// Prints how the distribution of the time to reach the target shifted from dataset `a` to dataset `b`
+pub fn compare_datasets(a: &Path, b: &Path, bin_width: f32) -> io::Result<()> {
+    let (mut times_a, version_a) = reader::dataset_times(a)?;
+    let (mut times_b, version_b) = reader::dataset_times(b)?;
+
+    // the dynamics can change between versions, so a shift could come from RocketSim instead of the settings
+    if version_a != version_b {
+        println!(
+            "Warning: the datasets were gathered with different rocketsim_rs versions, {version_a:?} and {version_b:?}"
+        );
+    }
+
+    for (dir, times) in [(a, &times_a), (b, &times_b)] {
+        if times.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no records found in {}", dir.display()),
+            ));
+        }
+    }
+
+    times_a.sort_by(f32::total_cmp);
+    times_b.sort_by(f32::total_cmp);
+
+    let (mean_a, mean_b) = (mean(&times_a), mean(&times_b));
+    println!("A: {} records, mean time {mean_a:.4} s ({})", times_a.len(), a.display());
+    println!("B: {} records, mean time {mean_b:.4} s ({})", times_b.len(), b.display());
+    println!(
+        "Mean shift: {:+.4} s ({:+.2}%)",
+        mean_b - mean_a,
+        (mean_b - mean_a) / mean_a * 100.
+    );
+    println!("KS statistic: {:.4}", ks_statistic(&times_a, &times_b));
+    println!();
+
+    let histogram_a = Histogram::new(&times_a, bin_width);
+    let histogram_b = Histogram::new(&times_b, bin_width);
+
+    println!("{:<15} {:>8} {:>8} {:>8}", "Time (s)", "A", "B", "Delta");
+    for bin in 0..histogram_a.counts.len().max(histogram_b.counts.len()) {
+        let (fraction_a, fraction_b) = (histogram_a.fraction(bin), histogram_b.fraction(bin));
+        let start = bin as f32 * histogram_a.bin_width;
+
+        println!(
+            "{:<15} {:>7.2}% {:>7.2}% {:>+7.2}%",
+            format!("{start:.2}-{:.2}", start + histogram_a.bin_width),
+            fraction_a * 100.,
+            fraction_b * 100.,
+            (fraction_b - fraction_a) * 100.
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,794 @@
+use crate::{
+    controller::PdGains,
+    format::{ByteOrder, Compression, Input, Layout, OutputFormat, Precision, TargetRotation, TimeCodec},
+    normalize::Scaling,
+    sampler::SampledDim,
+    simulation::{ArenaMode, CarPreset, StopCondition, UpReference},
+    CONVERGENCE_THRESHOLD, MAX_ANG_VEL, MAX_SIM_SECONDS, TICK_RATE, VALIDATION_EPSILON,
+};
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use std::{
+    env, f32::consts::PI, ffi::OsString, fs, net::SocketAddr, num::NonZeroUsize, path::Path, path::PathBuf, time::Duration,
+};
+
+/// Shown by `--version`, with the versions that decide the dynamics
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\nrocketsim_rs ",
+    env!("ROCKETSIM_RS_VERSION"),
+    "\ntick rate 120, heavy arenas"
+);
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+#[command(version, long_version = LONG_VERSION, about, args_conflicts_with_subcommands = true)]
+pub struct Config {
+    /// Run a tool instead of gathering data
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Load the options that aren't passed on the command line from this TOML file, keyed by their long names
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Number of worker threads, one per available core by default
+    #[arg(long)]
+    pub threads: Option<NonZeroUsize>,
+    /// Seconds every worker gathers samples for before they're written to the next file
+    #[arg(long, value_name = "SECONDS", default_value_t = 300., value_parser = parse_positive)]
+    pub interval: f32,
+    /// The folder the results files are written to
+    #[arg(long, value_name = "DIR", default_value = "results")]
+    pub out_dir: String,
+    /// Write this run's files to a new `run-{timestamp}` folder in --out-dir, with a manifest.json of its parameters
+    #[arg(long)]
+    pub per_run_dir: bool,
+    /// zstd compression level of the results files
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd_level: i32,
+    /// How the records of the binary results files are compressed
+    #[arg(long, value_enum, default_value_t)]
+    pub compression: Compression,
+    /// Threads zstd compresses every results file with, 0 compresses on the thread receiving the results
+    #[arg(long, default_value_t = 0)]
+    pub zstd_workers: u32,
+    /// Train a zstd dictionary on the first batch and compress every results file with it,
+    /// which shrinks small files
+    #[arg(long, conflicts_with_all = ["wal", "sidecar_metadata"])]
+    pub zstd_dictionary: bool,
+    /// The arena the car flies in, every mode but void needs collision meshes in --assets-dir
+    #[arg(long, value_enum, default_value_t)]
+    pub arena_mode: ArenaMode,
+    /// The folder RocketSim loads the collision meshes from, with a soccar and a hoops folder of .cmf files
+    #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+    pub assets_dir: String,
+    /// The car configs to pick from at random for every sample, with more than one the config's index is stored
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PRESET,...",
+        value_delimiter = ',',
+        default_value = "octane"
+    )]
+    pub car_configs: Vec<CarPreset>,
+    /// Largest magnitude (rad/s) of the sampled initial angular velocity, the scale of --normalize
+    #[arg(long, value_name = "RAD/S", default_value_t = MAX_ANG_VEL, value_parser = parse_positive)]
+    pub max_ang_vel: f32,
+    /// The initial orientation and the target are sampled uniformly in [0, MAX_ANGLE) radians, the scale of --normalize
+    #[arg(long, value_name = "RAD", default_value_t = PI, value_parser = parse_max_angle)]
+    pub max_angle: f32,
+    /// The target is reached once the car's forward axis is within this many radians of it
+    #[arg(long, value_name = "RAD", default_value_t = CONVERGENCE_THRESHOLD, value_parser = parse_positive)]
+    pub convergence_threshold: f32,
+    /// Simulated seconds after which a sample that hasn't reached the target is abandoned, the scale of --normalize
+    #[arg(long, value_name = "SECONDS", default_value_t = MAX_SIM_SECONDS, value_parser = parse_max_sim_seconds)]
+    pub max_sim_seconds: f32,
+    /// Limit the car-relative initial angular velocity (rad/s) about the forward (roll) axis, after sampling its magnitude
+    #[arg(long, value_name = "RAD/S", value_parser = parse_positive)]
+    pub max_rate_x: Option<f32>,
+    /// Limit the car-relative initial angular velocity (rad/s) about the right (pitch) axis
+    #[arg(long, value_name = "RAD/S", value_parser = parse_positive)]
+    pub max_rate_y: Option<f32>,
+    /// Limit the car-relative initial angular velocity (rad/s) about the up (yaw) axis
+    #[arg(long, value_name = "RAD/S", value_parser = parse_positive)]
+    pub max_rate_z: Option<f32>,
+    /// Fix a sampled dimension to a value (rad or rad/s) while randomizing the rest, can be repeated
+    #[arg(long, value_name = "DIM=VALUE", value_parser = parse_fix, allow_hyphen_values = true)]
+    pub fix: Vec<(SampledDim, f32)>,
+    /// Sample the initial orientation and the target around these angles (radians) instead of uniformly
+    #[arg(
+        long,
+        value_name = "PITCH,YAW,ROLL,TARGET_PITCH,TARGET_YAW",
+        value_parser = parse_floats::<5>,
+        allow_hyphen_values = true
+    )]
+    pub nominal: Option<[f32; 5]>,
+    /// Standard deviation (radians) of the Gaussian perturbation of every --nominal angle
+    #[arg(long, default_value_t = 0.05, value_parser = parse_positive, requires = "nominal")]
+    pub perturbation_std: f32,
+    /// Store every field pre-normalized to roughly [-1, 1] using the sampling ranges
+    #[arg(long)]
+    pub normalize: bool,
+    /// Also store the commanded control magnitude and the achieved angular acceleration at a fixed step
+    #[arg(long)]
+    pub record_response: bool,
+    /// Also store the target direction in the car's initial local frame, as seen by the controller
+    #[arg(long)]
+    pub record_local_target: bool,
+    /// Also store the car's initial world-frame orientation, so the `replay` command can simulate the samples again
+    #[arg(long)]
+    pub record_initial_orientation: bool,
+    /// Also store the sum of the step index times the angle to the target over every step, an ITAE-like quality metric
+    #[arg(long)]
+    pub record_itae: bool,
+    /// Also store the car's initial world-frame angular velocity, which the base fields only have car-relative
+    #[arg(long)]
+    pub record_world_ang_vel: bool,
+    /// Also store the target's world-frame pitch and yaw, which the base fields only have relative to the car
+    #[arg(long)]
+    pub record_world_target: bool,
+    /// Also store everything needed to re-project the samples into any frame: the initial orientation,
+    /// the world-frame angular velocity and the world-frame direction to the target
+    #[arg(long)]
+    pub record_world_frame: bool,
+    /// Also store the seed every sample was drawn from, so the `regenerate` command can draw it again
+    #[arg(long)]
+    pub record_seed: bool,
+    /// Leave these base inputs out of every record, e.g. target-roll for models that don't take it
+    #[arg(long, value_name = "INPUT,...", value_enum, value_delimiter = ',')]
+    pub omit_inputs: Vec<Input>,
+    /// How the relative target is stored, `quaternion` and `rotation6d` don't wrap around at ±π like the Euler angles
+    #[arg(long, value_enum, default_value_t)]
+    pub target_rotation: TargetRotation,
+    /// The proportional gain and the pitch, yaw and roll rate scales of a controller, can be repeated.
+    /// With more than one, every sample picks one at random and its index is stored
+    #[arg(long, value_name = "P,PITCH,YAW,ROLL", value_parser = parse_floats::<4>)]
+    pub controller: Vec<[f32; 4]>,
+    /// Also store a difficulty class, the number of these thresholds the time to reach the target is at or above
+    #[arg(long, value_name = "SECONDS,...", value_delimiter = ',', value_parser = parse_positive)]
+    pub difficulty_thresholds: Vec<f32>,
+    /// Write the samples of every difficulty class to their own folder, e.g. results/hard/{n}.bin
+    #[arg(long, requires = "difficulty_thresholds", conflicts_with_all = ["deterministic", "wal"])]
+    pub split_by_difficulty: bool,
+    /// Check physics and result invariants on every sample, logging any violation with its seed
+    #[arg(long)]
+    pub soak: bool,
+    /// Tolerance of the invariant checks done by --soak
+    #[arg(long, default_value_t = VALIDATION_EPSILON, value_parser = parse_positive)]
+    pub validation_epsilon: f32,
+    /// How the results files are written, protobuf needs the protobuf feature
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Write every file's header to a `{n}.json` sidecar and only the uncompressed records to `{n}.bin`, for tools that mmap them
+    #[arg(long)]
+    pub sidecar_metadata: bool,
+    /// Append every file as a length-prefixed chunk to one growing `dataset.bin` instead of writing `{n}.bin` files
+    #[arg(long, conflicts_with_all = ["wal", "sidecar_metadata"])]
+    pub single_file: bool,
+    /// Experimental encoding of the time field, to help zstd compress it
+    #[arg(long, value_enum, default_value_t, conflicts_with = "normalize")]
+    pub time_codec: TimeCodec,
+    /// Byte order of the record fields in the output files
+    #[arg(long, value_enum, default_value_t)]
+    pub byte_order: ByteOrder,
+    /// Also stream every batch of records to the TCP clients connected to this address, e.g. 0.0.0.0:7700
+    #[arg(long, value_name = "ADDR")]
+    pub stream: Option<SocketAddr>,
+    /// Byte order of the record fields sent to the --stream clients, network order by default whatever the host
+    #[arg(long, value_enum, default_value_t = ByteOrder::Big, requires = "stream")]
+    pub stream_byte_order: ByteOrder,
+    /// Arrangement of the records in the output files, `columns` compresses better
+    #[arg(long, value_enum, default_value_t, conflicts_with = "wal")]
+    pub layout: Layout,
+    /// Precision of the angular velocity, the relative target and the time in the output files,
+    /// `f16` and `i16` halve the size of the base fields, `i16` needs --normalize.
+    /// An `f16` time can be a tick off above 15 seconds, see the README
+    #[arg(long, value_enum, default_value_t, requires_if("i16", "normalize"))]
+    pub precision: Precision,
+    /// What "upright" means for the roll controller
+    #[arg(long, value_enum, default_value_t)]
+    pub up_reference: UpReference,
+    /// The world-frame up direction used by `--up-reference body`
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_direction, default_value = "0,0,1", allow_hyphen_values = true)]
+    pub up_direction: [f32; 3],
+    /// When the target counts as reached
+    #[arg(long, value_enum, default_value_t)]
+    pub stop_condition: StopCondition,
+    /// The angular velocity (rad/s) the car has to be below for `--stop-condition settled`
+    #[arg(long, value_name = "RAD/S", default_value_t = 0.5, value_parser = parse_positive)]
+    pub settle_ang_vel: f32,
+    /// Only record samples that reach the target within this many simulated seconds, resampling the rest
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    pub time_budget: Option<f32>,
+    /// Preferentially sample the inputs that are under-represented in this reference dataset folder, to even out its coverage
+    #[arg(long, value_name = "REFERENCE_DIR")]
+    pub fill_gaps: Option<PathBuf>,
+    /// Only record samples slower than this running percentile (0-100) of every sample's time, to gather the hard cases
+    #[arg(long, value_name = "P", value_parser = parse_percentile)]
+    pub record_above_percentile: Option<f32>,
+    /// The number of samples each worker draws to estimate the percentile before recording any with --record-above-percentile
+    #[arg(long, default_value_t = 10_000, requires = "record_above_percentile")]
+    pub percentile_warmup: u64,
+    /// Recreate every worker's arenas after this many samples, to compare against any drift of long-lived arenas
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u64).range(1..))]
+    pub reset_every: Option<u64>,
+    /// Seed the workers' RNGs so the samples each worker draws are reproducible
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Every worker writes files of exactly --records-per-file records to its own folder,
+    /// so with the same seed, thread count and options the output is byte-for-byte reproducible
+    #[arg(long, requires = "seed")]
+    pub deterministic: bool,
+    /// Append every batch to a write-ahead log as soon as it's received,
+    /// so the records of the next file survive a crash and are recovered on the next start
+    #[arg(long, conflicts_with = "deterministic")]
+    pub wal: bool,
+    /// The number of records in each file, 100000 by default with --deterministic,
+    /// otherwise every file holds the results of one --interval
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["wal", "split_by_difficulty"]
+    )]
+    pub records_per_file: Option<u32>,
+    /// Write files of this many bytes of uncompressed records instead of one every --interval
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["records_per_file", "deterministic", "wal", "split_by_difficulty"]
+    )]
+    pub max_file_size: Option<u64>,
+    /// Stop once the dataset in --out-dir holds this many records, writing the last file
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_samples: Option<u64>,
+    /// Stop after running this long, writing the last file, e.g. 90s, 45m, 12h or 2d
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+    /// Log that the process is alive with a timestamp and the number of samples drawn so far every this many seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+    pub heartbeat: Option<f32>,
+    /// Also write the latest status as a line of JSON to this file or named pipe, every time it's printed
+    #[arg(long, value_name = "PATH")]
+    pub status_file: Option<PathBuf>,
+    /// Upload every finished file to this S3 or GCS prefix, e.g. s3://bucket/datasets or gs://bucket/datasets,
+    /// with the credentials of the usual AWS_* or GOOGLE_* environment variables. Needs the upload feature
+    #[arg(long, value_name = "URL", value_parser = parse_upload_url, conflicts_with = "single_file")]
+    pub upload_to: Option<String>,
+    /// Tries at uploading a file and verifying its checksum before giving up and only keeping it locally
+    #[arg(long, value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub upload_attempts: u32,
+    /// Delete every file once its upload to --upload-to is verified
+    #[arg(long, requires = "upload_to")]
+    pub upload_delete_local: bool,
+}
+
+impl Config {
+    /// Parses the command line, with every option it doesn't pass taken from its `SFD_*` environment variable,
+    /// then from the `--config` file, if any
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| e.exit())
+    }
+
+    /// Like [`Config::load`], but returns the error instead of exiting, to reload the `--config` file while running
+    pub fn try_load() -> Result<Self, clap::Error> {
+        let mut args = env::args_os().collect::<Vec<_>>();
+
+        // only to find the config file and which options are already set,
+        // the options needed from the file would fail the full validation
+        let matches = Self::command_with_env().ignore_errors(true).try_get_matches_from(&args)?;
+
+        if let Some(path) = matches.get_one::<PathBuf>("config") {
+            let file_args =
+                config_file_args(path, &matches).map_err(|msg| Self::command().error(ErrorKind::InvalidValue, msg))?;
+
+            // the file only passes the options that aren't set yet, so their order doesn't matter
+            let cli_args = args.split_off(1);
+            args.extend(file_args);
+            args.extend(cli_args);
+        }
+
+        let matches = Self::command_with_env().try_get_matches_from(args)?;
+        Self::from_arg_matches(&matches)
+    }
+
+    /// The command with every option also read from an environment variable,
+    /// `SFD_` then its long name in upper snake case, e.g. `SFD_OUT_DIR` for `--out-dir`
+    fn command_with_env() -> clap::Command {
+        Self::command().mut_args(|arg| match arg.get_long() {
+            Some(long) => {
+                let name = format!("SFD_{}", long.to_uppercase().replace('-', "_"));
+                arg.env(name)
+            }
+            None => arg,
+        })
+    }
+
+    /// The gains of every `--controller`, or only the default ones
+    pub fn controllers(&self) -> Vec<PdGains> {
+        if self.controller.is_empty() {
+            vec![PdGains::default()]
+        } else {
+            self.controller.iter().copied().map(PdGains::from_array).collect()
+        }
+    }
+
+    /// The number of records in each file written by `--deterministic`
+    pub fn deterministic_records_per_file(&self) -> u32 {
+        self.records_per_file.unwrap_or(100_000)
+    }
+
+    /// The `--max-rate-x/y/z` limits, in axis order
+    pub const fn max_rates(&self) -> [Option<f32>; 3] {
+        [self.max_rate_x, self.max_rate_y, self.max_rate_z]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum Command {
+    /// Compare the distributions of the time to reach the target of two dataset folders
+    CompareDatasets {
+        /// The baseline dataset
+        a: PathBuf,
+        /// The dataset compared against the baseline
+        b: PathBuf,
+        /// Width of the histogram buckets, in seconds
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.25, value_parser = parse_positive)]
+        bin_width: f32,
+    },
+    /// Simulate random records of a dataset again from their initial conditions with the controller and dynamics
+    /// they were gathered with, reporting every one whose time doesn't match, to catch drift from this build
+    Validate {
+        /// A results file or a dataset folder, gathered with --record-initial-orientation
+        input: PathBuf,
+        /// Records checked of every file
+        #[arg(long, value_name = "N", default_value_t = 100)]
+        samples: usize,
+        /// Ticks a simulated time can be off from the stored one and still match
+        #[arg(long, value_name = "TICKS", default_value_t = 0)]
+        tolerance_ticks: u32,
+        /// Seed of the choice of records, a random one by default
+        #[arg(long)]
+        seed: Option<u64>,
+        /// The folder RocketSim loads the collision meshes from, for datasets gathered in an arena with collision
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+    /// Simulate a fixed set of seeded samples with the default options and compare their times with a reference file,
+    /// failing if any diverged, to check whether data of this build can be mixed with data of the one that wrote it
+    Regress {
+        /// The reference file, written if it doesn't exist yet
+        #[arg(long, default_value = "golden.json")]
+        reference: PathBuf,
+        /// How many samples to simulate
+        #[arg(long, value_name = "N", default_value_t = 200)]
+        samples: usize,
+        /// The seed of the worker the samples are drawn by
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Write the reference file again with the times of this build
+        #[arg(long)]
+        update: bool,
+        /// The folder RocketSim loads the collision meshes from, the default void arena doesn't need any
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+    /// Print the distributions of the time to reach the target, the angular velocity magnitude and the target angles
+    /// of results files or datasets, with their percentiles and histograms
+    Stats {
+        /// The results files and dataset folders
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Width of the buckets of the time histogram, in seconds
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.25, value_parser = parse_positive)]
+        bin_width: f32,
+    },
+    /// Write the mean, standard deviation, min and max of every value but the ids and classes of results files or datasets
+    /// to a normalization.json, and optionally a copy of the values scaled by them
+    Normalize {
+        /// The results files and dataset folders
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The normalization statistics
+        #[arg(long, short, default_value = "normalization.json")]
+        output: PathBuf,
+        /// Also write the scaled values of every input file to {n}.npy in this folder
+        #[arg(long, value_name = "DIR")]
+        scaled: Option<PathBuf>,
+        /// How the --scaled values are scaled
+        #[arg(long, value_enum, default_value_t, requires = "scaled")]
+        scaling: Scaling,
+    },
+    /// Print how evenly the records of a dataset cover the grid of --fill-gaps over the inputs,
+    /// and the cells and ranges of the inputs with the fewest records
+    Coverage {
+        /// The dataset folder
+        dataset: PathBuf,
+        /// Report the cells with fewer records than this fraction of the mean count
+        #[arg(long, value_name = "FRACTION", default_value_t = 0.25, value_parser = parse_positive)]
+        min_fraction: f32,
+        /// How many of the least covered cells to print
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        top: usize,
+    },
+    /// Write the records whose time to reach the target is far outside that of the records with about the same inputs
+    /// to a CSV file, with their seeds to simulate them again
+    Outliers {
+        /// The results files and dataset folders to check
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The CSV file of the outliers
+        #[arg(long, short, default_value = "outliers.csv")]
+        output: PathBuf,
+        /// How many interquartile ranges outside the quartiles of its bucket a time has to be to be an outlier
+        #[arg(long, value_name = "K", default_value_t = 3., value_parser = parse_positive)]
+        iqr_factor: f32,
+    },
+    /// Simulate every sample of a dataset again with other controller gains, writing the old and new times to a CSV file
+    Replay {
+        /// The dataset, gathered with --record-initial-orientation
+        dataset: PathBuf,
+        /// The proportional gain, then the scales the pitch, yaw and roll rates are divided by
+        #[arg(
+            long,
+            value_name = "P,PITCH,YAW,ROLL",
+            value_parser = parse_floats::<4>,
+            default_value = "35,3.4,5,3.1"
+        )]
+        gains: [f32; 4],
+        /// Where the paired times are written
+        #[arg(long, short, value_name = "FILE", default_value = "replay.csv")]
+        output: PathBuf,
+        /// The folder RocketSim loads the collision meshes from, for datasets gathered in an arena with collision
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+    /// Print the name, type, unit, frame and scale of every value of the records of a dataset, for each schema it has
+    Schema {
+        /// The dataset folder
+        dataset: PathBuf,
+    },
+    /// Write the results files of a dataset again in another output format, next to them
+    Convert {
+        /// A results file, or a dataset folder to convert every file of
+        input: PathBuf,
+        /// The output format to convert to
+        #[arg(long, value_enum)]
+        to: OutputFormat,
+    },
+    /// Print the record count, format version, compression ratio and the min, max, mean and standard deviation
+    /// of every value of a results file, or of every chunk of a dataset.bin
+    Inspect {
+        /// The results file
+        file: PathBuf,
+    },
+    /// Bundle a dataset folder into one zstd-compressed tar archive, with an index.json of its files, their checksums
+    /// and its manifest
+    Pack {
+        /// The dataset folder
+        dataset: PathBuf,
+        /// The archive, `{DATASET}.tar.zst` by default
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Extract an archive written by pack, checking every file against the checksum in its index
+    Unpack {
+        /// The archive
+        archive: PathBuf,
+        /// The folder the dataset is extracted to, which must not exist yet
+        #[arg(long, short, value_name = "DIR")]
+        output: PathBuf,
+    },
+    /// Decompress every results file of a dataset folder, checking its record count and checksum,
+    /// and print the files that are corrupt or truncated
+    Verify {
+        /// The dataset folder
+        dataset: PathBuf,
+    },
+    /// Concatenate the records of results files with the same schema and options into one file
+    Merge {
+        /// The results files and dataset folders to merge, in order
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The merged file
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Concatenate the records of results files with the same schema and options into one file,
+    /// dropping the records whose inputs are near those of an earlier record
+    Dedup {
+        /// The results files and dataset folders to deduplicate, in order
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The deduplicated file
+        #[arg(long, short)]
+        output: PathBuf,
+        /// How far apart, in rad/s and rad, every input of two records can be for the later one to be dropped
+        #[arg(long, default_value_t = 1e-3, value_parser = parse_positive)]
+        epsilon: f32,
+    },
+    /// Resample the records of results files with the same schema and options into one file,
+    /// with their times to reach the target spread uniformly over buckets
+    Rebalance {
+        /// The results files and dataset folders to rebalance
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The rebalanced file
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Width of the buckets the times are spread over, in seconds
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.25, value_parser = parse_positive)]
+        bin_width: f32,
+        /// Drop the records slower than this, the slowest record by default
+        #[arg(long, value_name = "SECONDS", value_parser = parse_positive)]
+        max_time: Option<f32>,
+        /// How many records to draw, as many as the inputs hold by default
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        records: Option<u64>,
+        /// Seed of the draws, a random one by default
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Write the records of results files with the same schema and options to new files in a random order,
+    /// shuffling on disk so the dataset never has to fit in memory
+    Shuffle {
+        /// The results files and dataset folders to shuffle
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The empty folder the shuffled files are written to
+        #[arg(long, short, value_name = "DIR")]
+        output: PathBuf,
+        /// About how many records every shuffled file holds, each is shuffled in memory
+        #[arg(long, value_name = "N", default_value_t = 1_000_000, value_parser = clap::value_parser!(u32).range(1..))]
+        records_per_file: u32,
+        /// Seed of the order, a random one by default
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Partition the records of results files into train, val and test folders, or K folds,
+    /// with a split.json describing them
+    Split {
+        /// The results files and dataset folders to split
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// The folder the train, val and test folders are written to
+        #[arg(long, short, value_name = "DIR")]
+        output: PathBuf,
+        /// The fractions of the records for train, val and test, scaled to add up to 1
+        #[arg(long, value_name = "TRAIN,VAL,TEST", value_parser = parse_ratios, default_value = "0.8,0.1,0.1")]
+        ratios: [f32; 3],
+        /// Assign every record to one of this many folds for cross-validation instead, by a hash of its seed
+        /// with --record-seed or of the whole record, writing a fold-{i}.json of the files to train and validate on for each
+        #[arg(
+            long,
+            value_name = "K",
+            value_parser = clap::value_parser!(u32).range(2..),
+            conflicts_with = "ratios"
+        )]
+        kfold: Option<u32>,
+        /// Seed of the partitions, the same seed and inputs always give the same ones
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Upgrade the headerless files of 7 little-endian f32 records written by the first versions
+    /// to the current format in place, with a header of placeholder metadata and a footer
+    Migrate {
+        /// The dataset folder
+        dataset: PathBuf,
+    },
+    /// Simulate one sample step by step, writing the orientation, angular velocity, controls and angle to the target
+    /// of every step as CSV
+    Trace {
+        /// Sample with the options and dynamics of this dataset instead of the defaults
+        #[arg(long)]
+        dataset: Option<PathBuf>,
+        /// Draw the sample like a worker seeded with this, as stored by --record-seed
+        #[arg(long, required_unless_present = "start")]
+        seed: Option<u64>,
+        /// Start from this world-frame angular velocity (rad/s), orientation and target pitch and yaw (rad) instead
+        #[arg(
+            long,
+            value_name = "X,Y,Z,PITCH,YAW,ROLL,TARGET_PITCH,TARGET_YAW",
+            value_parser = parse_floats::<8>,
+            allow_hyphen_values = true
+        )]
+        start: Option<[f32; 8]>,
+        /// The CSV file, printed by default
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// The folder RocketSim loads the collision meshes from, for datasets gathered in an arena with collision
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+    /// Simulate the records of a lost or corrupted worker-{id}/{n}.bin file of a --deterministic run again
+    /// and write it, or check an intact one against them
+    Reproduce {
+        /// The file, in a worker folder of the run's dataset folder
+        file: PathBuf,
+        /// The folder RocketSim loads the collision meshes from, for datasets gathered in an arena with collision
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+    /// Draw and simulate a sample of a dataset again from the seed in its record, printing it
+    Regenerate {
+        /// The dataset the sample is from, whose metadata has the options it was drawn with
+        dataset: PathBuf,
+        /// The seed of the sample, stored by --record-seed
+        seed: u64,
+        /// The folder RocketSim loads the collision meshes from, for datasets gathered in an arena with collision
+        #[arg(long, value_name = "DIR", default_value = "collision_meshes")]
+        assets_dir: String,
+    },
+}
+
+/// The options in the TOML file at `path` that aren't already set in `matches`, as command line arguments
+fn config_file_args(path: &Path, matches: &clap::ArgMatches) -> Result<Vec<OsString>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {e}", path.display()))?;
+    let table = text
+        .parse::<toml::Table>()
+        .map_err(|e| format!("invalid config file {}: {e}", path.display()))?;
+
+    let command = Config::command();
+    let mut args = Vec::new();
+
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()) && arg.get_id() != "config")
+            .ok_or_else(|| format!("unknown option {key:?} in {}", path.display()))?;
+
+        // the command line and the environment take precedence over the file
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        if !arg.get_action().takes_values() {
+            match value {
+                toml::Value::Boolean(true) => args.push(format!("--{long}").into()),
+                toml::Value::Boolean(false) => {}
+                _ => return Err(format!("{key} in {} must be true or false", path.display())),
+            }
+
+            continue;
+        }
+
+        // every element of an array is one occurrence of a repeatable option, e.g. --fix,
+        // while the array of any other option is its comma separated value, e.g. --nominal
+        let values = match value {
+            toml::Value::Array(values) if matches!(arg.get_action(), ArgAction::Append) => values,
+            value => vec![value],
+        };
+
+        for value in &values {
+            let value =
+                config_file_value(value).ok_or_else(|| format!("unsupported value of {key} in {}", path.display()))?;
+            args.push(format!("--{long}={value}").into());
+        }
+    }
+
+    Ok(args)
+}
+
+fn config_file_value(value: &toml::Value) -> Option<String> {
+    Some(match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Array(values) => values.iter().map(config_file_value).collect::<Option<Vec<_>>>()?.join(","),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => return None,
+    })
+}
+
+fn parse_floats<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|e| format!("invalid number {v:?}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    values
+        .try_into()
+        .map_err(|values: Vec<f32>| format!("expected {N} comma separated numbers, got {}", values.len()))
+}
+
+fn parse_fix(s: &str) -> Result<(SampledDim, f32), String> {
+    let (dim, value) = s.split_once('=').ok_or("expected DIM=VALUE")?;
+    let dim = SampledDim::from_str(dim.trim(), true)?;
+    let value = value
+        .trim()
+        .parse::<f32>()
+        .map_err(|e| format!("invalid number {value:?}: {e}"))?;
+
+    Ok((dim, value))
+}
+
+fn parse_direction(s: &str) -> Result<[f32; 3], String> {
+    let direction = parse_floats::<3>(s)?;
+
+    if direction.iter().all(|&v| v == 0.) {
+        return Err("the direction can't be zero".to_string());
+    }
+
+    Ok(direction)
+}
+
+fn parse_percentile(s: &str) -> Result<f32, String> {
+    let value = s.parse::<f32>().map_err(|e| e.to_string())?;
+
+    if !(value > 0. && value < 100.) {
+        return Err("must be between 0 and 100".to_string());
+    }
+
+    Ok(value)
+}
+
+fn parse_max_angle(s: &str) -> Result<f32, String> {
+    let value = s.parse::<f32>().map_err(|e| e.to_string())?;
+
+    if !(value > 0. && value <= PI) {
+        return Err("must be above 0 and at most pi".to_string());
+    }
+
+    Ok(value)
+}
+
+fn parse_max_sim_seconds(s: &str) -> Result<f32, String> {
+    let value = parse_positive(s)?;
+
+    // --time-codec ticks16 stores the ticks of any sample in a u16
+    let max_seconds = (f32::from(u16::MAX) - 1.) / TICK_RATE;
+    if value > max_seconds {
+        return Err(format!("must be at most {max_seconds} seconds"));
+    }
+
+    Ok(value)
+}
+
+fn parse_ratios(s: &str) -> Result<[f32; 3], String> {
+    let ratios = parse_floats::<3>(s)?;
+
+    if ratios.iter().any(|ratio| *ratio < 0. || !ratio.is_finite()) || ratios.iter().sum::<f32>() <= 0. {
+        return Err("the ratios must be non-negative, and not all 0".to_string());
+    }
+
+    Ok(ratios)
+}
+
+fn parse_upload_url(s: &str) -> Result<String, String> {
+    let bucket = s
+        .strip_prefix("s3://")
+        .or_else(|| s.strip_prefix("gs://"))
+        .ok_or("expected an s3:// or gs:// URL")?;
+
+    if bucket.split('/').next().unwrap_or_default().is_empty() {
+        return Err("the URL has no bucket".to_string());
+    }
+
+    Ok(s.to_string())
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (value, unit_seconds) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1.),
+        Some((i, 'm')) => (&s[..i], 60.),
+        Some((i, 'h')) => (&s[..i], 3600.),
+        Some((i, 'd')) => (&s[..i], 86400.),
+        _ => (s, 1.),
+    };
+
+    Ok(Duration::from_secs_f64(f64::from(parse_positive(value)?) * unit_seconds))
+}
+
+fn parse_positive(s: &str) -> Result<f32, String> {
+    let value = s.parse::<f32>().map_err(|e| e.to_string())?;
+
+    if value <= 0. || !value.is_finite() {
+        return Err("must be a positive number".to_string());
+    }
+
+    Ok(value)
+}
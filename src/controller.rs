@@ -0,0 +1,67 @@
+use rocketsim_rs::{glam_ext::glam::Vec3A, math::Angle, sim::CarControls};
+use serde::{Deserialize, Serialize};
+
+/// The gains of the PD controller that steers the car towards the target
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PdGains {
+    /// Multiplies the sum of the angle and the scaled rate of every axis
+    proportional: f32,
+    /// The angular velocity of each axis is divided by its scale before being added to the angle
+    pitch_rate_scale: f32,
+    yaw_rate_scale: f32,
+    roll_rate_scale: f32,
+}
+
+impl Default for PdGains {
+    fn default() -> Self {
+        Self {
+            proportional: 35.,
+            pitch_rate_scale: 3.4,
+            yaw_rate_scale: 5.0,
+            roll_rate_scale: 3.1,
+        }
+    }
+}
+
+impl PdGains {
+    /// From the proportional gain and the pitch, yaw and roll rate scales
+    pub const fn from_array([proportional, pitch_rate_scale, yaw_rate_scale, roll_rate_scale]: [f32; 4]) -> Self {
+        Self {
+            proportional,
+            pitch_rate_scale,
+            yaw_rate_scale,
+            roll_rate_scale,
+        }
+    }
+}
+
+fn control_pd(angle: f32, rate: f32, proportional: f32) -> f32 {
+    ((proportional * (angle + rate)).powi(3) / 10.).clamp(-1., 1.)
+}
+
+pub fn default_pd(local_target: Vec3A, local_ang_vel: Vec3A, local_up: Vec3A, gains: PdGains) -> CarControls {
+    let target_angles = Angle {
+        pitch: local_target.z.atan2(local_target.x),
+        yaw: local_target.y.atan2(local_target.x),
+        roll: local_up.y.atan2(local_up.z),
+    };
+
+    let pitch = control_pd(
+        target_angles.pitch,
+        local_ang_vel.y / gains.pitch_rate_scale,
+        gains.proportional,
+    );
+    let yaw = control_pd(target_angles.yaw, -local_ang_vel.z / gains.yaw_rate_scale, gains.proportional);
+    let roll = control_pd(
+        target_angles.roll,
+        local_ang_vel.x / gains.roll_rate_scale,
+        gains.proportional,
+    );
+
+    CarControls {
+        pitch,
+        yaw,
+        roll,
+        ..Default::default()
+    }
+}
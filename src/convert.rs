@@ -0,0 +1,69 @@
+use crate::{
+    config::Config,
+    format::{ByteOrder, Compression, Layout, OutputFormat, Precision, TargetRotation, TimeCodec},
+    reader::{self, DatasetFile},
+    writer::write_file,
+};
+use clap::Parser;
+use std::{io, path::Path};
+
+/// Writes every results file at `input`, a file or a dataset folder, again in `format` next to it,
+/// as `{n}.{extension}`, or `dataset-{offset}.{extension}` for the chunks of a `dataset.bin`
+pub fn convert(input: &Path, format: OutputFormat) -> io::Result<()> {
+    if format == OutputFormat::Binary {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the results files are already --format binary",
+        ));
+    }
+
+    let files = reader::files_or_dataset(input)?;
+
+    let mut config = Config::parse_from([env!("CARGO_PKG_NAME")]);
+    config.format = format;
+
+    // their schemas hold the Euler angles
+    #[cfg(feature = "protobuf")]
+    let euler_only = matches!(format, OutputFormat::Protobuf | OutputFormat::Capnp);
+    #[cfg(not(feature = "protobuf"))]
+    let euler_only = format == OutputFormat::Capnp;
+
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+        let mut header = file.header.clone();
+        if euler_only && header.target_rotation != TargetRotation::Euler {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{dataset_file} has a --target-rotation that --format protobuf and capnp can't hold"),
+            ));
+        }
+
+        // the other formats always hold the values as f32s, with the time in seconds
+        header.time_codec = TimeCodec::Seconds;
+        header.byte_order = ByteOrder::Little;
+        header.layout = Layout::Rows;
+        header.precision = Precision::F32;
+        header.compression = Compression::Zstd;
+        header.metadata.zstd_dictionary_id = None;
+
+        let output = match dataset_file {
+            DatasetFile::File(path) => path.with_extension(format.extension()),
+            DatasetFile::Chunk { path, offset, .. } => {
+                path.with_file_name(format!("dataset-{offset}.{}", format.extension()))
+            }
+        };
+        let output = output.to_string_lossy();
+
+        let results = file.results();
+        let (uncompressed_size, size) = write_file(&output, &config, &header, &results)?;
+        println!(
+            "Converted {dataset_file} to {output}: {} records, {uncompressed_size} -> {size} bytes",
+            results.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::gaps::{self, OccupancyGrid};
+use std::{io, path::Path};
+
+/// Prints how the records of the dataset folder `dir` cover the cells of the `--fill-gaps` grid over the
+/// car-relative initial angular velocity and the relative target angles: the spread of the counts, the least covered
+/// range of every input, and the `top` cells with the fewest records among those with under `min_fraction` of the mean count
+pub fn print_coverage(dir: &Path, min_fraction: f32, top: usize) -> io::Result<()> {
+    let (grid, reference) = OccupancyGrid::from_dataset(dir)?;
+    let counts = grid.counts();
+
+    let mean = reference.num_records as f32 / counts.len() as f32;
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let percentile = |p: f32| sorted[((sorted.len() - 1) as f32 * p).round() as usize];
+
+    println!(
+        "{} records from {} files in {} cells, {} of them empty",
+        reference.num_records,
+        reference.num_files,
+        counts.len(),
+        counts.iter().filter(|&&count| count == 0).count()
+    );
+    println!(
+        "Records per cell: mean {mean:.2}, p10 {}, p50 {}, p90 {}, max {}",
+        percentile(0.1),
+        percentile(0.5),
+        percentile(0.9),
+        sorted[sorted.len() - 1]
+    );
+    println!();
+
+    // the records of every range of every input, whatever the other inputs
+    println!("Least covered range of every input:");
+    let mut marginals = [[0u64; gaps::BINS]; 6];
+    for (cell, &count) in counts.iter().enumerate() {
+        for (dim, bin) in gaps::cell_bins(cell).into_iter().enumerate() {
+            marginals[dim][bin] += u64::from(count);
+        }
+    }
+    for (dim, ((name, unit), bins)) in gaps::DIMENSIONS.into_iter().zip(marginals).enumerate() {
+        let (bin, &count) = bins.iter().enumerate().min_by_key(|&(_, count)| count).unwrap();
+        let (min, max) = gaps::bin_range(dim, bin);
+
+        println!(
+            "  {name:<13} {min:>6.2} to {max:<6.2} {unit:<6} {:>6.2}% of the records",
+            count as f64 / reference.num_records as f64 * 100.
+        );
+    }
+    println!();
+
+    let threshold = mean * min_fraction;
+    let mut under_covered = (0..counts.len())
+        .filter(|&cell| (counts[cell] as f32) < threshold)
+        .collect::<Vec<_>>();
+    under_covered.sort_by_key(|&cell| counts[cell]);
+
+    println!(
+        "{} cells have fewer than {threshold:.2} records, {min_fraction} of the mean",
+        under_covered.len()
+    );
+    for &cell in under_covered.iter().take(top) {
+        let ranges = gaps::cell_bins(cell)
+            .into_iter()
+            .zip(gaps::DIMENSIONS)
+            .enumerate()
+            .map(|(dim, (bin, (name, _)))| {
+                let (min, max) = gaps::bin_range(dim, bin);
+                format!("{name} {min:.2} to {max:.2}")
+            })
+            .collect::<Vec<_>>();
+
+        println!("  {:>6} records: {}", counts[cell], ranges.join(", "));
+    }
+
+    if !under_covered.is_empty() {
+        println!();
+        println!(
+            "Gathering with --fill-gaps {} samples the under-covered cells more",
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
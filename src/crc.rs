@@ -0,0 +1,93 @@
+/// The CRC-32 of zip
+pub const IEEE: &CrcTable = &CrcTable::new(0xEDB8_8320);
+/// The CRC-32C of TFRecord and the results files
+pub const CASTAGNOLI: &CrcTable = &CrcTable::new(0x82F6_3B78);
+
+/// The lookup table of a reflected CRC-32 with a reversed polynomial, built at compile time
+pub struct CrcTable([u32; 256]);
+
+impl CrcTable {
+    const fn new(polynomial: u32) -> Self {
+        let mut table = [0; 256];
+        let mut i = 0;
+
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+
+            while bit < 8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ polynomial } else { crc >> 1 };
+                bit += 1;
+            }
+
+            table[i] = crc;
+            i += 1;
+        }
+
+        Self(table)
+    }
+}
+
+/// A reflected CRC-32 of data given in parts
+pub struct Crc32 {
+    table: &'static CrcTable,
+    crc: u32,
+}
+
+impl Crc32 {
+    pub const fn new(table: &'static CrcTable) -> Self {
+        Self { table, crc: !0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc = data.iter().fold(self.crc, |crc, &byte| {
+            self.table.0[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+        });
+    }
+
+    /// The CRC of every part so far
+    pub const fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+/// The reflected CRC-32 of `data` with the polynomial of `table`
+pub fn crc32(table: &'static CrcTable, data: &[u8]) -> u32 {
+    let mut crc = Crc32::new(table);
+    crc.update(data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The standard check input of the CRC catalogues
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn castagnoli_check_value() {
+        assert_eq!(crc32(CASTAGNOLI, CHECK_INPUT), 0xE306_9283);
+    }
+
+    #[test]
+    fn ieee_check_value() {
+        assert_eq!(crc32(IEEE, CHECK_INPUT), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn empty_data() {
+        assert_eq!(crc32(CASTAGNOLI, &[]), 0);
+        assert_eq!(crc32(IEEE, &[]), 0);
+    }
+
+    #[test]
+    fn parts_match_the_whole() {
+        let mut crc = Crc32::new(CASTAGNOLI);
+        crc.update(&CHECK_INPUT[..4]);
+        crc.update(&[]);
+        crc.update(&CHECK_INPUT[4..]);
+
+        assert_eq!(crc.finish(), crc32(CASTAGNOLI, CHECK_INPUT));
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{format::Header, simulation::SimResult, table};
+use std::fmt::Write;
+
+/// A header row and a row for every result, with the columns of `table::columns`
+pub fn serialize(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let columns = table::columns(header);
+    let mut csv = columns.iter().map(|column| column.name).collect::<Vec<_>>().join(",");
+    csv.push('\n');
+
+    for result in results {
+        for (i, (column, value)) in columns.iter().zip(table::row(header, result)).enumerate() {
+            if i != 0 {
+                csv.push(',');
+            }
+
+            // floats are written in the shortest form that parses back to the same f32
+            if column.integer {
+                write!(csv, "{}", value as i32).unwrap();
+            } else {
+                write!(csv, "{value}").unwrap();
+            }
+        }
+
+        csv.push('\n');
+    }
+
+    csv.into_bytes()
+}
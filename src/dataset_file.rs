@@ -0,0 +1,148 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The file `--single-file` appends every batch to, in every folder the numbered files would be written to
+pub const DATASET_FILE: &str = "dataset.bin";
+/// The offset and the length of every chunk of the `DATASET_FILE` next to it, rebuilt after every append
+const INDEX_EXTENSION: &str = "idx";
+
+/// Appends `chunk`, a complete results file, to the dataset file at `path` prefixed with its length,
+/// dropping a chunk a crash cut short first, then rewrites the index
+pub fn append(path: &Path, chunk: &[u8]) -> io::Result<()> {
+    let mut chunks = match fs::File::open(path) {
+        Ok(mut file) => indexed_chunks(path, &mut file)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let end = chunks.last().map_or(0, |&(offset, len)| offset + len);
+
+    let mut file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    file.set_len(end)?;
+    file.seek(SeekFrom::Start(end))?;
+    file.write_all(&(chunk.len() as u64).to_le_bytes())?;
+    file.write_all(chunk)?;
+    file.sync_all()?;
+
+    chunks.push((end + 8, chunk.len() as u64));
+    write_index(path, &chunks)
+}
+
+/// The offset and the length of every complete chunk of the dataset file at `path`, in order
+pub fn chunks(path: &Path) -> io::Result<Vec<(u64, u64)>> {
+    indexed_chunks(path, &mut fs::File::open(path)?)
+}
+
+/// The chunks of the index next to the dataset file at `path`, followed by any a crash kept out of it,
+/// or every chunk found by following the length prefixes if the index is missing or doesn't match `file`
+fn indexed_chunks(path: &Path, file: &mut fs::File) -> io::Result<Vec<(u64, u64)>> {
+    let mut chunks = match read_index(path, file) {
+        Ok(chunks) => chunks,
+        Err(e) if e.kind() == io::ErrorKind::NotFound || e.kind() == io::ErrorKind::InvalidData => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let end = chunks.last().map_or(0, |&(offset, len)| offset + len);
+    chunks.extend(scan(file, end)?);
+
+    Ok(chunks)
+}
+
+/// The chunks of the index next to the dataset file at `path`, checked against the length prefixes they should follow
+fn read_index(path: &Path, file: &mut fs::File) -> io::Result<Vec<(u64, u64)>> {
+    let index = fs::read(path.with_extension(INDEX_EXTENSION))?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "the index doesn't match the dataset file");
+
+    if index.len() % 16 != 0 {
+        return Err(invalid());
+    }
+
+    let chunks = index
+        .chunks_exact(16)
+        .map(|entry| {
+            (
+                u64::from_le_bytes(entry[..8].try_into().unwrap()),
+                u64::from_le_bytes(entry[8..].try_into().unwrap()),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // every chunk right after the length prefix that follows the one before it
+    let mut end = 0;
+    for &(offset, len) in &chunks {
+        if offset != end + 8 {
+            return Err(invalid());
+        }
+        end = offset.checked_add(len).ok_or_else(invalid)?;
+    }
+
+    if end > file.metadata()?.len() {
+        return Err(invalid());
+    }
+
+    // a dataset file replaced since the index was written is unlikely to have the same last length prefix
+    if let Some(&(offset, len)) = chunks.last() {
+        let mut prefix = [0; 8];
+        file.seek(SeekFrom::Start(offset - 8))?;
+        file.read_exact(&mut prefix)?;
+
+        if u64::from_le_bytes(prefix) != len {
+            return Err(invalid());
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Reads the chunk at `offset` of the dataset file at `path`
+pub fn read_chunk(path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut chunk = vec![0; len as usize];
+    file.read_exact(&mut chunk)?;
+
+    Ok(chunk)
+}
+
+/// Follows the length prefixes from `offset` of `file`, stopping at the end or at a chunk that was cut short
+fn scan(file: &mut fs::File, mut offset: u64) -> io::Result<Vec<(u64, u64)>> {
+    let file_len = file.metadata()?.len();
+    let mut chunks = Vec::new();
+
+    while offset + 8 <= file_len {
+        let mut len = [0; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+
+        // a torn or garbage length can overflow
+        let Some(end) = offset
+            .checked_add(8)
+            .and_then(|start| start.checked_add(len))
+            .filter(|&end| end <= file_len)
+        else {
+            break;
+        };
+
+        chunks.push((offset + 8, len));
+        offset = end;
+    }
+
+    Ok(chunks)
+}
+
+/// Writes the index next to the dataset file at `path`, a little-endian `u64` offset and length of every chunk
+fn write_index(path: &Path, chunks: &[(u64, u64)]) -> io::Result<()> {
+    let index = chunks
+        .iter()
+        .flat_map(|&(offset, len)| [offset, len])
+        .flat_map(u64::to_le_bytes)
+        .collect::<Vec<_>>();
+
+    let tmp_path = path.with_extension("idx.tmp");
+    fs::write(&tmp_path, index)?;
+    fs::rename(tmp_path, path.with_extension(INDEX_EXTENSION))
+}
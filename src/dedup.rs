@@ -0,0 +1,101 @@
+use crate::{
+    format::TimeCodec,
+    merge::{self, MergedHeader},
+    writer::write_serialized,
+};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The car-relative initial angular velocity and the relative target angles of a record
+type InputVector = [f32; 6];
+
+/// Writes the records of every results file in `inputs`, or in the dataset folders among them, to one file at `output`,
+/// dropping every record whose inputs are all within `epsilon` of those of a record kept before it.
+///
+/// The kept inputs are bucketed in a grid of cells `epsilon` wide, so only the records in the cells around a record
+/// are compared with it
+pub fn dedup(inputs: &[PathBuf], output: &Path, epsilon: f32) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+
+    let mut merged = MergedHeader::default();
+    let mut records = Vec::new();
+    let mut cells: HashMap<[i64; 6], Vec<InputVector>> = HashMap::new();
+    let mut num_records = 0;
+
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        merged.add(dataset_file, &file)?;
+
+        let file_inputs = file.inputs();
+        let record_size = file.header.record_size();
+        let mut file_records = file.records;
+        // a delta only means something after the record before it, which can be dropped
+        if file.header.time_codec == TimeCodec::DeltaTicks {
+            merge::absolute_ticks(&file.header, &mut file_records);
+        }
+
+        for (record, (ang_vel, target)) in file_records.chunks_exact(record_size).zip(file_inputs) {
+            num_records += 1;
+
+            let vector = [ang_vel.x, ang_vel.y, ang_vel.z, target.pitch, target.yaw, target.roll];
+            let cell = vector.map(|value| (value / epsilon).floor() as i64);
+            if has_neighbor(&cells, cell, &vector, epsilon) {
+                continue;
+            }
+
+            cells.entry(cell).or_default().push(vector);
+            records.extend_from_slice(record);
+        }
+    }
+
+    let Some(mut header) = merged.finish() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no results files to deduplicate"));
+    };
+    if header.time_codec == TimeCodec::DeltaTicks {
+        header.time_codec = TimeCodec::Ticks;
+    }
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    let num_kept = records.len() / header.record_size();
+    write_serialized(
+        &output.to_string_lossy(),
+        &header_bytes,
+        header.compression,
+        &records,
+        num_kept,
+    )?;
+
+    println!(
+        "Removed {} of {num_records} records from {} files within {epsilon} of another, wrote {num_kept} to {}",
+        num_records - num_kept,
+        files.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Whether any input vector in `cell` or the cells around it is within `epsilon` of `vector` in every value
+fn has_neighbor(cells: &HashMap<[i64; 6], Vec<InputVector>>, cell: [i64; 6], vector: &InputVector, epsilon: f32) -> bool {
+    // every combination of -1, 0 and 1 for the 6 values
+    (0..3usize.pow(6)).any(|combination| {
+        let mut neighbor = cell;
+        let mut rest = combination;
+        for value in &mut neighbor {
+            *value += (rest % 3) as i64 - 1;
+            rest /= 3;
+        }
+
+        cells.get(&neighbor).is_some_and(|kept| {
+            kept.iter()
+                .any(|kept| kept.iter().zip(vector).all(|(a, b)| (a - b).abs() <= epsilon))
+        })
+    })
+}
@@ -0,0 +1,142 @@
+/// The bits of the IEEE 754 half-precision float nearest to `value`, rounding ties to even, infinite if it's too large
+pub fn from_f32(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16) as u16 & 0x8000;
+    let exponent = (bits >> 23 & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // infinity, or a NaN that stays one
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+
+    let half_exponent = exponent - 127 + 15;
+
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // the mantissa with the implicit bit, and how far it's shifted right to become the half's mantissa
+    let (mantissa, shift) = if half_exponent > 0 {
+        (mantissa, 13)
+    } else if half_exponent >= -10 {
+        (mantissa | 0x80_0000, (14 - half_exponent) as u32)
+    } else {
+        return sign;
+    };
+
+    let half = if half_exponent > 0 { (half_exponent as u32) << 10 } else { 0 } | mantissa >> shift;
+    let remainder = mantissa & ((1 << shift) - 1);
+    let midpoint = 1 << (shift - 1);
+
+    // rounding up can carry into the exponent, up to infinity, which is still the nearest half float
+    let round_up = remainder > midpoint || (remainder == midpoint && half & 1 == 1);
+
+    sign | (half + u32::from(round_up)) as u16
+}
+
+/// The exact value of the half float `bits`
+pub fn to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from(bits >> 10 & 0x1f);
+    let mantissa = u32::from(bits & 0x3ff);
+
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            if sign == 0 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | mantissa << 13),
+        _ => f32::from_bits(sign | (exponent + 127 - 15) << 23 | mantissa << 13),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_half_float_round_trips() {
+        for bits in 0..=u16::MAX {
+            let value = to_f32(bits);
+
+            if value.is_nan() {
+                assert!(to_f32(from_f32(value)).is_nan(), "{bits:#06x} isn't a NaN anymore");
+            } else {
+                assert_eq!(from_f32(value), bits, "{bits:#06x} ({value}) doesn't round trip");
+            }
+        }
+    }
+
+    #[test]
+    fn normal_values() {
+        assert_eq!(from_f32(1.), 0x3c00);
+        assert_eq!(from_f32(-2.), 0xc000);
+        assert_eq!(from_f32(0.5), 0x3800);
+        assert_eq!(from_f32(65504.), 0x7bff);
+        assert_eq!(from_f32(-0.), 0x8000);
+        assert_eq!(to_f32(0x3555), 0.333_251_95);
+    }
+
+    #[test]
+    fn subnormals() {
+        let smallest = 2f32.powi(-24);
+
+        assert_eq!(from_f32(smallest), 0x0001);
+        assert_eq!(from_f32(1023. * smallest), 0x03ff);
+        assert_eq!(from_f32(-3. * smallest), 0x8003);
+        assert_eq!(to_f32(0x0001), smallest);
+        assert_eq!(to_f32(0x83ff), -1023. * smallest);
+        // the smallest normal, right above the largest subnormal
+        assert_eq!(from_f32(2f32.powi(-14)), 0x0400);
+        // too small for even the smallest subnormal
+        assert_eq!(from_f32(1e-10), 0x0000);
+        assert_eq!(from_f32(-1e-10), 0x8000);
+    }
+
+    #[test]
+    fn overflow_to_infinity() {
+        assert_eq!(from_f32(1e6), 0x7c00);
+        assert_eq!(from_f32(-1e6), 0xfc00);
+        assert_eq!(from_f32(f32::INFINITY), 0x7c00);
+        assert_eq!(from_f32(f32::NEG_INFINITY), 0xfc00);
+        // still nearer the largest half float than infinity
+        assert_eq!(from_f32(65519.), 0x7bff);
+        // halfway to the next exponent, rounding to even carries into infinity
+        assert_eq!(from_f32(65520.), 0x7c00);
+        assert_eq!(to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(to_f32(0xfc00), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn nan() {
+        let bits = from_f32(f32::NAN);
+
+        assert_eq!(bits & 0x7c00, 0x7c00);
+        assert_ne!(bits & 0x3ff, 0);
+        assert!(to_f32(0x7e00).is_nan());
+        assert!(to_f32(0xfc01).is_nan());
+    }
+
+    #[test]
+    fn ties_round_to_even() {
+        let ulp = 2f32.powi(-10);
+
+        // halfway between 1 and the next half float, 1 is even
+        assert_eq!(from_f32(1. + ulp / 2.), 0x3c00);
+        // halfway between the odd 0x3c01 and 0x3c02
+        assert_eq!(from_f32(1. + 3. * ulp / 2.), 0x3c02);
+        // just above halfway rounds up
+        assert_eq!(from_f32(1. + ulp / 2. + 2f32.powi(-20)), 0x3c01);
+        // the spacing is 2 from 2048
+        assert_eq!(from_f32(2049.), 0x6800);
+        assert_eq!(from_f32(2051.), 0x6802);
+        // halfway between 0 and the smallest subnormal, and between the odd 0x0001 and 0x0002
+        assert_eq!(from_f32(2f32.powi(-25)), 0x0000);
+        assert_eq!(from_f32(3. * 2f32.powi(-25)), 0x0002);
+    }
+}
@@ -0,0 +1,1321 @@
+use crate::{
+    config::Config,
+    controller::PdGains,
+    crc::{Crc32, CASTAGNOLI},
+    f16,
+    gaps::GapReference,
+    sampler::SampledDim,
+    simulation::{ArenaMode, CarPreset, SimResult, StopCondition, UpReference},
+    table, ARENA_MEM_WEIGHT_MODE, MAX_ANG_VEL, MAX_SIM_SECONDS, ROCKETSIM_RS_VERSION, TICK_RATE,
+};
+use clap::ValueEnum;
+use rocketsim_rs::glam_ext::glam::{Mat3A, Quat};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// The first bytes of every results file
+pub const MAGIC: [u8; 4] = *b"SFDR";
+pub const FORMAT_VERSION: u32 = 1;
+/// The headerless files of the first versions, read with the header `migrate` rewrites them with
+pub const LEGACY_FORMAT_VERSION: u32 = 0;
+/// The last bytes of every results file, after the record count and the checksum
+const FOOTER_MAGIC: [u8; 4] = *b"SFDE";
+/// The record count, the checksum and `FOOTER_MAGIC`
+const FOOTER_SIZE: usize = 8 + 4 + 4;
+/// Records serialized at once by `serialize_to`
+const SERIALIZE_CHUNK_RECORDS: usize = 1 << 12;
+/// The angular velocity and the relative target, each of `Precision::size` bytes unless omitted, which the time follows
+const INPUT_VALUES: usize = 6;
+
+/// Optional fields that follow the base fields of each record, in the order of their bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Fields(u32);
+
+impl Fields {
+    /// `ControlResponse`, 2 f32
+    pub const RESPONSE: Self = Self(1 << 0);
+    /// Initial local-frame target direction, 3 f32
+    pub const LOCAL_TARGET: Self = Self(1 << 1);
+    /// Difficulty class from the metadata's thresholds, 1 u8
+    pub const DIFFICULTY_CLASS: Self = Self(1 << 2);
+    /// Index of the car config in the metadata's `car_configs`, 1 u8
+    pub const CAR_CONFIG_ID: Self = Self(1 << 3);
+    /// Initial world-frame orientation as pitch, yaw and roll, 3 f32
+    pub const INITIAL_ORIENTATION: Self = Self(1 << 4);
+    /// Time-weighted absolute angle error, 1 f32
+    pub const ITAE: Self = Self(1 << 5);
+    /// Index of the controller in the metadata's `controllers`, 1 u8
+    pub const CONTROLLER_ID: Self = Self(1 << 6);
+    /// Initial world-frame angular velocity, 3 f32
+    pub const WORLD_ANG_VEL: Self = Self(1 << 7);
+    /// The target's world-frame pitch and yaw, 2 f32
+    pub const WORLD_TARGET: Self = Self(1 << 8);
+    /// Unit direction to the target in the world frame, 3 f32
+    pub const WORLD_TARGET_DIR: Self = Self(1 << 9);
+    /// The RNG state the sample was drawn from, which `Rng::with_seed` draws it again from, 1 u64
+    pub const SEED: Self = Self(1 << 10);
+
+    /// Every optional field with its size in bytes, in the order of their bits
+    const SIZES: [(Self, usize); 11] = [
+        (Self::RESPONSE, 4 * 2),
+        (Self::LOCAL_TARGET, 4 * 3),
+        (Self::DIFFICULTY_CLASS, 1),
+        (Self::CAR_CONFIG_ID, 1),
+        (Self::INITIAL_ORIENTATION, 4 * 3),
+        (Self::ITAE, 4),
+        (Self::CONTROLLER_ID, 1),
+        (Self::WORLD_ANG_VEL, 4 * 3),
+        (Self::WORLD_TARGET, 4 * 2),
+        (Self::WORLD_TARGET_DIR, 4 * 3),
+        (Self::SEED, 8),
+    ];
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut fields = Self::default();
+
+        if config.record_response {
+            fields.insert(Self::RESPONSE);
+        }
+
+        if config.record_local_target {
+            fields.insert(Self::LOCAL_TARGET);
+        }
+
+        if !config.difficulty_thresholds.is_empty() {
+            fields.insert(Self::DIFFICULTY_CLASS);
+        }
+
+        if config.car_configs.len() > 1 {
+            fields.insert(Self::CAR_CONFIG_ID);
+        }
+
+        if config.record_initial_orientation || config.record_world_frame {
+            fields.insert(Self::INITIAL_ORIENTATION);
+        }
+
+        if config.record_itae {
+            fields.insert(Self::ITAE);
+        }
+
+        if config.controller.len() > 1 {
+            fields.insert(Self::CONTROLLER_ID);
+        }
+
+        if config.record_world_ang_vel || config.record_world_frame {
+            fields.insert(Self::WORLD_ANG_VEL);
+        }
+
+        if config.record_world_target {
+            fields.insert(Self::WORLD_TARGET);
+        }
+
+        if config.record_world_frame {
+            fields.insert(Self::WORLD_TARGET_DIR);
+        }
+
+        if config.record_seed {
+            fields.insert(Self::SEED);
+        }
+
+        fields
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// The size in bytes of the optional fields present in every record
+    fn optional_size(self) -> usize {
+        Self::SIZES
+            .iter()
+            .filter(|&&(field, _)| self.contains(field))
+            .map(|(_, size)| size)
+            .sum()
+    }
+
+    /// The size in bytes of every optional value present in a record, in order
+    fn optional_value_sizes(self) -> Vec<usize> {
+        let mut sizes = Vec::new();
+
+        for &(field, size) in &Self::SIZES {
+            if self.contains(field) {
+                // the u8 fields and the seed are a single value, the others are made of f32s
+                sizes.extend(if size == 1 || field == Self::SEED {
+                    vec![size]
+                } else {
+                    vec![4; size / 4]
+                });
+            }
+        }
+
+        sizes
+    }
+
+    /// Where the optional `field` starts after the base fields, after the present fields before it
+    fn optional_offset(self, field: Self) -> usize {
+        Self::SIZES
+            .iter()
+            .take_while(|&&(other, _)| other != field)
+            .filter(|&&(other, _)| self.contains(other))
+            .map(|(_, size)| size)
+            .sum()
+    }
+}
+
+/// A base input value of every record, in the order they're written
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[repr(u32)]
+pub enum Input {
+    AngVelX,
+    AngVelY,
+    AngVelZ,
+    TargetPitch,
+    TargetYaw,
+    TargetRoll,
+}
+
+impl Input {
+    pub const ALL: [Self; INPUT_VALUES] = [
+        Self::AngVelX,
+        Self::AngVelY,
+        Self::AngVelZ,
+        Self::TargetPitch,
+        Self::TargetYaw,
+        Self::TargetRoll,
+    ];
+}
+
+/// The base input values left out of every record by `--omit-inputs`, bit `n` for the `n`th of `Input::ALL`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OmittedInputs(u32);
+
+impl OmittedInputs {
+    pub fn from_config(config: &Config) -> Self {
+        Self(config.omit_inputs.iter().fold(0, |bits, &input| bits | 1 << input as u32))
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, input: Input) -> bool {
+        self.0 & 1 << input as u32 != 0
+    }
+
+    /// The number of base input values present in every record
+    pub fn num_present(self) -> usize {
+        Input::ALL.iter().filter(|&&input| !self.contains(input)).count()
+    }
+
+    /// Where `input` is among the base input values present in every record, `None` if it's omitted
+    pub fn position(self, input: Input) -> Option<usize> {
+        (!self.contains(input)).then(|| {
+            Input::ALL
+                .iter()
+                .take_while(|&&other| other != input)
+                .filter(|&&other| !self.contains(other))
+                .count()
+        })
+    }
+}
+
+/// How the relative target is stored in the base fields of every record
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum TargetRotation {
+    /// Pitch, yaw and roll, divided by the angle scale
+    #[default]
+    Euler = 0,
+    /// The unit quaternion (w, x, y, z) of the rotation from the initial orientation to the target, with `w >= 0`,
+    /// which doesn't wrap around
+    Quaternion = 1,
+    /// The first 2 columns of the matrix of the rotation from the initial orientation to the target,
+    /// its forward and right axes, which is continuous
+    Rotation6d = 2,
+}
+
+impl TargetRotation {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Euler),
+            1 => Some(Self::Quaternion),
+            2 => Some(Self::Rotation6d),
+            _ => None,
+        }
+    }
+
+    /// The number of base values the relative target is stored in
+    pub const fn num_values(self) -> usize {
+        match self {
+            Self::Euler => 3,
+            Self::Quaternion => 4,
+            Self::Rotation6d => 6,
+        }
+    }
+}
+
+/// The unit quaternion (w, x, y, z) of the rotation `rot`, the one of the 2 with `w >= 0`
+pub fn quaternion(rot: Mat3A) -> [f32; 4] {
+    let q = Quat::from_mat3a(&rot);
+    let q = if q.w < 0. { -q } else { q };
+
+    [q.w, q.x, q.y, q.z]
+}
+
+/// The first 2 columns of the rotation matrix `rot`, its forward and right axes
+pub fn rotation_6d(rot: Mat3A) -> [f32; 6] {
+    let [x, y, z] = rot.x_axis.to_array();
+    let [right_x, right_y, right_z] = rot.y_axis.to_array();
+
+    [x, y, z, right_x, right_y, right_z]
+}
+
+/// How the records are written to the results files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The header and the zstd compressed records described in the README
+    #[default]
+    Binary,
+    /// A zstd compressed, length-delimited `ResultBatch` of `proto/results.proto`
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+    /// zstd compressed CSV with a header row, for spreadsheets and R
+    Csv,
+    /// An uncompressed NumPy archive of the inputs and the times, for `np.load`
+    Npz,
+    /// Uncompressed JSON Lines, an object of named fields for every record, for debugging and jq
+    Jsonl,
+    /// An uncompressed TFRecord file of a `tf.train.Example` for every record, for `tf.data`
+    Tfrecord,
+    /// An uncompressed Cap'n Proto message of a `ResultBatch` of `proto/results.capnp`, for zero-copy reading
+    Capnp,
+    /// A Parquet file with a column for every field, for polars, pandas and DuckDB
+    #[cfg(feature = "parquet")]
+    Parquet,
+    /// An uncompressed Arrow IPC (Feather v2) file, for memory-mapping with pyarrow and polars
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// A SQLite database with an indexed table of the samples, for exploring them with SQL
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// One HDF5 file of chunked, compressed datasets that every batch is appended to
+    #[cfg(feature = "hdf5")]
+    Hdf5,
+}
+
+impl OutputFormat {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Binary => "bin",
+            #[cfg(feature = "protobuf")]
+            Self::Protobuf => "pb.zst",
+            Self::Csv => "csv.zst",
+            Self::Npz => "npz",
+            Self::Tfrecord => "tfrecord",
+            Self::Jsonl => "jsonl",
+            Self::Capnp => "capnp",
+            #[cfg(feature = "parquet")]
+            Self::Parquet => "parquet",
+            #[cfg(feature = "arrow")]
+            Self::Arrow => "arrow",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => "sqlite",
+            #[cfg(feature = "hdf5")]
+            Self::Hdf5 => "h5",
+        }
+    }
+}
+
+/// How the time field of each record is stored, always in 4 bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum TimeCodec {
+    /// `f32` seconds
+    #[default]
+    Seconds = 0,
+    /// `u32` number of ticks
+    Ticks = 1,
+    /// `i32` number of ticks minus the number of ticks of the previous record in the file
+    DeltaTicks = 2,
+    /// `u16` number of ticks, half the size and still lossless
+    Ticks16 = 3,
+}
+
+impl TimeCodec {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Seconds),
+            1 => Some(Self::Ticks),
+            2 => Some(Self::DeltaTicks),
+            3 => Some(Self::Ticks16),
+            _ => None,
+        }
+    }
+}
+
+/// Byte order of the record fields, the header is always little-endian
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum ByteOrder {
+    #[default]
+    Little = 0,
+    Big = 1,
+}
+
+impl ByteOrder {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Little),
+            1 => Some(Self::Big),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn read_f32(self, bytes: [u8; 4]) -> f32 {
+        match self {
+            Self::Little => f32::from_le_bytes(bytes),
+            Self::Big => f32::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    pub fn read_u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Self::Little => u32::from_le_bytes(bytes),
+            Self::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    pub fn read_u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    pub fn read_i16(self, bytes: [u8; 2]) -> i16 {
+        match self {
+            Self::Little => i16::from_le_bytes(bytes),
+            Self::Big => i16::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    pub fn read_i32(self, bytes: [u8; 4]) -> i32 {
+        match self {
+            Self::Little => i32::from_le_bytes(bytes),
+            Self::Big => i32::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    pub fn read_u64(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            Self::Little => u64::from_le_bytes(bytes),
+            Self::Big => u64::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    fn f32(self, value: f32) -> [u8; 4] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    #[inline]
+    fn u16(self, value: u16) -> [u8; 2] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    #[inline]
+    pub fn u32(self, value: u32) -> [u8; 4] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    #[inline]
+    fn u64(self, value: u64) -> [u8; 8] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    #[inline]
+    fn i16(self, value: i16) -> [u8; 2] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    #[inline]
+    pub fn i32(self, value: i32) -> [u8; 4] {
+        match self {
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// How the values of the records are arranged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum Layout {
+    /// Every record after the previous one
+    #[default]
+    Rows = 0,
+    /// The first value of every record, then the second value of every record and so on,
+    /// which puts similar values next to each other for zstd
+    Columns = 1,
+}
+
+impl Layout {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Rows),
+            1 => Some(Self::Columns),
+            _ => None,
+        }
+    }
+}
+
+/// How the angular velocity, the relative target and the time of the records are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum Precision {
+    /// `f32`
+    #[default]
+    F32 = 0,
+    /// IEEE 754 half-precision floats, half the size
+    F16 = 1,
+    /// `i16` fractions of the scales, which are always the normalized ones, half the size
+    I16 = 2,
+}
+
+impl Precision {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::F32),
+            1 => Some(Self::F16),
+            2 => Some(Self::I16),
+            _ => None,
+        }
+    }
+
+    /// The size in bytes of every base value
+    pub const fn size(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 | Self::I16 => 2,
+        }
+    }
+}
+
+/// How the records are compressed after the header
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum Compression {
+    /// zstd at `--zstd-level`
+    #[default]
+    Zstd = 0,
+    /// Uncompressed, for storage that compresses by itself
+    None = 1,
+    /// The LZ4 frame format, much faster and bigger than zstd
+    #[cfg(feature = "lz4")]
+    Lz4 = 2,
+    /// gzip, for tools that can't read zstd
+    #[cfg(feature = "gzip")]
+    Gzip = 3,
+}
+
+impl Compression {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Zstd),
+            1 => Some(Self::None),
+            #[cfg(feature = "lz4")]
+            2 => Some(Self::Lz4),
+            #[cfg(feature = "gzip")]
+            3 => Some(Self::Gzip),
+            _ => None,
+        }
+    }
+}
+
+/// Generation settings that change the dynamics, stored as JSON at the end of the header.
+///
+/// Keys missing from older files of the same format version get their defaults when read.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    /// The rocketsim_rs version the file was gathered with, missing from older files
+    pub rocketsim_rs_version: Option<String>,
+    /// Memory weight mode of the arenas, missing from older files
+    pub arena_mem_weight_mode: Option<String>,
+    pub arena_mode: ArenaMode,
+    /// The car configs the samples were spread over, indexed by the car config id field
+    pub car_configs: Vec<CarPreset>,
+    /// The gains of the controllers the samples were spread over, indexed by the controller id field
+    pub controllers: Vec<PdGains>,
+    pub up_reference: UpReference,
+    pub up_direction: [f32; 3],
+    pub stop_condition: StopCondition,
+    /// Max angular velocity of a settled arrival, present with `StopCondition::Settled`
+    pub settle_ang_vel: Option<f32>,
+    /// Max seconds a recorded sample took to reach the target, if limited
+    pub time_budget: Option<f32>,
+    /// Only samples slower than this running percentile of the times were recorded, if set
+    pub record_above_percentile: Option<f32>,
+    /// Samples each worker drew before recording any, with `record_above_percentile`
+    pub percentile_warmup: Option<u64>,
+    /// The number of samples after which every worker recreated its arenas, if it did
+    pub reset_every: Option<u64>,
+    /// Seed the workers' seeds were derived from, `--seed` or a random one, only missing from older files
+    pub seed: Option<u64>,
+    /// Present in deterministic mode, where every file holds this many records
+    pub records_per_file: Option<u32>,
+    /// Max absolute car-relative initial angular velocity about x, y and z, `None` for the unlimited axes
+    pub max_rates: [Option<f32>; 3],
+    /// Largest magnitude of the sampled initial angular velocity, missing from older files
+    pub max_ang_vel: Option<f32>,
+    /// The orientation and the target were sampled in `[0, max_angle)`, missing from older files
+    pub max_angle: Option<f32>,
+    /// Radians between the car's forward axis and the target at which it was reached, missing from older files
+    pub convergence_threshold: Option<f32>,
+    /// Simulated seconds after which a sample was abandoned, missing from older files
+    pub max_sim_seconds: Option<f32>,
+    /// The sampled dimensions `--fix` held constant, and their values
+    pub fixed: BTreeMap<SampledDim, f32>,
+    /// The angles `--nominal` sampled around, as pitch, yaw, roll, target pitch and target yaw
+    pub nominal: Option<[f32; 5]>,
+    /// Standard deviation of the perturbation around `nominal`
+    pub perturbation_std: Option<f32>,
+    /// The reference dataset `--fill-gaps` sampled around, set once it's loaded
+    pub fill_gaps: Option<GapReference>,
+    /// Ascending seconds that separate the difficulty classes, empty if they aren't stored
+    pub difficulty_thresholds: Vec<f32>,
+    /// The difficulty class of every record in the file, with `--split-by-difficulty`
+    pub difficulty_band: Option<u8>,
+    /// The id of the `--zstd-dictionary` the records were compressed with, stored as `dictionary-{id}.zstd`
+    /// in the file's folder or the one above it
+    pub zstd_dictionary_id: Option<u32>,
+    /// The `--zstd-level` the records were compressed at, present with zstd compression, missing from older files
+    pub zstd_level: Option<i32>,
+    /// The `--zstd-workers` the records were compressed with, present with zstd compression, missing from older files
+    pub zstd_workers: Option<u32>,
+    /// Set by `migrate` on a file written by the first, headerless versions,
+    /// whose other keys are placeholders rather than the options it was gathered with
+    pub migrated: bool,
+}
+
+impl Metadata {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            rocketsim_rs_version: Some(ROCKETSIM_RS_VERSION.to_string()),
+            arena_mem_weight_mode: Some(ARENA_MEM_WEIGHT_MODE.1.to_string()),
+            arena_mode: config.arena_mode,
+            car_configs: config.car_configs.clone(),
+            controllers: config.controllers(),
+            up_reference: config.up_reference,
+            up_direction: config.up_direction,
+            stop_condition: config.stop_condition,
+            settle_ang_vel: (config.stop_condition == StopCondition::Settled).then_some(config.settle_ang_vel),
+            time_budget: config.time_budget,
+            record_above_percentile: config.record_above_percentile,
+            percentile_warmup: config.record_above_percentile.map(|_| config.percentile_warmup),
+            reset_every: config.reset_every,
+            seed: config.seed,
+            records_per_file: config.deterministic.then(|| config.deterministic_records_per_file()),
+            max_rates: config.max_rates(),
+            max_ang_vel: Some(config.max_ang_vel),
+            max_angle: Some(config.max_angle),
+            convergence_threshold: Some(config.convergence_threshold),
+            max_sim_seconds: Some(config.max_sim_seconds),
+            fixed: config.fix.iter().copied().collect(),
+            nominal: config.nominal,
+            perturbation_std: config.nominal.map(|_| config.perturbation_std),
+            difficulty_thresholds: config.difficulty_thresholds.clone(),
+            fill_gaps: None,
+            difficulty_band: None,
+            zstd_dictionary_id: None,
+            zstd_level: (config.compression == Compression::Zstd).then_some(config.zstd_level),
+            zstd_workers: (config.compression == Compression::Zstd).then_some(config.zstd_workers),
+            migrated: false,
+        }
+    }
+}
+
+/// Written uncompressed at the start of every results file, followed by the records compressed with `compression`.
+///
+/// Every base field has been divided by its scale,
+/// so consumers multiply by the scale to get the original value back.
+/// The time scale only applies to `TimeCodec::Seconds`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub fields: Fields,
+    pub omitted_inputs: OmittedInputs,
+    pub target_rotation: TargetRotation,
+    pub time_codec: TimeCodec,
+    pub byte_order: ByteOrder,
+    pub layout: Layout,
+    pub precision: Precision,
+    pub compression: Compression,
+    pub tick_rate: f32,
+    pub ang_vel_scale: f32,
+    pub angle_scale: f32,
+    pub time_scale: f32,
+    pub metadata: Metadata,
+}
+
+impl Header {
+    pub fn new(config: &Config) -> Self {
+        let fields = Fields::from_config(config);
+        let omitted_inputs = OmittedInputs::from_config(config);
+        let target_rotation = config.target_rotation;
+        let time_codec = config.time_codec;
+        let byte_order = config.byte_order;
+        let layout = config.layout;
+        let precision = config.precision;
+        let compression = config.compression;
+        let metadata = Metadata::new(config);
+
+        if config.normalize {
+            // the max of each sampling range maps to 1
+            Self {
+                fields,
+                omitted_inputs,
+                target_rotation,
+                time_codec,
+                byte_order,
+                layout,
+                precision,
+                compression,
+                tick_rate: TICK_RATE,
+                ang_vel_scale: config.max_ang_vel,
+                angle_scale: config.max_angle,
+                time_scale: config.max_sim_seconds,
+                metadata,
+            }
+        } else {
+            Self {
+                fields,
+                omitted_inputs,
+                target_rotation,
+                time_codec,
+                byte_order,
+                layout,
+                precision,
+                compression,
+                tick_rate: TICK_RATE,
+                ang_vel_scale: 1.,
+                angle_scale: 1.,
+                time_scale: 1.,
+                metadata,
+            }
+        }
+    }
+
+    /// Size in bytes of the time of every record
+    pub fn time_size(&self) -> usize {
+        match self.time_codec {
+            TimeCodec::Seconds => self.precision.size(),
+            TimeCodec::Ticks | TimeCodec::DeltaTicks => 4,
+            TimeCodec::Ticks16 => 2,
+        }
+    }
+
+    /// Size in bytes of the base fields of every record, which the optional fields follow
+    fn base_size(&self) -> usize {
+        self.num_inputs() * self.precision.size() + self.time_size()
+    }
+
+    /// Size in bytes of every record, with the optional fields
+    pub fn record_size(&self) -> usize {
+        self.base_size() + self.fields.optional_size()
+    }
+
+    /// Where the optional `field` starts in every record, after the base fields and the present fields before it
+    pub fn offset(&self, field: Fields) -> usize {
+        self.base_size() + self.fields.optional_offset(field)
+    }
+
+    /// The number of base input values in every record, which the time follows
+    pub fn num_inputs(&self) -> usize {
+        // the target inputs can only be omitted with `TargetRotation::Euler`
+        self.omitted_inputs.num_present() + self.target_rotation.num_values() - 3
+    }
+
+    /// The base input values of `result` present in every record, in order, each divided by its scale
+    pub fn inputs(&self, result: &SimResult) -> Vec<f32> {
+        let present = |values: [(Input, f32); 3]| {
+            values
+                .into_iter()
+                .filter(|&(input, _)| !self.omitted_inputs.contains(input))
+                .map(|(_, value)| value)
+        };
+
+        let iav = result.initial_angular_velocity / self.ang_vel_scale;
+        let mut inputs =
+            present([(Input::AngVelX, iav.x), (Input::AngVelY, iav.y), (Input::AngVelZ, iav.z)]).collect::<Vec<_>>();
+
+        let rt = result.relative_target;
+        match self.target_rotation {
+            TargetRotation::Euler => inputs.extend(present([
+                (Input::TargetPitch, rt.pitch / self.angle_scale),
+                (Input::TargetYaw, rt.yaw / self.angle_scale),
+                (Input::TargetRoll, rt.roll / self.angle_scale),
+            ])),
+            TargetRotation::Quaternion => inputs.extend(quaternion(result.relative_rotation)),
+            TargetRotation::Rotation6d => inputs.extend(rotation_6d(result.relative_rotation)),
+        }
+
+        inputs
+    }
+
+    /// The size in bytes of every value of a record, in order, the base fields followed by the optional fields present
+    pub fn value_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![self.precision.size(); self.num_inputs()];
+        sizes.push(self.time_size());
+        sizes.extend(self.fields.optional_value_sizes());
+        sizes
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&self.fields.0.to_le_bytes())?;
+        writer.write_all(&self.omitted_inputs.0.to_le_bytes())?;
+        writer.write_all(&(self.target_rotation as u32).to_le_bytes())?;
+        writer.write_all(&(self.time_codec as u32).to_le_bytes())?;
+        writer.write_all(&(self.byte_order as u32).to_le_bytes())?;
+        writer.write_all(&(self.layout as u32).to_le_bytes())?;
+        writer.write_all(&(self.precision as u32).to_le_bytes())?;
+        writer.write_all(&(self.compression as u32).to_le_bytes())?;
+        writer.write_all(&self.tick_rate.to_le_bytes())?;
+        writer.write_all(&self.ang_vel_scale.to_le_bytes())?;
+        writer.write_all(&self.angle_scale.to_le_bytes())?;
+        writer.write_all(&self.time_scale.to_le_bytes())?;
+
+        let metadata = serde_json::to_vec(&self.metadata)?;
+        writer.write_all(&(metadata.len() as u32).to_le_bytes())?;
+        writer.write_all(&metadata)?;
+
+        // padded with whitespace, which JSON ignores, so the records of an uncompressed file start 8-byte aligned
+        // for the `mmap` feature's readers
+        let mut schema = serde_json::to_vec(&self.schema())?;
+        let header_size = 16 * 4 + metadata.len() + schema.len();
+        schema.resize(schema.len() + header_size.next_multiple_of(8) - header_size, b' ');
+        writer.write_all(&(schema.len() as u32).to_le_bytes())?;
+        writer.write_all(&schema)
+    }
+
+    /// A `SchemaField` for every value of a record, in order, written as the header's schema block
+    pub fn schema(&self) -> Vec<SchemaField> {
+        let mut names = table::columns(self).into_iter().map(|column| column.name).collect::<Vec<_>>();
+        // only the binary records can hold it, so it isn't one of the tabular columns
+        if self.fields.contains(Fields::SEED) {
+            names.push("seed");
+        }
+
+        let num_inputs = self.num_inputs();
+        let base_type = match self.precision {
+            Precision::F32 => "f32",
+            Precision::F16 => "f16",
+            Precision::I16 => "i16",
+        };
+        // the i16 values are fractions of the scales
+        let base_scale = |scale: f32| match self.precision {
+            Precision::I16 => scale / f32::from(i16::MAX),
+            Precision::F32 | Precision::F16 => scale,
+        };
+
+        names
+            .into_iter()
+            .zip(self.value_sizes())
+            .enumerate()
+            .map(|(i, (name, size))| {
+                let (unit, frame, scale) = match name {
+                    "ang_vel_x" | "ang_vel_y" | "ang_vel_z" => ("rad/s", "car", self.ang_vel_scale),
+                    "target_pitch" | "target_yaw" | "target_roll" => ("rad", "car", self.angle_scale),
+                    "time" if self.time_codec == TimeCodec::Seconds => ("s", "", self.time_scale),
+                    "time" => ("ticks", "", 1.),
+                    "response_ang_accel" => ("rad/s^2", "car", 1.),
+                    "itae" => ("rad", "", 1.),
+                    "world_ang_vel_x" | "world_ang_vel_y" | "world_ang_vel_z" => ("rad/s", "world", self.ang_vel_scale),
+                    "initial_pitch" | "initial_yaw" | "initial_roll" | "world_target_pitch" | "world_target_yaw" => {
+                        ("rad", "world", self.angle_scale)
+                    }
+                    _ if name.starts_with("world_") => ("", "world", 1.),
+                    _ if name.starts_with("target_") || name.starts_with("local_target_") => ("", "car", 1.),
+                    _ => ("", "", 1.),
+                };
+
+                let (value_type, scale) = match (i, size) {
+                    (i, _) if i < num_inputs => (base_type, base_scale(scale)),
+                    (i, _) if i == num_inputs => match self.time_codec {
+                        TimeCodec::Seconds => (base_type, base_scale(scale)),
+                        TimeCodec::Ticks => ("u32", scale),
+                        TimeCodec::DeltaTicks => ("i32", scale),
+                        TimeCodec::Ticks16 => ("u16", scale),
+                    },
+                    (_, 1) => ("u8", scale),
+                    (_, 8) => ("u64", scale),
+                    _ => ("f32", scale),
+                };
+
+                SchemaField {
+                    name: name.to_string(),
+                    value_type: value_type.to_string(),
+                    unit: unit.to_string(),
+                    frame: frame.to_string(),
+                    scale,
+                }
+            })
+            .collect()
+    }
+
+    /// Reads a header written by `write_to`, failing on files of newer format versions
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::read_with_schema(reader).map(|(header, _, _)| header)
+    }
+
+    /// Reads a header written by `write_to` of any format version this build knows, along with its schema block
+    /// and the format version it was written with
+    pub fn read_with_schema<R: Read>(reader: &mut R) -> io::Result<(Self, Vec<SchemaField>, u32)> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+
+        if bytes[..4] != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a results file"));
+        }
+
+        let version = u32::from_le_bytes(bytes[4..].try_into().unwrap());
+        let (header, schema) = match version {
+            FORMAT_VERSION => Self::read_version_1(reader)?,
+            _ => return Err(unsupported_version(version)),
+        };
+
+        Ok((header, schema, version))
+    }
+
+    /// Reads the rest of a header of format version 1, after its version
+    fn read_version_1<R: Read>(reader: &mut R) -> io::Result<(Self, Vec<SchemaField>)> {
+        let mut read_u32 = || -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let fields = Fields(read_u32()?);
+        let omitted_inputs = OmittedInputs(read_u32()?);
+        let target_rotation = read_u32()?;
+        let target_rotation = TargetRotation::from_u32(target_rotation)
+            .ok_or_else(|| invalid(format!("unknown target rotation {target_rotation}")))?;
+        let time_codec = read_u32()?;
+        let time_codec =
+            TimeCodec::from_u32(time_codec).ok_or_else(|| invalid(format!("unknown time codec {time_codec}")))?;
+        let byte_order = read_u32()?;
+        let byte_order =
+            ByteOrder::from_u32(byte_order).ok_or_else(|| invalid(format!("unknown byte order {byte_order}")))?;
+        let layout = read_u32()?;
+        let layout = Layout::from_u32(layout).ok_or_else(|| invalid(format!("unknown layout {layout}")))?;
+        let precision = read_u32()?;
+        let precision = Precision::from_u32(precision).ok_or_else(|| invalid(format!("unknown precision {precision}")))?;
+        let compression = read_u32()?;
+        let compression = Compression::from_u32(compression).ok_or_else(|| {
+            invalid(format!(
+                "unknown compression {compression}, LZ4 and gzip need the lz4 and gzip features"
+            ))
+        })?;
+        let tick_rate = f32::from_bits(read_u32()?);
+        let ang_vel_scale = f32::from_bits(read_u32()?);
+        let angle_scale = f32::from_bits(read_u32()?);
+        let time_scale = f32::from_bits(read_u32()?);
+
+        let mut metadata = vec![0; read_u32()? as usize];
+        reader.read_exact(&mut metadata)?;
+        let metadata = serde_json::from_slice(&metadata)?;
+
+        let mut schema_len = [0; 4];
+        reader.read_exact(&mut schema_len)?;
+        let mut schema = vec![0; u32::from_le_bytes(schema_len) as usize];
+        reader.read_exact(&mut schema)?;
+        let schema = serde_json::from_slice(&schema)?;
+
+        let header = Self {
+            fields,
+            omitted_inputs,
+            target_rotation,
+            time_codec,
+            byte_order,
+            layout,
+            precision,
+            compression,
+            tick_rate,
+            ang_vel_scale,
+            angle_scale,
+            time_scale,
+            metadata,
+        };
+
+        Ok((header, schema))
+    }
+}
+
+/// A value of every record, as described by the schema block of the header
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SchemaField {
+    /// The name of its column in the tabular formats
+    pub name: String,
+    /// `f32`, `f16`, `i16`, `u8`, `u16`, `u32`, `i32` or `u64`, in the record byte order
+    #[serde(rename = "type")]
+    pub value_type: String,
+    /// The unit of the value multiplied by `scale`, empty for ids, classes and unitless values like directions
+    pub unit: String,
+    /// `car` for the car's initial local frame or relative to its initial orientation, `world`, or empty
+    pub frame: String,
+    /// Multiply the stored value by it to get the value in `unit`
+    pub scale: f32,
+}
+
+/// Written as `{n}.json` next to every `{n}.bin` by `--sidecar-metadata`, which then only holds the uncompressed records
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub format_version: u32,
+    pub num_records: u64,
+    /// Size in bytes of every record, with the optional fields
+    pub record_size: usize,
+    /// The largest magnitude of the sampled initial angular velocity
+    pub max_ang_vel: f32,
+    /// The simulated seconds after which a sample is abandoned
+    pub max_sim_seconds: f32,
+    #[serde(flatten)]
+    pub header: Header,
+    /// The schema block of the header
+    pub schema: Vec<SchemaField>,
+}
+
+impl Sidecar {
+    pub fn new(header: &Header, num_records: usize) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            num_records: num_records as u64,
+            record_size: header.record_size(),
+            max_ang_vel: header.metadata.max_ang_vel.unwrap_or(MAX_ANG_VEL),
+            max_sim_seconds: header.metadata.max_sim_seconds.unwrap_or(MAX_SIM_SECONDS),
+            header: header.clone(),
+            schema: header.schema(),
+        }
+    }
+
+    /// Reads a sidecar written by `--sidecar-metadata` of any format version this build knows,
+    /// failing on files of newer format versions
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let sidecar: serde_json::Value = serde_json::from_slice(&fs::read(path)?)?;
+
+        // the version decides how to read the rest, so it's read on its own first
+        let version = sidecar
+            .get("format_version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the sidecar has no format version"))?;
+
+        match u32::try_from(version) {
+            Ok(FORMAT_VERSION) => Ok(serde_json::from_value(sidecar)?),
+            _ => Err(unsupported_version(version)),
+        }
+    }
+}
+
+/// The error of a file written with a format version this build doesn't know, likely by a newer one
+fn unsupported_version(version: impl fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("format version {version} isn't supported, this build reads up to {FORMAT_VERSION}"),
+    )
+}
+
+/// Written as `manifest.json` in the folder of every `--per-run-dir` run, describing how to read all of its files
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    /// Seconds since the Unix epoch when the run started
+    pub started_at: u64,
+    /// Size in bytes of every record, with the optional fields
+    pub record_size: usize,
+    /// The largest magnitude of the sampled initial angular velocity
+    pub max_ang_vel: f32,
+    /// The simulated seconds after which a sample is abandoned
+    pub max_sim_seconds: f32,
+    #[serde(flatten)]
+    pub header: Header,
+    /// The schema block of the header
+    pub schema: Vec<SchemaField>,
+}
+
+impl Manifest {
+    pub fn new(header: &Header, started_at: u64) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            started_at,
+            record_size: header.record_size(),
+            max_ang_vel: header.metadata.max_ang_vel.unwrap_or(MAX_ANG_VEL),
+            max_sim_seconds: header.metadata.max_sim_seconds.unwrap_or(MAX_SIM_SECONDS),
+            header: header.clone(),
+            schema: header.schema(),
+        }
+    }
+
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        fs::write(dir.join("manifest.json"), serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Written after the compressed records, so readers can check that a file holds every record it was written with
+/// and that `checksum`, the CRC-32C of the uncompressed records, still matches them
+pub fn write_footer<W: Write>(writer: &mut W, num_records: usize, checksum: u32) -> io::Result<()> {
+    writer.write_all(&(num_records as u64).to_le_bytes())?;
+    writer.write_all(&checksum.to_le_bytes())?;
+    writer.write_all(&FOOTER_MAGIC)
+}
+
+/// Splits the footer written by `write_footer` off the end of `bytes`,
+/// returning the bytes before it, the record count and the checksum
+pub fn split_footer(bytes: &[u8]) -> io::Result<(&[u8], u64, u32)> {
+    let Some(footer_start) = bytes.len().checked_sub(FOOTER_SIZE) else {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the file ends before its footer",
+        ));
+    };
+    let (bytes, footer) = bytes.split_at(footer_start);
+
+    if footer[12..] != FOOTER_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the file doesn't end with a footer, it may be truncated",
+        ));
+    }
+
+    let num_records = u64::from_le_bytes(footer[..8].try_into().unwrap());
+    let checksum = u32::from_le_bytes(footer[8..12].try_into().unwrap());
+
+    Ok((bytes, num_records, checksum))
+}
+
+/// The uncompressed records, in the layout, byte order and time codec described by `header`.
+///
+/// `previous` is the record before `results` in the same file, if any, for `TimeCodec::DeltaTicks`.
+pub fn serialize(header: &Header, results: &[SimResult], previous: Option<&SimResult>) -> Vec<u8> {
+    let bytes = serialize_rows(header, results, previous);
+
+    match header.layout {
+        Layout::Rows => bytes,
+        Layout::Columns => to_columns(header, &bytes),
+    }
+}
+
+/// Writes the records of `serialize` to `writer` `SERIALIZE_CHUNK_RECORDS` at a time, so they're never all in memory,
+/// except for `Layout::Columns` which needs every record to write the first column.
+/// Returns the CRC-32C of the records for `write_footer`
+pub fn serialize_to<W: Write + ?Sized>(writer: &mut W, header: &Header, results: &[SimResult]) -> io::Result<u32> {
+    let mut crc = Crc32::new(CASTAGNOLI);
+
+    if header.layout == Layout::Columns {
+        let bytes = serialize(header, results, None);
+        crc.update(&bytes);
+        writer.write_all(&bytes)?;
+
+        return Ok(crc.finish());
+    }
+
+    let mut previous = None;
+
+    for chunk in results.chunks(SERIALIZE_CHUNK_RECORDS) {
+        let bytes = serialize_rows(header, chunk, previous);
+        crc.update(&bytes);
+        writer.write_all(&bytes)?;
+        previous = chunk.last();
+    }
+
+    Ok(crc.finish())
+}
+
+/// The records of `serialize` in `Layout::Rows`
+fn serialize_rows(header: &Header, results: &[SimResult], previous: Option<&SimResult>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(results.len() * header.record_size());
+    let order = header.byte_order;
+    let base = |value: f32| match header.precision {
+        Precision::F32 => order.f32(value).to_vec(),
+        Precision::F16 => order.u16(f16::from_f32(value)).to_vec(),
+        Precision::I16 => order
+            .i16((value.clamp(-1., 1.) * f32::from(i16::MAX)).round() as i16)
+            .to_vec(),
+    };
+    let mut last_ticks = previous.map_or(0, |result| result.ticks() as i32);
+
+    for result in results {
+        for value in header.inputs(result) {
+            bytes.extend(base(value));
+        }
+
+        match header.time_codec {
+            TimeCodec::Seconds => bytes.extend(base(result.time / header.time_scale)),
+            TimeCodec::Ticks => bytes.extend(order.u32(result.ticks())),
+            // at most `--max-sim-seconds` of ticks, which it keeps below `u16::MAX`
+            TimeCodec::Ticks16 => bytes.extend(order.u16(result.ticks() as u16)),
+            TimeCodec::DeltaTicks => {
+                let ticks = result.ticks() as i32;
+                bytes.extend(order.i32(ticks - last_ticks));
+                last_ticks = ticks;
+            }
+        }
+
+        if header.fields.contains(Fields::RESPONSE) {
+            bytes.extend(order.f32(result.response.control));
+            bytes.extend(order.f32(result.response.ang_accel));
+        }
+
+        if header.fields.contains(Fields::LOCAL_TARGET) {
+            let lt = result.local_target;
+            bytes.extend(order.f32(lt.x));
+            bytes.extend(order.f32(lt.y));
+            bytes.extend(order.f32(lt.z));
+        }
+
+        if header.fields.contains(Fields::DIFFICULTY_CLASS) {
+            bytes.push(result.difficulty_class(&header.metadata.difficulty_thresholds));
+        }
+
+        if header.fields.contains(Fields::CAR_CONFIG_ID) {
+            bytes.push(result.car_config_id);
+        }
+
+        if header.fields.contains(Fields::INITIAL_ORIENTATION) {
+            let io = result.initial_orientation;
+            bytes.extend(order.f32(io.pitch / header.angle_scale));
+            bytes.extend(order.f32(io.yaw / header.angle_scale));
+            bytes.extend(order.f32(io.roll / header.angle_scale));
+        }
+
+        if header.fields.contains(Fields::ITAE) {
+            bytes.extend(order.f32(result.itae));
+        }
+
+        if header.fields.contains(Fields::CONTROLLER_ID) {
+            bytes.push(result.controller_id);
+        }
+
+        if header.fields.contains(Fields::WORLD_ANG_VEL) {
+            let wav = result.world_angular_velocity / header.ang_vel_scale;
+            bytes.extend(order.f32(wav.x));
+            bytes.extend(order.f32(wav.y));
+            bytes.extend(order.f32(wav.z));
+        }
+
+        if header.fields.contains(Fields::WORLD_TARGET) {
+            let wt = result.world_target;
+            bytes.extend(order.f32(wt.pitch / header.angle_scale));
+            bytes.extend(order.f32(wt.yaw / header.angle_scale));
+        }
+
+        if header.fields.contains(Fields::WORLD_TARGET_DIR) {
+            let wtd = result.world_target_dir;
+            bytes.extend(order.f32(wtd.x));
+            bytes.extend(order.f32(wtd.y));
+            bytes.extend(order.f32(wtd.z));
+        }
+
+        if header.fields.contains(Fields::SEED) {
+            bytes.extend(order.u64(result.seed));
+        }
+    }
+
+    bytes
+}
+
+/// Rearranges whole records described by `header` from `Layout::Rows` to `Layout::Columns`
+pub fn to_columns(header: &Header, records: &[u8]) -> Vec<u8> {
+    let mut columns = Vec::with_capacity(records.len());
+    let mut offset = 0;
+
+    for size in header.value_sizes() {
+        for record in records.chunks_exact(header.record_size()) {
+            columns.extend(&record[offset..offset + size]);
+        }
+
+        offset += size;
+    }
+
+    columns
+}
+
+/// Rearranges whole records described by `header` from `Layout::Columns` back to `Layout::Rows`
+pub fn to_rows(header: &Header, columns: &[u8]) -> Vec<u8> {
+    let record_size = header.record_size();
+    let mut records = vec![0; columns.len()];
+    let num_records = columns.len() / record_size;
+    let mut offset = 0;
+
+    for size in header.value_sizes() {
+        let column = &columns[offset * num_records..][..size * num_records];
+
+        for (record, value) in records.chunks_exact_mut(record_size).zip(column.chunks_exact(size)) {
+            record[offset..offset + size].copy_from_slice(value);
+        }
+
+        offset += size;
+    }
+
+    records
+}
+
+/// Splits an uncompressed results file, like the write-ahead log, into its header, its whole records
+/// and the number of them, dropping a record that was cut short. `None` if it doesn't start with a complete header.
+pub fn split_records(bytes: &[u8]) -> Option<(&[u8], &[u8], usize)> {
+    let mut records = bytes;
+    let record_size = Header::read_from(&mut records).ok()?.record_size();
+    let header_size = bytes.len() - records.len();
+
+    let num_records = records.len() / record_size;
+
+    Some((&bytes[..header_size], &records[..num_records * record_size], num_records))
+}
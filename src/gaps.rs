@@ -0,0 +1,125 @@
+use crate::{reader, MAX_ANG_VEL};
+use rocketsim_rs::{glam_ext::glam::Vec3A, math::Angle};
+use serde::{Deserialize, Serialize};
+use std::{f32::consts::PI, io, path::Path};
+
+/// Buckets per input dimension, 6 dimensions make `4^6 = 4096` cells
+pub const BINS: usize = 4;
+
+/// Identifies the dataset `--fill-gaps` sampled around, stored in the metadata
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GapReference {
+    /// The dataset folder, as passed to `--fill-gaps`
+    pub path: String,
+    pub num_files: usize,
+    pub num_records: u64,
+    /// 64-bit FNV-1a hash of every file's decompressed records, in file order
+    pub records_hash: u64,
+}
+
+/// How many records of a reference dataset fall in every cell of a grid over the
+/// car-relative initial angular velocity and the relative target angles
+pub struct OccupancyGrid {
+    counts: Vec<u32>,
+    /// The mean count of the occupied cells, every cell at or below it is always sampled
+    mean_occupied: f32,
+}
+
+impl OccupancyGrid {
+    pub fn from_dataset(dir: &Path) -> io::Result<(Self, GapReference)> {
+        let files = reader::dataset_files(dir)?;
+        let mut counts = vec![0u32; BINS.pow(6)];
+        let mut records_hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut num_records = 0;
+
+        for dataset_file in &files {
+            let file = dataset_file
+                .open()
+                .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+            for &byte in &file.records {
+                records_hash = (records_hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+
+            for (ang_vel, relative_target) in file.inputs() {
+                counts[cell(ang_vel, relative_target)] += 1;
+                num_records += 1;
+            }
+        }
+
+        if num_records == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no records found in {}", dir.display()),
+            ));
+        }
+
+        let num_occupied = counts.iter().filter(|&&count| count != 0).count();
+        let grid = Self {
+            counts,
+            mean_occupied: num_records as f32 / num_occupied as f32,
+        };
+
+        let reference = GapReference {
+            path: dir.display().to_string(),
+            num_files: files.len(),
+            num_records,
+            records_hash,
+        };
+
+        Ok((grid, reference))
+    }
+
+    /// The number of records in every cell, indexed like `cell_bins`
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// The probability of keeping a sample with these inputs, 1 for cells with at most the mean count
+    /// and lower the more the reference over-represents the cell, so the samples even out the coverage
+    pub fn acceptance(&self, ang_vel: Vec3A, relative_target: Angle) -> f32 {
+        let count = self.counts[cell(ang_vel, relative_target)] as f32;
+        ((self.mean_occupied + 1.) / (count + 1.)).min(1.)
+    }
+}
+
+/// The input of every dimension of the grid, with its unit
+pub const DIMENSIONS: [(&str, &str); 6] = [
+    ("ang_vel_x", "rad/s"),
+    ("ang_vel_y", "rad/s"),
+    ("ang_vel_z", "rad/s"),
+    ("target_pitch", "rad"),
+    ("target_yaw", "rad"),
+    ("target_roll", "rad"),
+];
+
+/// The bucket of every dimension of `cell`, in the order of `DIMENSIONS`
+pub fn cell_bins(cell: usize) -> [usize; 6] {
+    std::array::from_fn(|dim| cell / BINS.pow(5 - dim as u32) % BINS)
+}
+
+/// The range of the values of the dimension `dim` in its bucket `bin`
+pub fn bin_range(dim: usize, bin: usize) -> (f32, f32) {
+    let max = if dim < 3 { MAX_ANG_VEL } else { PI };
+    let width = 2. * max / BINS as f32;
+
+    (-max + bin as f32 * width, -max + (bin + 1) as f32 * width)
+}
+
+/// The bucket of `value` in `[-max, max]`, clamped to the outer buckets
+fn bin(value: f32, max: f32) -> usize {
+    (((value / max + 1.) / 2. * BINS as f32) as usize).min(BINS - 1)
+}
+
+fn cell(ang_vel: Vec3A, relative_target: Angle) -> usize {
+    [
+        bin(ang_vel.x, MAX_ANG_VEL),
+        bin(ang_vel.y, MAX_ANG_VEL),
+        bin(ang_vel.z, MAX_ANG_VEL),
+        bin(relative_target.pitch, PI),
+        bin(relative_target.yaw, PI),
+        bin(relative_target.roll, PI),
+    ]
+    .into_iter()
+    .fold(0, |cell, bin| cell * BINS + bin)
+}
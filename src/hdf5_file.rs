@@ -0,0 +1,72 @@
+use crate::{format::Header, simulation::SimResult, table};
+use hdf5::{types::VarLenUnicode, File, Group, H5Type, Result};
+use std::{fs, path::Path};
+
+/// Rows per chunk of every dataset
+const CHUNK_ROWS: usize = 1 << 16;
+/// gzip level of every chunk
+const DEFLATE_LEVEL: u8 = 4;
+
+/// Appends `results` to the HDF5 file at `path`, creating it with a chunked, compressed 1-D dataset for every
+/// `table::columns` in the `samples` group and the header as JSON in its `header` attribute.
+/// Returns the size of the values and how much the file grew
+pub fn append(path: &Path, header: &Header, results: &[SimResult]) -> Result<(u64, u64)> {
+    let old_size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+    let header_json = serde_json::to_string(header).unwrap();
+
+    let file = File::append(path)?;
+    let samples = if file.link_exists("samples") {
+        // a file only ever holds the samples of the same options
+        let old_header = file.attr("header")?.read_scalar::<VarLenUnicode>()?;
+        if old_header.as_str() != header_json {
+            return Err(format!("{} holds samples gathered with other options", path.display()).into());
+        }
+
+        file.group("samples")?
+    } else {
+        file.new_attr::<VarLenUnicode>()
+            .create("header")?
+            .write_scalar(&header_json.parse::<VarLenUnicode>().unwrap())?;
+        file.create_group("samples")?
+    };
+
+    let columns = table::columns(header);
+    let rows = results.iter().map(|result| table::row(header, result)).collect::<Vec<_>>();
+
+    for (i, column) in columns.iter().enumerate() {
+        if column.integer {
+            let values = rows.iter().map(|row| row[i] as i32).collect::<Vec<_>>();
+            append_column(&samples, column.name, &values)?;
+        } else {
+            let values = rows.iter().map(|row| row[i]).collect::<Vec<_>>();
+            append_column(&samples, column.name, &values)?;
+        }
+    }
+
+    file.flush()?;
+    drop(file);
+
+    let uncompressed_size = (rows.len() * columns.len() * 4) as u64;
+    let new_size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+
+    Ok((uncompressed_size, new_size.saturating_sub(old_size)))
+}
+
+/// Appends `values` to the end of the resizable dataset `name` in `group`, creating it if needed
+fn append_column<T: H5Type>(group: &Group, name: &str, values: &[T]) -> Result<()> {
+    let dataset = if group.link_exists(name) {
+        group.dataset(name)?
+    } else {
+        group
+            .new_dataset::<T>()
+            .shape(0..)
+            .chunk(CHUNK_ROWS)
+            .deflate(DEFLATE_LEVEL)
+            .create(name)?
+    };
+
+    let start = dataset.shape()[0];
+    let end = start + values.len();
+    dataset.resize(end)?;
+    dataset.write_slice(values, start..end)
+}
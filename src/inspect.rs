@@ -0,0 +1,99 @@
+use crate::reader::{self, DatasetFile, ResultsFile};
+use std::{fs, io, path::Path};
+
+/// Prints the header, the number of records and the compression ratio of the results file at `path`,
+/// or of every chunk of a `dataset.bin`, and the min, max, mean and standard deviation of every value of its records
+pub fn inspect(path: &Path) -> io::Result<()> {
+    for dataset_file in reader::files_at(path)? {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        let size = match &dataset_file {
+            DatasetFile::File(path) => fs::metadata(path)?.len(),
+            DatasetFile::Chunk { len, .. } => *len,
+        };
+
+        print_file(&dataset_file, &file, size);
+    }
+
+    Ok(())
+}
+
+fn print_file(dataset_file: &DatasetFile, file: &ResultsFile, size: u64) {
+    let header = &file.header;
+    let num_records = file.records.len() / header.record_size();
+
+    println!("{dataset_file}:");
+    println!(
+        "  format version {}, {num_records} records of {} bytes",
+        file.format_version,
+        header.record_size()
+    );
+    println!(
+        "  {} -> {size} bytes ({:.2}x) with {:?} compression",
+        file.records.len(),
+        file.records.len() as f64 / size as f64,
+        header.compression
+    );
+    println!(
+        "  {:?} precision, {:?} time codec, {:?} byte order, {:?} layout",
+        header.precision, header.time_codec, header.byte_order, header.layout
+    );
+    if let Some(version) = &header.metadata.rocketsim_rs_version {
+        println!("  gathered with rocketsim_rs {version}");
+    }
+
+    println!(
+        "  {:<20} {:<8} {:>12} {:>12} {:>12} {:>12}",
+        "name", "unit", "min", "max", "mean", "std"
+    );
+
+    // the time in seconds whatever its codec, since the delta ticks only add up to it
+    let time_index = header.num_inputs();
+
+    for (i, field) in file.schema.iter().enumerate() {
+        let (values, unit) = if i == time_index {
+            (file.times().into_iter().map(f64::from).collect(), "s")
+        } else {
+            (file.field_values(i), field.unit.as_str())
+        };
+
+        let Some(stats) = Stats::new(&values) else {
+            println!("  {:<20} {unit:<8}", field.name);
+            continue;
+        };
+
+        println!(
+            "  {:<20} {unit:<8} {:>12.5} {:>12.5} {:>12.5} {:>12.5}",
+            field.name, stats.min, stats.max, stats.mean, stats.std
+        );
+    }
+}
+
+struct Stats {
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// Population standard deviation
+    std: f64,
+}
+
+impl Stats {
+    /// `None` without any values
+    fn new(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let len = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / len;
+        let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / len;
+
+        Some(Self {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            std: variance.sqrt(),
+        })
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{format::Header, simulation::SimResult, table};
+use serde_json::{Map, Number, Value};
+
+/// A line of JSON for every result, an object of every `table::columns` and its value
+pub fn serialize(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let columns = table::columns(header);
+    let mut bytes = Vec::new();
+
+    for result in results {
+        let record = columns
+            .iter()
+            .zip(table::row(header, result))
+            .map(|(column, value)| {
+                // through the f32's shortest representation, which the f64 it widens to doesn't have,
+                // NaN, e.g. a response of a sample that reached the target sooner, has no JSON number and is null
+                let value = if column.integer {
+                    Value::from(value as i64)
+                } else {
+                    value.to_string().parse::<Number>().map_or(Value::Null, Value::Number)
+                };
+
+                (column.name.to_string(), value)
+            })
+            .collect::<Map<_, _>>();
+
+        serde_json::to_writer(&mut bytes, &record).unwrap();
+        bytes.push(b'\n');
+    }
+
+    bytes
+}
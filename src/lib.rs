@@ -0,0 +1,108 @@
+//! Gathers how long a car takes to turn towards a target in RocketSim, for training models of aerial control.
+//!
+//! The `stat-final-data` binary is a thin wrapper around [`run`]. Other projects can embed the generator instead:
+//! after `rocketsim_rs::init`, build a [`simulation::Simulation`] from a [`config::Config`], draw samples from it with
+//! [`simulation::Simulation::do_random`] and write them with the functions of [`writer`].
+//! [`reader::ResultReader`] reads them back.
+
+#[cfg(feature = "arrow")]
+mod arrow_file;
+mod capnp;
+mod checkpoint;
+mod compare;
+pub mod config;
+pub mod controller;
+mod convert;
+mod coverage;
+mod crc;
+mod csv;
+mod dataset_file;
+mod dedup;
+mod f16;
+pub mod format;
+mod gaps;
+#[cfg(feature = "hdf5")]
+mod hdf5_file;
+mod inspect;
+mod jsonl;
+pub mod loader;
+#[cfg(feature = "mmap")]
+pub mod mapped;
+mod merge;
+mod migrate;
+mod normalize;
+mod npz;
+mod outliers;
+mod pack;
+#[cfg(feature = "parquet")]
+mod parquet_file;
+mod percentile;
+#[cfg(feature = "protobuf")]
+mod proto;
+#[cfg(feature = "python")]
+mod python;
+pub mod reader;
+mod rebalance;
+mod regress;
+mod replay;
+mod reproduce;
+mod run;
+pub mod sampler;
+mod shuffle;
+pub mod simulation;
+mod split;
+#[cfg(feature = "sqlite")]
+mod sqlite_file;
+mod stats;
+mod stream;
+mod table;
+mod tfrecord;
+mod trace;
+#[cfg(feature = "upload")]
+mod upload;
+mod verify;
+pub mod writer;
+
+use gaps::OccupancyGrid;
+use rocketsim_rs::sim::ArenaMemWeightMode;
+use std::sync::{
+    atomic::{AtomicBool, AtomicI32, AtomicU64},
+    OnceLock,
+};
+
+pub use run::run;
+
+/// Physics ticks per second of every arena
+pub const TICK_RATE: f32 = 120.;
+/// Memory weight mode of every arena, with its name for the metadata
+const ARENA_MEM_WEIGHT_MODE: (ArenaMemWeightMode, &str) = (ArenaMemWeightMode::HEAVY, "heavy");
+/// The rocketsim_rs version in Cargo.lock, RocketSim itself doesn't expose its version
+const ROCKETSIM_RS_VERSION: &str = env!("ROCKETSIM_RS_VERSION");
+/// Largest magnitude of the sampled initial angular velocity, by default
+const MAX_ANG_VEL: f32 = 5.5;
+/// A sample is abandoned after this much simulated time, by default
+const MAX_SIM_SECONDS: f32 = 30.;
+/// The target is reached once the car's forward axis is within this many radians of it, by default
+const CONVERGENCE_THRESHOLD: f32 = 0.1;
+/// Default tolerance of every validation check, e.g. how far `R^T * R` may be from the identity.
+///
+/// f32 rounding accumulated over a full `MAX_SIM_SECONDS` sample stays well below this,
+/// while a rotation that has actually been skewed or scaled by bad physics is off by orders of magnitude more.
+const VALIDATION_EPSILON: f32 = 1e-3;
+
+/// Set by Ctrl+C, workers send the results of their current batch and stop
+static STOPPING: AtomicBool = AtomicBool::new(false);
+/// Set by the `pause` command, workers stop producing results while keeping their arenas
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// The `--interval` in milliseconds, read by the workers at the start of every batch so it can be reloaded
+static INTERVAL_MILLIS: AtomicU64 = AtomicU64::new(0);
+/// The `--zstd-level` of the next file written, so it can be reloaded
+static ZSTD_LEVEL: AtomicI32 = AtomicI32::new(0);
+/// Every record in the output folder, resumed from the checkpoint, for `--max-samples`
+static NUM_RECORDS: AtomicU64 = AtomicU64::new(0);
+/// Every sample the workers finished, recorded or not, for `--heartbeat`
+static NUM_SAMPLES: AtomicU64 = AtomicU64::new(0);
+/// The occupancy of the `--fill-gaps` reference dataset, built before the workers start
+static GAP_GRID: OnceLock<OccupancyGrid> = OnceLock::new();
+/// The `--zstd-dictionary` every results file is compressed with, trained on the first batch
+static ZSTD_DICTIONARY: OnceLock<Vec<u8>> = OnceLock::new();
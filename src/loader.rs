@@ -0,0 +1,198 @@
+use crate::{
+    format::Header,
+    reader::{self, DatasetFile},
+    simulation::SimResult,
+    table,
+};
+use crossbeam_channel::{bounded, Receiver};
+use fastrand::Rng;
+use std::{io, path::Path, thread};
+
+/// How a `Loader` batches and shuffles the records
+#[derive(Clone, Copy, Debug)]
+pub struct LoaderOptions {
+    /// Records in every batch, the last batch of an epoch can have fewer
+    pub batch_size: usize,
+    /// Records the batches are drawn from at random, the larger the closer to a full shuffle
+    pub buffer_size: usize,
+    /// Batches read ahead by the thread reading the files
+    pub prefetch: usize,
+    /// Every epoch is shuffled with a seed drawn from it, so the same epoch always has the same batches
+    pub seed: u64,
+    /// Skips the last batch of an epoch if it has fewer than `batch_size` records
+    pub drop_last: bool,
+}
+
+impl Default for LoaderOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 256,
+            buffer_size: 65536,
+            prefetch: 4,
+            seed: 0,
+            drop_last: false,
+        }
+    }
+}
+
+/// A mini-batch of records
+pub struct Batch {
+    /// The values of the `Loader::columns` of every record, record by record, scaled like the files
+    pub values: Vec<f32>,
+    /// The records the values were taken from, e.g. for their seeds
+    pub results: Vec<SimResult>,
+}
+
+impl Batch {
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+/// Streams shuffled mini-batches from the files of a dataset folder, for training without loading every record at once.
+///
+/// Every epoch visits the files in a shuffled order and keeps `buffer_size` of their records, drawing every record
+/// of a batch at random from them and replacing it with the next record read
+pub struct Loader {
+    files: Vec<DatasetFile>,
+    columns: Vec<&'static str>,
+    options: LoaderOptions,
+}
+
+impl Loader {
+    /// Lists the files at `path`, a results file or a dataset folder with its worker folders,
+    /// reading the first one for the columns every file needs to have
+    pub fn open(path: &Path, options: LoaderOptions) -> io::Result<Self> {
+        if options.batch_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the batch size can't be 0"));
+        }
+
+        let files = reader::files_or_dataset(path)?;
+        let first = files
+            .first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no results files in {}", path.display())))?;
+
+        let header = first
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {first}: {e}")))?
+            .header;
+
+        Ok(Self {
+            columns: column_names(&header),
+            files,
+            options,
+        })
+    }
+
+    /// The names of the values of every record of a batch, those of the tabular formats, like `ang_vel_x` and `time`
+    pub fn columns(&self) -> &[&'static str] {
+        &self.columns
+    }
+
+    /// The batches of the `epoch`th pass over the files, read by a background thread `prefetch` batches ahead.
+    /// A file that can't be read or doesn't have the columns of the first ends the epoch with its error
+    pub fn epoch(&self, epoch: u64) -> Epoch {
+        let LoaderOptions {
+            batch_size,
+            buffer_size,
+            prefetch,
+            seed,
+            drop_last,
+        } = self.options;
+
+        // like the workers' seeds are drawn from the run's seed
+        let mut seeds = Rng::with_seed(seed);
+        let mut rng = Rng::with_seed((0..=epoch).map(|_| seeds.u64(..)).last().unwrap());
+
+        let mut files = self.files.clone();
+        rng.shuffle(&mut files);
+        let columns = self.columns.clone();
+
+        let (tx, rx) = bounded(prefetch);
+        thread::spawn(move || {
+            let mut headers = Vec::new();
+            let mut buffer = Vec::with_capacity(buffer_size + 1);
+            let mut batch = Batch {
+                values: Vec::with_capacity(batch_size * columns.len()),
+                results: Vec::with_capacity(batch_size),
+            };
+
+            // moves a random record of the buffer to the batch, sending it once it's full
+            let mut draw = |buffer: &mut Vec<(SimResult, usize)>, headers: &[Header], rng: &mut Rng| {
+                let (result, header) = buffer.swap_remove(rng.usize(..buffer.len()));
+                batch.values.extend(table::row(&headers[header], &result));
+                batch.results.push(result);
+
+                if batch.len() == batch_size {
+                    let full = Batch {
+                        values: Vec::with_capacity(batch_size * columns.len()),
+                        results: Vec::with_capacity(batch_size),
+                    };
+                    return tx.send(Ok(std::mem::replace(&mut batch, full))).is_ok();
+                }
+
+                true
+            };
+
+            for dataset_file in files {
+                let file = match dataset_file.open() {
+                    Ok(file) if column_names(&file.header) == columns => file,
+                    Ok(_) => {
+                        let _ = tx.send(Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("{dataset_file} doesn't have the same columns as the first file"),
+                        )));
+                        return;
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}"))));
+                        return;
+                    }
+                };
+
+                headers.push(file.header.clone());
+                for result in file.results() {
+                    buffer.push((result, headers.len() - 1));
+
+                    // stops once the epoch is dropped
+                    if buffer.len() > buffer_size && !draw(&mut buffer, &headers, &mut rng) {
+                        return;
+                    }
+                }
+            }
+
+            while !buffer.is_empty() {
+                if !draw(&mut buffer, &headers, &mut rng) {
+                    return;
+                }
+            }
+
+            if !batch.is_empty() && !drop_last {
+                let _ = tx.send(Ok(batch));
+            }
+        });
+
+        Epoch { rx }
+    }
+}
+
+/// The batches of an epoch of a `Loader`, reading stops when it's dropped
+pub struct Epoch {
+    rx: Receiver<io::Result<Batch>>,
+}
+
+impl Iterator for Epoch {
+    type Item = io::Result<Batch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+fn column_names(header: &Header) -> Vec<&'static str> {
+    table::columns(header).into_iter().map(|column| column.name).collect()
+}
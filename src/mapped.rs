@@ -0,0 +1,139 @@
+use crate::{
+    crc::{self, CASTAGNOLI},
+    format::{self, ByteOrder, Compression, Header, Layout, SchemaField, Sidecar, MAGIC},
+};
+use memmap2::Mmap;
+use std::{fs, io, ops::Range, path::Path};
+
+/// An uncompressed `--layout columns` results file mapped into memory, whose `f32` columns are read in place
+/// as the OS pages them in, without copying or decoding them
+pub struct MappedFile {
+    pub header: Header,
+    /// The name, type, unit, frame and scale of every column, from the header's schema block
+    pub schema: Vec<SchemaField>,
+    mmap: Mmap,
+    /// Where the records are in the file, after the embedded header and before the footer
+    records: Range<usize>,
+    num_records: usize,
+    /// The CRC-32C of the records from the footer, `None` for the files with a `--sidecar-metadata` sidecar
+    checksum: Option<u32>,
+}
+
+impl MappedFile {
+    /// Maps a results file written with `--compression none --layout columns`, with an embedded header or a
+    /// `--sidecar-metadata` sidecar, in the byte order of this machine. Only the header and the footer are read,
+    /// `verify` checks the records against the footer's checksum
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        // SAFETY: results files are written to `{name}.tmp` and renamed into place, never changed once they're in it
+        let mmap = unsafe { Mmap::map(&fs::File::open(path)?)? };
+
+        let (header, schema, records, num_records, checksum) = if mmap.starts_with(&MAGIC) {
+            let mut bytes = &mmap[..];
+            let (header, schema, _) = Header::read_with_schema(&mut bytes)?;
+            let records_start = mmap.len() - bytes.len();
+            let (records, num_records, checksum) = format::split_footer(bytes)?;
+
+            if header.compression != Compression::None {
+                return Err(invalid(format!(
+                    "{}'s records are compressed, only files written with --compression none can be mapped",
+                    path.display()
+                )));
+            }
+
+            let records = records_start..records_start + records.len();
+            (header, schema, records, num_records, Some(checksum))
+        } else {
+            let sidecar = Sidecar::read_from(&path.with_extension("json"))?;
+            (sidecar.header, sidecar.schema, 0..mmap.len(), sidecar.num_records, None)
+        };
+
+        if header.layout != Layout::Columns {
+            return Err(invalid(format!(
+                "{} holds its records in rows, only files written with --layout columns can be mapped",
+                path.display()
+            )));
+        }
+
+        let native_order = if cfg!(target_endian = "little") {
+            ByteOrder::Little
+        } else {
+            ByteOrder::Big
+        };
+        if header.byte_order != native_order {
+            return Err(invalid(format!(
+                "{} isn't in the byte order of this machine, write it with --byte-order {}",
+                path.display(),
+                if native_order == ByteOrder::Little { "little" } else { "big" }
+            )));
+        }
+
+        if records.len() as u64 != num_records * header.record_size() as u64 {
+            return Err(invalid(format!(
+                "{} holds {} bytes of records, but should hold {num_records} records of {} bytes",
+                path.display(),
+                records.len(),
+                header.record_size()
+            )));
+        }
+
+        Ok(Self {
+            header,
+            schema,
+            mmap,
+            records,
+            num_records: num_records as usize,
+            checksum,
+        })
+    }
+
+    pub const fn num_records(&self) -> usize {
+        self.num_records
+    }
+
+    /// The bytes of the `index`th column of the schema, a value of its type for every record
+    pub fn column_bytes(&self, index: usize) -> &[u8] {
+        let sizes = self.header.value_sizes();
+        let start = self.records.start + sizes[..index].iter().sum::<usize>() * self.num_records;
+
+        &self.mmap[start..start + sizes[index] * self.num_records]
+    }
+
+    /// The `index`th column of the schema as stored, to be multiplied by its scale to get the value in its unit.
+    /// `None` if its values aren't `f32`, like the ids, the seed, the time with the tick codecs or every value with
+    /// `--precision f16` or `i16`, or if they aren't 4-byte aligned, after a `u8` column of a record count that isn't
+    /// a multiple of 4
+    pub fn column(&self, index: usize) -> Option<&[f32]> {
+        if self.schema[index].value_type != "f32" {
+            return None;
+        }
+
+        // SAFETY: every bit pattern is a valid f32
+        let (before, values, after) = unsafe { self.column_bytes(index).align_to::<f32>() };
+        (before.is_empty() && after.is_empty()).then_some(values)
+    }
+
+    /// The `f32` column of the schema named `name`, see `column`
+    pub fn column_named(&self, name: &str) -> Option<&[f32]> {
+        let index = self.schema.iter().position(|field| field.name == name)?;
+        self.column(index)
+    }
+
+    /// Reads every record once to check them against the footer's checksum, the files with a sidecar don't have one
+    pub fn verify(&self) -> io::Result<()> {
+        let Some(checksum) = self.checksum else {
+            return Ok(());
+        };
+
+        if crc::crc32(CASTAGNOLI, &self.mmap[self.records.clone()]) != checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the records don't match their checksum, the file is corrupted",
+            ));
+        }
+
+        Ok(())
+    }
+}
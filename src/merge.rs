@@ -0,0 +1,193 @@
+use crate::{
+    format::{Header, Layout, SchemaField, TimeCodec},
+    reader::{self, DatasetFile, ResultsFile},
+    writer::write_serialized,
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Metadata keys that can differ between the merged files, only kept when every file has the same value
+const PER_FILE_KEYS: [&str; 6] = [
+    "seed",
+    "records_per_file",
+    "difficulty_band",
+    "zstd_dictionary_id",
+    "zstd_level",
+    "zstd_workers",
+];
+
+/// The header of the records of several files written as one, which fails to add a file that has another schema
+/// or was gathered with other options than the first
+#[derive(Default)]
+pub struct MergedHeader {
+    /// The header of the first file with the `PER_FILE_KEYS` that differ cleared, and its `shared_metadata`
+    first: Option<(Header, serde_json::Value)>,
+    schema: Vec<SchemaField>,
+    first_file: String,
+}
+
+impl MergedHeader {
+    pub fn add(&mut self, dataset_file: &DatasetFile, file: &ResultsFile) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let metadata = shared_metadata(&file.header);
+
+        let Some((header, first_metadata)) = &mut self.first else {
+            self.schema.clone_from(&file.schema);
+            self.first = Some((file.header.clone(), metadata));
+            self.first_file = dataset_file.to_string();
+            return Ok(());
+        };
+
+        if file.schema != self.schema
+            || file.header.byte_order != header.byte_order
+            || file.header.tick_rate != header.tick_rate
+        {
+            return Err(invalid(format!("{dataset_file} has another schema than {}", self.first_file)));
+        }
+
+        if metadata != *first_metadata {
+            return Err(invalid(format!(
+                "{dataset_file} was gathered with other options than {}",
+                self.first_file
+            )));
+        }
+
+        let file_metadata = &file.header.metadata;
+        if header.metadata.seed != file_metadata.seed {
+            header.metadata.seed = None;
+        }
+        if header.metadata.records_per_file != file_metadata.records_per_file {
+            header.metadata.records_per_file = None;
+        }
+        if header.metadata.difficulty_band != file_metadata.difficulty_band {
+            header.metadata.difficulty_band = None;
+        }
+
+        Ok(())
+    }
+
+    /// The header of the merged records, `None` if no file was added
+    pub fn finish(self) -> Option<Header> {
+        let (mut header, _) = self.first?;
+
+        // the records were read into rows, and are compressed again without the dictionary, at the default level
+        header.layout = Layout::Rows;
+        header.metadata.zstd_dictionary_id = None;
+        header.metadata.zstd_level = None;
+        header.metadata.zstd_workers = None;
+
+        Some(header)
+    }
+}
+
+/// Every results file in `inputs`, or in the dataset folders among them, in order
+pub fn input_files(inputs: &[PathBuf]) -> io::Result<Vec<DatasetFile>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        files.extend(reader::files_or_dataset(input)?);
+    }
+
+    Ok(files)
+}
+
+/// Concatenates the records of every results file in `inputs`, or in the dataset folders among them,
+/// into one file at `output` with the header of the first file, failing if any file has another schema
+/// or was gathered with other options
+pub fn merge(inputs: &[PathBuf], output: &Path) -> io::Result<()> {
+    let files = input_files(inputs)?;
+
+    let mut merged = MergedHeader::default();
+    let mut records = Vec::new();
+    // with `TimeCodec::DeltaTicks`, the ticks of the last record merged so far
+    let mut last_ticks = 0;
+
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        merged.add(dataset_file, &file)?;
+
+        let mut file_records = file.records;
+        if file.header.time_codec == TimeCodec::DeltaTicks {
+            last_ticks = rebase_delta_ticks(&file.header, &mut file_records, last_ticks);
+        }
+
+        records.extend(file_records);
+    }
+
+    let Some(header) = merged.finish() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no results files to merge"));
+    };
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    let num_records = records.len() / header.record_size();
+    write_serialized(
+        &output.to_string_lossy(),
+        &header_bytes,
+        header.compression,
+        &records,
+        num_records,
+    )?;
+
+    println!(
+        "Merged {num_records} records from {} files into {}",
+        files.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// The metadata of `header` without `PER_FILE_KEYS`, which has to be the same for files to be merged
+fn shared_metadata(header: &Header) -> serde_json::Value {
+    let mut metadata = serde_json::to_value(&header.metadata).unwrap();
+    for key in PER_FILE_KEYS {
+        metadata.as_object_mut().unwrap().remove(key);
+    }
+
+    metadata
+}
+
+/// Makes the first delta of `records`, which every file counts from 0 ticks, count from `last_ticks` instead,
+/// returning the ticks of the last record
+fn rebase_delta_ticks(header: &Header, records: &mut [u8], last_ticks: i32) -> i32 {
+    let order = header.byte_order;
+    // the time follows the base inputs present
+    let time_offset = header.num_inputs() * header.precision.size();
+    let mut ticks = 0;
+
+    for (i, record) in records.chunks_exact_mut(header.record_size()).enumerate() {
+        let time = &mut record[time_offset..time_offset + 4];
+        let delta = order.read_i32(time.try_into().unwrap());
+        ticks += delta;
+
+        if i == 0 {
+            time.copy_from_slice(&order.i32(delta - last_ticks));
+        }
+    }
+
+    // a file without records leaves the last ticks as they were
+    if records.is_empty() {
+        last_ticks
+    } else {
+        ticks
+    }
+}
+
+/// Replaces the delta ticks of every record of `records` with the ticks they add up to, like `TimeCodec::Ticks`,
+/// so no record depends on the one before it
+pub fn absolute_ticks(header: &Header, records: &mut [u8]) {
+    let order = header.byte_order;
+    let time_offset = header.num_inputs() * header.precision.size();
+    let mut ticks = 0;
+
+    for record in records.chunks_exact_mut(header.record_size()) {
+        let time = &mut record[time_offset..time_offset + 4];
+        ticks += order.read_i32(time.try_into().unwrap());
+        time.copy_from_slice(&order.u32(ticks as u32));
+    }
+}
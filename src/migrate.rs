@@ -0,0 +1,127 @@
+use crate::{
+    config::Config,
+    format::{Header, Metadata, MAGIC},
+    reader::{self, DatasetFile},
+    writer::write_serialized,
+    MAX_SIM_SECONDS, TICK_RATE, VALIDATION_EPSILON,
+};
+use clap::Parser;
+use std::{f32::consts::PI, fs, io, path::Path};
+
+/// The car-relative initial angular velocity, the relative target's pitch, yaw and roll and the seconds to reach it
+const LEGACY_RECORD_SIZE: usize = 7 * 4;
+/// The first bytes of a zstd frame, for the versions that compressed the headerless records
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+/// Ticks a legacy time may be off the tick grid by, from the f32 rounding of dividing the ticks by the tick rate
+const TICK_TOLERANCE: f32 = 0.01;
+
+/// The header of the default options, whose records are laid out like the headerless ones,
+/// with placeholder metadata marked as migrated
+pub fn legacy_header() -> Header {
+    let config = Config::parse_from([env!("CARGO_PKG_NAME")]);
+    let mut header = Header::new(&config);
+    header.metadata = Metadata {
+        migrated: true,
+        ..Metadata::default()
+    };
+
+    header
+}
+
+/// The records of the headerless file at `path` holding `bytes`, decompressed if the versions that compressed them wrote it,
+/// failing unless they're a whole number of records with finite values and times on the tick grid of a finished sample
+pub fn legacy_records(path: &Path, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {message}", path.display()));
+
+    let records = if bytes.starts_with(&ZSTD_MAGIC) {
+        zstd::stream::decode_all(&bytes[..]).map_err(|e| invalid(format!("its zstd frame doesn't decode: {e}")))?
+    } else {
+        bytes
+    };
+
+    if records.len() % LEGACY_RECORD_SIZE != 0 {
+        return Err(invalid(format!(
+            "it has neither a header nor a whole number of {LEGACY_RECORD_SIZE}-byte records"
+        )));
+    }
+
+    for (i, record) in records.chunks_exact(LEGACY_RECORD_SIZE).enumerate() {
+        let values = record
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        if values.iter().any(|value| !value.is_finite()) {
+            return Err(invalid(format!("record {i} has a value that isn't finite")));
+        }
+
+        if values[3..6].iter().any(|angle| angle.abs() > PI + VALIDATION_EPSILON) {
+            return Err(invalid(format!("record {i} has a target angle outside [-pi, pi]")));
+        }
+
+        let ticks = values[6] * TICK_RATE;
+        if ticks <= 0. || values[6] > MAX_SIM_SECONDS + VALIDATION_EPSILON || (ticks - ticks.round()).abs() > TICK_TOLERANCE
+        {
+            return Err(invalid(format!(
+                "record {i} took {} seconds, which isn't a whole number of ticks of a finished sample",
+                values[6]
+            )));
+        }
+    }
+
+    Ok(records)
+}
+
+/// Rewrites every headerless file of `dir` with `legacy_header`, the records compressed with zstd and the footer.
+/// Every file is checked before any is rewritten, so a folder with one that doesn't parse is left as it was
+pub fn migrate(dir: &Path) -> io::Result<()> {
+    let header = legacy_header();
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    let mut legacy_files = Vec::new();
+    for file in reader::dataset_files(dir)? {
+        // the chunks of a --single-file dataset always have a header
+        let DatasetFile::File(path) = file else {
+            continue;
+        };
+
+        // the records of a --sidecar-metadata file are headerless too, but their header is in the sidecar
+        if path.with_extension("json").exists() {
+            continue;
+        }
+
+        let bytes = fs::read(&path)?;
+        if bytes.starts_with(&MAGIC) {
+            continue;
+        }
+
+        // only checked here, the records of terabytes of files don't fit in memory together
+        legacy_records(&path, bytes)?;
+        legacy_files.push(path);
+    }
+
+    let mut num_migrated = 0;
+    let mut num_records = 0;
+
+    for path in legacy_files {
+        let records = legacy_records(&path, fs::read(&path)?)?;
+        let file_records = records.len() / LEGACY_RECORD_SIZE;
+        write_serialized(
+            &path.to_string_lossy(),
+            &header_bytes,
+            header.compression,
+            &records,
+            file_records,
+        )?;
+
+        println!("Migrated {} with {file_records} records", path.display());
+        num_migrated += 1;
+        num_records += file_records;
+    }
+
+    println!("Migrated {num_migrated} files with {num_records} records in total");
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use crate::{
+    merge::{self, MergedHeader},
+    npz,
+    reader::ResultsFile,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// How `--scaled` copies scale every value with the normalization statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaling {
+    /// Minus the mean, divided by the standard deviation
+    #[default]
+    ZScore,
+    /// Minus the min, divided by the range, from 0 to 1
+    MinMax,
+}
+
+/// The values of the schema that identify rather than measure, left out of the statistics and never scaled
+const IDENTIFIERS: [&str; 4] = ["seed", "car_config_id", "controller_id", "difficulty_class"];
+
+/// Written as `normalization.json`, so every consumer of a dataset normalizes it the same way
+#[derive(Serialize)]
+struct Normalization<'a> {
+    /// Every input file, in order
+    sources: Vec<String>,
+    num_records: u64,
+    /// Every value of the schema but the `IDENTIFIERS`, in order
+    features: Vec<&'a FeatureStats>,
+}
+
+/// The statistics of a value of the schema, in its unit, with the time in seconds whatever its codec
+#[derive(Serialize)]
+struct FeatureStats {
+    name: String,
+    unit: String,
+    mean: f64,
+    /// The population standard deviation
+    std: f64,
+    min: f64,
+    max: f64,
+}
+
+impl FeatureStats {
+    fn new(name: String, unit: String) -> Self {
+        Self {
+            name,
+            unit,
+            mean: 0.,
+            std: 0.,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// `value` scaled by `scaling`, leaving out the division when the value never changes
+    fn scale(&self, value: f64, scaling: Scaling) -> f64 {
+        let (offset, range) = match scaling {
+            Scaling::ZScore => (self.mean, self.std),
+            Scaling::MinMax => (self.min, self.max - self.min),
+        };
+
+        if range > 0. {
+            (value - offset) / range
+        } else {
+            value - offset
+        }
+    }
+}
+
+/// Writes the mean, standard deviation, min and max of every value but the `IDENTIFIERS` of the records of the results files
+/// in `inputs`, or in the dataset folders among them, to `output`, and with `scaled`, the values of every input file
+/// scaled by `scaling` as an N×F `f32` array to `{scaled}/{n}.npy`, with `n` the index of the input file
+/// and the `IDENTIFIERS` as they are
+pub fn normalize(inputs: &[PathBuf], output: &Path, scaled: Option<&Path>, scaling: Scaling) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+
+    let mut merged = MergedHeader::default();
+    // `None` for the identifiers
+    let mut features: Vec<Option<FeatureStats>> = Vec::new();
+    let mut sums = Vec::new();
+    let mut num_records = 0;
+
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        merged.add(dataset_file, &file)?;
+
+        if features.is_empty() {
+            features = file
+                .schema
+                .iter()
+                .map(|field| {
+                    (!IDENTIFIERS.contains(&field.name.as_str()))
+                        .then(|| FeatureStats::new(field.name.clone(), field.unit.clone()))
+                })
+                .collect();
+            sums = vec![(0., 0.); features.len()];
+        }
+
+        for (i, values) in columns(&file).into_iter().enumerate() {
+            let Some(feature) = &mut features[i] else {
+                continue;
+            };
+
+            for value in values {
+                sums[i].0 += value;
+                sums[i].1 += value * value;
+                feature.min = feature.min.min(value);
+                feature.max = feature.max.max(value);
+            }
+        }
+
+        num_records += (file.records.len() / file.header.record_size()) as u64;
+    }
+
+    if num_records == 0 {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no records to normalize"));
+    }
+
+    let count = num_records as f64;
+    for (feature, (sum, sum_squares)) in features.iter_mut().zip(sums) {
+        let Some(feature) = feature else {
+            continue;
+        };
+
+        feature.mean = sum / count;
+        feature.std = (sum_squares / count - feature.mean * feature.mean).max(0.).sqrt();
+    }
+
+    let normalization = Normalization {
+        sources: files.iter().map(ToString::to_string).collect(),
+        num_records,
+        features: features.iter().flatten().collect(),
+    };
+    fs::write(output, serde_json::to_vec_pretty(&normalization)?)?;
+
+    for feature in &normalization.features {
+        println!(
+            "{:<20} {:<8} mean {:>12.4}, std {:>12.4}, min {:>12.4}, max {:>12.4}",
+            feature.name, feature.unit, feature.mean, feature.std, feature.min, feature.max
+        );
+    }
+    println!(
+        "Wrote the normalization of {num_records} records from {} files to {}",
+        files.len(),
+        output.display()
+    );
+
+    let Some(scaled) = scaled else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(scaled)?;
+    for (n, dataset_file) in files.iter().enumerate() {
+        let file = dataset_file.open()?;
+        let columns = columns(&file);
+        let num_file_records = file.records.len() / file.header.record_size();
+
+        let mut values = Vec::with_capacity(num_file_records * columns.len());
+        for record in 0..num_file_records {
+            for (feature, column) in features.iter().zip(&columns) {
+                let value = match feature {
+                    Some(feature) => feature.scale(column[record], scaling),
+                    None => column[record],
+                };
+                values.push(value as f32);
+            }
+        }
+
+        let path = scaled.join(format!("{n}.npy"));
+        fs::write(&path, npz::npy(&values, [num_file_records, columns.len()]))?;
+        println!("Wrote {} with {num_file_records} scaled records", path.display());
+    }
+
+    Ok(())
+}
+
+/// Every value of the schema of every record of `file`, in its unit, with the time in seconds whatever its codec
+fn columns(file: &ResultsFile) -> Vec<Vec<f64>> {
+    // the time follows the base inputs present, and the delta ticks only add up to it in order
+    let time_index = file.header.num_inputs();
+
+    (0..file.schema.len())
+        .map(|i| {
+            if i == time_index {
+                file.times().into_iter().map(f64::from).collect()
+            } else {
+                file.field_values(i)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+use crate::{
+    crc::{self, IEEE},
+    format::Header,
+    simulation::SimResult,
+};
+use std::io;
+
+/// The DOS date of every archive entry, 1980-01-01
+const ENTRY_DATE: u16 = (1 << 5) | 1;
+
+/// An uncompressed `.npz` archive, as written by `numpy.savez`, of `inputs`, an N×6 `f32` array of the car-relative
+/// initial angular velocity and the relative target's pitch, yaw and roll, less the omitted ones or with the quaternion,
+/// and `times`, an N×1 `f32` array of the seconds to reach the target, scaled like `format::serialize`.
+/// Fails if an array or the archive would reach 4 GiB, which needs zip64
+pub fn serialize(header: &Header, results: &[SimResult]) -> io::Result<Vec<u8>> {
+    let num_inputs = header.num_inputs();
+    let mut inputs = Vec::with_capacity(results.len() * num_inputs);
+    let mut times = Vec::with_capacity(results.len());
+
+    for result in results {
+        inputs.extend(header.inputs(result));
+        times.push(result.time / header.time_scale);
+    }
+
+    zip(&[
+        ("inputs.npy", npy(&inputs, [results.len(), num_inputs])),
+        ("times.npy", npy(&times, [results.len(), 1])),
+    ])
+}
+
+/// A little-endian `f32` array in the `.npy` format version 1.0
+pub fn npy(values: &[f32], shape: [usize; 2]) -> Vec<u8> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+    let mut dict = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        shape[0], shape[1]
+    );
+
+    // the data starts 64-byte aligned, after the magic, the length of the dict and the dict ending with a newline
+    let unpadded_len = MAGIC.len() + 2 + dict.len() + 1;
+    dict.extend(std::iter::repeat_n(' ', unpadded_len.next_multiple_of(64) - unpadded_len));
+    dict.push('\n');
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + dict.len() + values.len() * 4);
+    bytes.extend(MAGIC);
+    bytes.extend((dict.len() as u16).to_le_bytes());
+    bytes.extend(dict.as_bytes());
+    bytes.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+
+    bytes
+}
+
+/// A zip archive of the stored, uncompressed `files`, failing unless it's smaller than 4 GiB
+fn zip(files: &[(&str, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut central_directory = Vec::new();
+
+    for (name, data) in files {
+        let offset = zip_u32(bytes.len())?;
+        let size = zip_u32(data.len())?;
+
+        // the fields the local file header and the central directory header share
+        let mut fields = Vec::new();
+        fields.extend(20u16.to_le_bytes()); // version needed to extract, 2.0
+        fields.extend(0u16.to_le_bytes()); // flags
+        fields.extend(0u16.to_le_bytes()); // compression method, stored
+        fields.extend(0u16.to_le_bytes()); // modification time
+        fields.extend(ENTRY_DATE.to_le_bytes());
+        fields.extend(crc::crc32(IEEE, data).to_le_bytes());
+        fields.extend(size.to_le_bytes()); // compressed size
+        fields.extend(size.to_le_bytes()); // uncompressed size
+        fields.extend((name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes()); // extra field length
+
+        bytes.extend(0x0403_4b50u32.to_le_bytes());
+        bytes.extend(&fields);
+        bytes.extend(name.as_bytes());
+        bytes.extend(data);
+
+        central_directory.extend(0x0201_4b50u32.to_le_bytes());
+        central_directory.extend(20u16.to_le_bytes()); // version made by
+        central_directory.extend(&fields);
+        central_directory.extend(0u16.to_le_bytes()); // comment length
+        central_directory.extend(0u16.to_le_bytes()); // disk number
+        central_directory.extend(0u16.to_le_bytes()); // internal attributes
+        central_directory.extend(0u32.to_le_bytes()); // external attributes
+        central_directory.extend(offset.to_le_bytes());
+        central_directory.extend(name.as_bytes());
+    }
+
+    let central_directory_offset = zip_u32(bytes.len())?;
+    bytes.extend(&central_directory);
+
+    // end of central directory record
+    bytes.extend(0x0605_4b50u32.to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // this disk
+    bytes.extend(0u16.to_le_bytes()); // disk with the central directory
+    bytes.extend((files.len() as u16).to_le_bytes());
+    bytes.extend((files.len() as u16).to_le_bytes());
+    bytes.extend(zip_u32(central_directory.len())?.to_le_bytes());
+    bytes.extend(central_directory_offset.to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // comment length
+
+    Ok(bytes)
+}
+
+/// A size or offset of the archive as its 32-bit field, where `u32::MAX` would mean it's in a zip64 field instead
+fn zip_u32(value: usize) -> io::Result<u32> {
+    u32::try_from(value).ok().filter(|&value| value != u32::MAX).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{value} bytes don't fit in an npz archive without zip64, write fewer records per file"),
+        )
+    })
+}
@@ -0,0 +1,137 @@
+use crate::{
+    format::{self, Fields, TargetRotation},
+    merge,
+};
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Width of the buckets of the angular velocity magnitude the records are compared within, in rad/s
+const ANG_VEL_BIN_WIDTH: f32 = 0.5;
+/// Width of the buckets of the angle of the rotation to the target the records are compared within, in rad
+const ANGLE_BIN_WIDTH: f32 = PI / 8.;
+/// Buckets with fewer records don't have quartiles worth comparing against
+const MIN_BIN_RECORDS: usize = 20;
+
+/// A record of the inputs, where to find it and what it started from
+struct Record {
+    file: usize,
+    index: usize,
+    seed: Option<u64>,
+    ang_vel: f32,
+    angle: f32,
+    time: f32,
+}
+
+/// Writes every record of the results files in `inputs`, or in the dataset folders among them, whose time to reach the target
+/// is more than `iqr_factor` interquartile ranges outside the quartiles of the records with about the same
+/// initial angular velocity magnitude and angle of the rotation to the target to `output` as CSV, with their seeds to simulate them again
+pub fn outliers(inputs: &[PathBuf], output: &Path, iqr_factor: f32) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+
+    let mut bins: HashMap<(usize, usize), Vec<Record>> = HashMap::new();
+    let mut num_records = 0;
+
+    for (file_index, dataset_file) in files.iter().enumerate() {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        let has_seed = file.header.fields.contains(Fields::SEED);
+
+        // the angles are only the differences of the pitch, yaw and roll, not the rotation
+        if file.header.target_rotation == TargetRotation::Euler
+            && (!file.header.fields.contains(Fields::INITIAL_ORIENTATION) || file.header.omitted_inputs.bits() != 0)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{dataset_file} doesn't have the rotation to the target, write it with --target-rotation quaternion \
+                     or rotation6d, or with --record-initial-orientation and every input"
+                ),
+            ));
+        }
+
+        for (index, result) in file.results().into_iter().enumerate() {
+            let [w, ..] = format::quaternion(result.relative_rotation);
+            let record = Record {
+                file: file_index,
+                index,
+                seed: has_seed.then_some(result.seed),
+                ang_vel: result.initial_angular_velocity.length(),
+                angle: 2. * w.clamp(-1., 1.).acos(),
+                time: result.time,
+            };
+
+            let bin = (
+                (record.ang_vel / ANG_VEL_BIN_WIDTH) as usize,
+                (record.angle / ANGLE_BIN_WIDTH) as usize,
+            );
+            bins.entry(bin).or_default().push(record);
+            num_records += 1;
+        }
+    }
+
+    if num_records == 0 {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no records to check"));
+    }
+
+    let mut writer = BufWriter::new(fs::File::create(output)?);
+    writeln!(
+        writer,
+        "file,record,seed,ang_vel,target_angle,time,bin_median,lower_fence,upper_fence"
+    )?;
+
+    let mut bin_keys = bins.keys().copied().collect::<Vec<_>>();
+    bin_keys.sort_unstable();
+
+    let mut num_outliers = 0;
+    let mut num_skipped = 0;
+    for key in bin_keys {
+        let records = &bins[&key];
+        if records.len() < MIN_BIN_RECORDS {
+            num_skipped += records.len();
+            continue;
+        }
+
+        let mut times = records.iter().map(|record| record.time).collect::<Vec<_>>();
+        times.sort_by(f32::total_cmp);
+        let quantile = |q: f32| times[((times.len() - 1) as f32 * q).round() as usize];
+        let (q1, median, q3) = (quantile(0.25), quantile(0.5), quantile(0.75));
+        let lower_fence = q1 - iqr_factor * (q3 - q1);
+        let upper_fence = q3 + iqr_factor * (q3 - q1);
+
+        for record in records {
+            if (lower_fence..=upper_fence).contains(&record.time) {
+                continue;
+            }
+
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{median},{lower_fence},{upper_fence}",
+                files[record.file],
+                record.index,
+                record.seed.map_or_else(String::new, |seed| seed.to_string()),
+                record.ang_vel,
+                record.angle,
+                record.time
+            )?;
+            num_outliers += 1;
+        }
+    }
+
+    writer.flush()?;
+
+    if num_skipped != 0 {
+        println!("{num_skipped} records in buckets of fewer than {MIN_BIN_RECORDS} records weren't checked");
+    }
+    println!(
+        "Wrote {num_outliers} of {num_records} records more than {iqr_factor} IQRs outside their bucket's quartiles to {}",
+        output.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use crate::{
+    crc::{Crc32, CASTAGNOLI},
+    format::FORMAT_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// The first entry of every archive, describing the others
+const INDEX: &str = "index.json";
+
+/// Written as `index.json` at the top of every archive
+#[derive(Serialize, Deserialize)]
+struct PackIndex {
+    format_version: u32,
+    /// The name of the packed folder
+    dataset: String,
+    /// The `manifest.json` of the dataset, if it has one
+    manifest: Option<serde_json::Value>,
+    /// Every other entry of the archive, in order
+    files: Vec<PackedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackedFile {
+    /// Relative to the dataset folder, with `/` separators
+    path: String,
+    size: u64,
+    crc32c: u32,
+}
+
+/// Writes every file of the dataset folder `dir` and its subfolders, apart from unfinished `.tmp` files,
+/// to a zstd-compressed tar archive at `output`, after an `index.json` of their sizes and CRC-32Cs and the dataset's manifest
+pub fn pack(dir: &Path, output: &Path) -> io::Result<()> {
+    let mut paths = Vec::new();
+    walk(dir, &mut paths)?;
+    paths.retain(|path| path.extension().is_none_or(|ext| ext != "tmp"));
+    paths.sort();
+
+    let mut files = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let mut crc = Crc32::new(CASTAGNOLI);
+        let size = read_parts(&mut fs::File::open(path)?, u64::MAX, |part| {
+            crc.update(part);
+            Ok(())
+        })?;
+
+        files.push(PackedFile {
+            path: entry_name(path.strip_prefix(dir).unwrap())?,
+            size,
+            crc32c: crc.finish(),
+        });
+    }
+
+    let manifest_path = dir.join("manifest.json");
+    let index = PackIndex {
+        format_version: FORMAT_VERSION,
+        dataset: dir
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        manifest: if manifest_path.exists() {
+            Some(serde_json::from_slice(&fs::read(manifest_path)?)?)
+        } else {
+            None
+        },
+        files,
+    };
+
+    let mut tmp_path = output.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    // the results files are already compressed, so the default level is enough
+    let encoder = zstd::Encoder::new(BufWriter::new(fs::File::create(&tmp_path)?), 0)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let index_bytes = serde_json::to_vec_pretty(&index)?;
+    builder.append_data(&mut entry_header(index_bytes.len() as u64, 0), INDEX, &index_bytes[..])?;
+
+    for (path, file) in paths.iter().zip(&index.files) {
+        let mtime = fs::metadata(path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        // the file may have grown since it was indexed, so only as much as was indexed is written
+        let mut data = fs::File::open(path)?.take(file.size);
+        builder.append_data(&mut entry_header(file.size, mtime), &file.path, &mut data)?;
+        if data.limit() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} shrank while it was packed", path.display()),
+            ));
+        }
+    }
+
+    builder
+        .into_inner()?
+        .finish()?
+        .into_inner()
+        .map_err(io::Error::from)?
+        .sync_all()?;
+    fs::rename(&tmp_path, output)?;
+
+    println!(
+        "Packed {} files, {} bytes, of {} into {}",
+        index.files.len(),
+        index.files.iter().map(|file| file.size).sum::<u64>(),
+        dir.display(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Extracts an archive written by `pack` into the folder `output`, which must not exist yet,
+/// failing if any file doesn't match the size and CRC-32C in its index
+pub fn unpack(archive: &Path, output: &Path) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    let mut tar = tar::Archive::new(zstd::Decoder::new(fs::File::open(archive)?)?);
+    let mut entries = tar.entries()?;
+
+    let Some(mut entry) = entries.next().transpose()? else {
+        return Err(invalid(format!("{} is empty", archive.display())));
+    };
+    if *entry.path()? != *Path::new(INDEX) {
+        return Err(invalid(format!("{} doesn't start with an {INDEX}", archive.display())));
+    }
+
+    let mut index_bytes = Vec::new();
+    entry.read_to_end(&mut index_bytes)?;
+    let index: PackIndex = serde_json::from_slice(&index_bytes)?;
+
+    if index.format_version != FORMAT_VERSION {
+        println!(
+            "{} was packed with format version {}, this build reads {FORMAT_VERSION}, `migrate` may be needed",
+            archive.display(),
+            index.format_version
+        );
+    }
+
+    for file in &index.files {
+        let Some(mut entry) = entries.next().transpose()? else {
+            return Err(invalid(format!("{} ends before {}", archive.display(), file.path)));
+        };
+        let name = entry.path()?.display().to_string();
+        if *entry.path()? != *Path::new(&file.path) || entry.size() != file.size {
+            return Err(invalid(format!("{name} isn't the {} its index lists next", file.path)));
+        }
+
+        let relative = Path::new(&file.path);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(invalid(format!("{name} isn't a path inside the dataset")));
+        }
+
+        let path = output.join(relative);
+        fs::create_dir_all(path.parent().unwrap())?;
+
+        let mut writer = BufWriter::new(fs::File::create(&path)?);
+        let mut crc = Crc32::new(CASTAGNOLI);
+        let written = read_parts(&mut entry, file.size, |part| {
+            crc.update(part);
+            writer.write_all(part)
+        })?;
+        writer.flush()?;
+
+        if written != file.size {
+            return Err(invalid(format!("{} ends in the middle of {name}", archive.display())));
+        }
+        if crc.finish() != file.crc32c {
+            return Err(invalid(format!(
+                "{name} doesn't match its checksum, the archive is corrupted"
+            )));
+        }
+    }
+
+    println!(
+        "Unpacked {} files of {} into {}",
+        index.files.len(),
+        if index.dataset.is_empty() {
+            "the dataset"
+        } else {
+            index.dataset.as_str()
+        },
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Appends every file in `dir` and its subfolders to `paths`
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The name of the entry of the file at `relative`, with `/` separators on every platform
+fn entry_name(relative: &Path) -> io::Result<String> {
+    let parts = relative
+        .components()
+        .map(|component| {
+            component.as_os_str().to_str().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} isn't valid UTF-8", relative.display()),
+                )
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(parts.join("/"))
+}
+
+/// Reads up to `len` bytes of `reader`, passing them to `part` a buffer at a time, and returns how many it read
+fn read_parts<R: Read>(reader: &mut R, len: u64, mut part: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<u64> {
+    let mut buffer = vec![0; 1 << 16];
+    let mut read = 0;
+
+    while read < len {
+        let max = (len - read).min(buffer.len() as u64) as usize;
+        let n = reader.read(&mut buffer[..max])?;
+        if n == 0 {
+            break;
+        }
+
+        part(&buffer[..n])?;
+        read += n as u64;
+    }
+
+    Ok(read)
+}
+
+/// The header of a regular file of `size` bytes, last modified `mtime` seconds after the Unix epoch,
+/// with the GNU extension for the names longer than the 100 bytes of a plain tar header
+fn entry_header(size: u64, mtime: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header
+}
@@ -0,0 +1,57 @@
+use crate::{format::Header, simulation::SimResult, table};
+use parquet::{
+    basic::{Compression, ZstdLevel},
+    data_type::{FloatType, Int32Type},
+    errors::Result,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    format::KeyValue,
+    schema::parser::parse_message_type,
+};
+use std::{io::Write, sync::Arc};
+
+/// Writes a zstd compressed Parquet file with a column for every `table::columns` and the header as JSON
+/// in the `header` key of its metadata, returning the size of the values before compression
+pub fn write<W: Write + Send>(writer: W, header: &Header, results: &[SimResult], zstd_level: i32) -> Result<u64> {
+    let columns = table::columns(header);
+    let schema = columns
+        .iter()
+        .map(|column| {
+            let physical_type = if column.integer { "INT32" } else { "FLOAT" };
+            format!("REQUIRED {physical_type} {};", column.name)
+        })
+        .collect::<String>();
+    let schema = parse_message_type(&format!("message results {{ {schema} }}"))?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::try_new(zstd_level)?))
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            "header".to_string(),
+            serde_json::to_string(header).unwrap(),
+        )]))
+        .build();
+
+    let rows = results.iter().map(|result| table::row(header, result)).collect::<Vec<_>>();
+
+    // every file is one batch of results, so it's one row group
+    let mut file = SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))?;
+    let mut row_group = file.next_row_group()?;
+    let mut i = 0;
+
+    while let Some(mut column) = row_group.next_column()? {
+        if columns[i].integer {
+            let values = rows.iter().map(|row| row[i] as i32).collect::<Vec<_>>();
+            column.typed::<Int32Type>().write_batch(&values, None, None)?;
+        } else {
+            let values = rows.iter().map(|row| row[i]).collect::<Vec<_>>();
+            column.typed::<FloatType>().write_batch(&values, None, None)?;
+        }
+
+        column.close()?;
+        i += 1;
+    }
+
+    row_group.close()?;
+    file.close()?;
+
+    Ok((rows.len() * columns.len() * 4) as u64)
+}
@@ -0,0 +1,134 @@
+/// Streaming estimate of a single percentile with the P² algorithm (Jain & Chlamtac, 1985),
+/// in constant memory and without storing the observations
+pub struct PercentileEstimator {
+    /// The percentile as a fraction in `(0, 1)`
+    p: f64,
+    count: u64,
+    /// Marker heights, the middle one is the estimate
+    heights: [f64; 5],
+    /// Actual marker positions
+    positions: [f64; 5],
+    /// Desired marker positions
+    desired: [f64; 5],
+    /// How much the desired positions move per observation
+    increments: [f64; 5],
+}
+
+impl PercentileEstimator {
+    pub fn new(percentile: f32) -> Self {
+        let p = f64::from(percentile) / 100.;
+
+        Self {
+            p,
+            count: 0,
+            heights: [0.; 5],
+            positions: [0., 1., 2., 3., 4.],
+            desired: [0., 2. * p, 4. * p, 2. + 2. * p, 4.],
+            increments: [0., p / 2., p, (1. + p) / 2., 1.],
+        }
+    }
+
+    pub fn add(&mut self, value: f32) {
+        let x = f64::from(value);
+
+        // the markers start at the first 5 observations
+        if self.count < 5 {
+            self.heights[self.count as usize] = x;
+            self.count += 1;
+
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+
+            return;
+        }
+
+        self.count += 1;
+
+        let h = &mut self.heights;
+        let k = if x < h[0] {
+            h[0] = x;
+            0
+        } else if x >= h[4] {
+            h[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < h[i + 1]).unwrap()
+        };
+
+        for position in &mut self.positions[k + 1..] {
+            *position += 1.;
+        }
+
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        // move the middle markers towards their desired positions
+        for i in 1..4 {
+            let n = &mut self.positions;
+            let d = self.desired[i] - n[i];
+
+            if (d >= 1. && n[i + 1] - n[i] > 1.) || (d <= -1. && n[i - 1] - n[i] < -1.) {
+                let d = d.signum();
+
+                let parabolic = h[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]));
+
+                h[i] = if h[i - 1] < parabolic && parabolic < h[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0. { i + 1 } else { i - 1 };
+                    h[i] + d * (h[j] - h[i]) / (n[j] - n[i])
+                };
+
+                n[i] += d;
+            }
+        }
+    }
+
+    /// The number of values added so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The current estimate, `None` before any value was added
+    pub fn estimate(&self) -> Option<f32> {
+        if self.count == 0 {
+            return None;
+        }
+
+        if self.count < 5 {
+            let mut values = self.heights[..self.count as usize].to_vec();
+            values.sort_by(f64::total_cmp);
+            let index = ((values.len() - 1) as f64 * self.p).round() as usize;
+            return Some(values[index] as f32);
+        }
+
+        Some(self.heights[2] as f32)
+    }
+}
+
+/// Only keeps the times above a running percentile of every time seen, after a warmup that establishes the distribution
+pub struct PercentileFilter {
+    estimator: PercentileEstimator,
+    warmup: u64,
+}
+
+impl PercentileFilter {
+    pub fn new(percentile: f32, warmup: u64) -> Self {
+        Self {
+            estimator: PercentileEstimator::new(percentile),
+            warmup,
+        }
+    }
+
+    /// Adds `time` to the estimate, returning if it's above the percentile and the warmup is over
+    pub fn keep(&mut self, time: f32) -> bool {
+        let keep = self.estimator.count() >= self.warmup && self.estimator.estimate().is_some_and(|p| time > p);
+        self.estimator.add(time);
+        keep
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    format::{Fields, Header, Input},
+    simulation::SimResult,
+};
+use prost::Message;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoHeader {
+    #[prost(uint32, tag = "1")]
+    pub fields: u32,
+    #[prost(float, tag = "2")]
+    pub tick_rate: f32,
+    #[prost(float, tag = "3")]
+    pub ang_vel_scale: f32,
+    #[prost(float, tag = "4")]
+    pub angle_scale: f32,
+    #[prost(float, tag = "5")]
+    pub time_scale: f32,
+    #[prost(string, tag = "6")]
+    pub metadata: String,
+    #[prost(uint32, tag = "7")]
+    pub omitted_inputs: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Record {
+    #[prost(float, tag = "1")]
+    pub ang_vel_x: f32,
+    #[prost(float, tag = "2")]
+    pub ang_vel_y: f32,
+    #[prost(float, tag = "3")]
+    pub ang_vel_z: f32,
+    #[prost(float, tag = "4")]
+    pub target_pitch: f32,
+    #[prost(float, tag = "5")]
+    pub target_yaw: f32,
+    #[prost(float, tag = "6")]
+    pub target_roll: f32,
+    #[prost(float, tag = "7")]
+    pub time: f32,
+    #[prost(float, optional, tag = "8")]
+    pub response_control: Option<f32>,
+    #[prost(float, optional, tag = "9")]
+    pub response_ang_accel: Option<f32>,
+    #[prost(float, optional, tag = "10")]
+    pub local_target_x: Option<f32>,
+    #[prost(float, optional, tag = "11")]
+    pub local_target_y: Option<f32>,
+    #[prost(float, optional, tag = "12")]
+    pub local_target_z: Option<f32>,
+    #[prost(uint32, optional, tag = "13")]
+    pub difficulty_class: Option<u32>,
+    #[prost(uint32, optional, tag = "14")]
+    pub car_config_id: Option<u32>,
+    #[prost(float, optional, tag = "15")]
+    pub initial_pitch: Option<f32>,
+    #[prost(float, optional, tag = "16")]
+    pub initial_yaw: Option<f32>,
+    #[prost(float, optional, tag = "17")]
+    pub initial_roll: Option<f32>,
+    #[prost(float, optional, tag = "18")]
+    pub itae: Option<f32>,
+    #[prost(uint32, optional, tag = "19")]
+    pub controller_id: Option<u32>,
+    #[prost(float, optional, tag = "20")]
+    pub world_ang_vel_x: Option<f32>,
+    #[prost(float, optional, tag = "21")]
+    pub world_ang_vel_y: Option<f32>,
+    #[prost(float, optional, tag = "22")]
+    pub world_ang_vel_z: Option<f32>,
+    #[prost(float, optional, tag = "23")]
+    pub world_target_pitch: Option<f32>,
+    #[prost(float, optional, tag = "24")]
+    pub world_target_yaw: Option<f32>,
+    #[prost(float, optional, tag = "25")]
+    pub world_target_x: Option<f32>,
+    #[prost(float, optional, tag = "26")]
+    pub world_target_y: Option<f32>,
+    #[prost(float, optional, tag = "27")]
+    pub world_target_z: Option<f32>,
+}
+
+/// The contents of a `--format protobuf` file, see `proto/results.proto`
+#[derive(Clone, PartialEq, Message)]
+pub struct ResultBatch {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ProtoHeader>,
+    #[prost(message, repeated, tag = "2")]
+    pub records: Vec<Record>,
+}
+
+/// The length-delimited `ResultBatch` of `results`, scaled like `format::serialize` with the seconds time codec,
+/// with the omitted inputs left 0
+pub fn serialize(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let fields = header.fields;
+    let omitted = header.omitted_inputs;
+
+    let records = results
+        .iter()
+        .map(|result| {
+            let iav = result.initial_angular_velocity / header.ang_vel_scale;
+            let rt = result.relative_target;
+            let input = |input: Input, value: f32| if omitted.contains(input) { 0. } else { value };
+            let has_response = fields.contains(Fields::RESPONSE);
+            let has_local_target = fields.contains(Fields::LOCAL_TARGET);
+            let has_initial_orientation = fields.contains(Fields::INITIAL_ORIENTATION);
+            let io = result.initial_orientation;
+            let has_world_ang_vel = fields.contains(Fields::WORLD_ANG_VEL);
+            let wav = result.world_angular_velocity / header.ang_vel_scale;
+            let has_world_target = fields.contains(Fields::WORLD_TARGET);
+            let wt = result.world_target;
+            let has_world_target_dir = fields.contains(Fields::WORLD_TARGET_DIR);
+            let wtd = result.world_target_dir;
+
+            Record {
+                ang_vel_x: input(Input::AngVelX, iav.x),
+                ang_vel_y: input(Input::AngVelY, iav.y),
+                ang_vel_z: input(Input::AngVelZ, iav.z),
+                target_pitch: input(Input::TargetPitch, rt.pitch / header.angle_scale),
+                target_yaw: input(Input::TargetYaw, rt.yaw / header.angle_scale),
+                target_roll: input(Input::TargetRoll, rt.roll / header.angle_scale),
+                time: result.time / header.time_scale,
+                response_control: has_response.then_some(result.response.control),
+                response_ang_accel: has_response.then_some(result.response.ang_accel),
+                local_target_x: has_local_target.then_some(result.local_target.x),
+                local_target_y: has_local_target.then_some(result.local_target.y),
+                local_target_z: has_local_target.then_some(result.local_target.z),
+                difficulty_class: fields
+                    .contains(Fields::DIFFICULTY_CLASS)
+                    .then(|| result.difficulty_class(&header.metadata.difficulty_thresholds).into()),
+                car_config_id: fields.contains(Fields::CAR_CONFIG_ID).then_some(result.car_config_id.into()),
+                initial_pitch: has_initial_orientation.then_some(io.pitch / header.angle_scale),
+                initial_yaw: has_initial_orientation.then_some(io.yaw / header.angle_scale),
+                initial_roll: has_initial_orientation.then_some(io.roll / header.angle_scale),
+                itae: fields.contains(Fields::ITAE).then_some(result.itae),
+                controller_id: fields.contains(Fields::CONTROLLER_ID).then_some(result.controller_id.into()),
+                world_ang_vel_x: has_world_ang_vel.then_some(wav.x),
+                world_ang_vel_y: has_world_ang_vel.then_some(wav.y),
+                world_ang_vel_z: has_world_ang_vel.then_some(wav.z),
+                world_target_pitch: has_world_target.then_some(wt.pitch / header.angle_scale),
+                world_target_yaw: has_world_target.then_some(wt.yaw / header.angle_scale),
+                world_target_x: has_world_target_dir.then_some(wtd.x),
+                world_target_y: has_world_target_dir.then_some(wtd.y),
+                world_target_z: has_world_target_dir.then_some(wtd.z),
+            }
+        })
+        .collect();
+
+    let batch = ResultBatch {
+        header: Some(ProtoHeader {
+            fields: fields.bits(),
+            tick_rate: header.tick_rate,
+            ang_vel_scale: header.ang_vel_scale,
+            angle_scale: header.angle_scale,
+            time_scale: header.time_scale,
+            metadata: serde_json::to_string(&header.metadata).unwrap(),
+            omitted_inputs: omitted.bits(),
+        }),
+        records,
+    };
+
+    batch.encode_length_delimited_to_vec()
+}
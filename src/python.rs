@@ -0,0 +1,183 @@
+use crate::{
+    config::Config,
+    format::{Fields, Header},
+    percentile::PercentileFilter,
+    reader,
+    simulation::{SimResult, Simulation},
+    table,
+};
+use clap::Parser;
+use numpy::PyArray1;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBool, PyDict, PyList, PyTuple},
+};
+use std::{io, path::PathBuf, sync::Once};
+
+/// RocketSim is only initialized by the first `generate`, with its `assets_dir`
+static ROCKETSIM_INIT: Once = Once::new();
+
+/// The `stat_final_data` Python module, built with `maturin` and the `python` feature
+#[pymodule]
+fn stat_final_data(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+
+    Ok(())
+}
+
+/// The values of every column of `table::columns`, scaled like the files, and the seeds if the records have them
+struct Columns {
+    names: Vec<table::Column>,
+    values: Vec<Vec<f32>>,
+    seeds: Option<Vec<u64>>,
+}
+
+impl Columns {
+    fn new(header: &Header) -> Self {
+        let names = table::columns(header);
+
+        Self {
+            values: vec![Vec::new(); names.len()],
+            names,
+            seeds: header.fields.contains(Fields::SEED).then(Vec::new),
+        }
+    }
+
+    fn extend(&mut self, header: &Header, results: &[SimResult]) {
+        for result in results {
+            for (column, value) in self.values.iter_mut().zip(table::row(header, result)) {
+                column.push(value);
+            }
+        }
+
+        if let Some(seeds) = &mut self.seeds {
+            seeds.extend(results.iter().map(|result| result.seed));
+        }
+    }
+
+    /// A dict of a 1-D array for every column, `float32` for the values and `uint8` for the ids and classes,
+    /// with a `uint64` `seed` array if the records have seeds
+    fn into_dict(self, py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+        let dict = PyDict::new(py);
+
+        for (column, values) in self.names.into_iter().zip(self.values) {
+            if column.integer {
+                let values = values.into_iter().map(|value| value as u8).collect::<Vec<_>>();
+                dict.set_item(column.name, PyArray1::from_vec(py, values))?;
+            } else {
+                dict.set_item(column.name, PyArray1::from_vec(py, values))?;
+            }
+        }
+
+        if let Some(seeds) = self.seeds {
+            dict.set_item("seed", PyArray1::from_vec(py, seeds))?;
+        }
+
+        Ok(dict)
+    }
+}
+
+/// Reads every record of the results file or dataset folder at `path` into a dict of NumPy arrays, one for every column
+/// of the tabular formats, like `ang_vel_x` and `time`. Every file of a dataset needs the same columns
+#[pyfunction]
+fn load(py: Python<'_>, path: PathBuf) -> PyResult<Bound<'_, PyDict>> {
+    let columns = py.detach(|| -> io::Result<Columns> {
+        let mut columns: Option<Columns> = None;
+
+        for dataset_file in reader::files_or_dataset(&path)? {
+            let file = dataset_file
+                .open()
+                .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+            let columns = columns.get_or_insert_with(|| Columns::new(&file.header));
+
+            let file_columns = Columns::new(&file.header);
+            if file_columns
+                .names
+                .iter()
+                .map(|column| column.name)
+                .ne(columns.names.iter().map(|column| column.name))
+                || file_columns.seeds.is_some() != columns.seeds.is_some()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{dataset_file} doesn't have the same columns as the files before it"),
+                ));
+            }
+
+            columns.extend(&file.header, &file.results());
+        }
+
+        columns.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no results files in {}", path.display())))
+    })?;
+
+    columns.into_dict(py)
+}
+
+/// Draws and simulates `n` samples like a worker seeded with `seed`, or with a random seed, and returns them like `load`.
+///
+/// Every keyword argument is the command line option of the same name, e.g. `car_configs=["octane", "dominus"]` for
+/// `--car-configs octane,dominus` or `record_seed=True` for `--record-seed`, and the records have the fields they select
+#[pyfunction]
+#[pyo3(signature = (n, seed=None, **params))]
+fn generate<'py>(
+    py: Python<'py>,
+    n: usize,
+    seed: Option<u64>,
+    params: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut args = vec![env!("CARGO_PKG_NAME").to_string()];
+
+    for (key, value) in params.into_iter().flatten() {
+        let option = format!("--{}", key.extract::<String>()?.replace('_', "-"));
+
+        if value.is_instance_of::<PyBool>() {
+            if value.extract::<bool>()? {
+                args.push(option);
+            }
+        } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+            let values = value
+                .try_iter()?
+                .map(|value| value?.str().map(|value| value.to_string()))
+                .collect::<PyResult<Vec<_>>>()?;
+            args.extend([option, values.join(",")]);
+        } else {
+            args.extend([option, value.str()?.to_string()]);
+        }
+    }
+
+    let config = Config::try_parse_from(args).map_err(|e| PyValueError::new_err(e.render().to_string()))?;
+
+    // the grid of the reference dataset can only be built once per process
+    if config.fill_gaps.is_some() {
+        return Err(PyValueError::new_err("fill_gaps isn't supported by generate"));
+    }
+
+    let columns = py.detach(|| {
+        ROCKETSIM_INIT.call_once(|| rocketsim_rs::init(Some(&config.assets_dir)));
+
+        let mut simulation = Simulation::new(&config, seed);
+        let mut record_above = config
+            .record_above_percentile
+            .map(|percentile| PercentileFilter::new(percentile, config.percentile_warmup));
+
+        let mut results = Vec::with_capacity(n);
+        while results.len() < n {
+            let Some(result) = simulation.do_random() else {
+                continue;
+            };
+
+            if record_above.as_mut().is_none_or(|filter| filter.keep(result.time)) {
+                results.push(result);
+            }
+        }
+
+        let header = Header::new(&config);
+        let mut columns = Columns::new(&header);
+        columns.extend(&header, &results);
+        columns
+    });
+
+    columns.into_dict(py)
+}
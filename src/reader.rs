@@ -0,0 +1,849 @@
+use crate::{
+    crc::{self, CASTAGNOLI},
+    dataset_file, f16,
+    format::{
+        self, Compression, Fields, Header, Input, Layout, Precision, SchemaField, Sidecar, TargetRotation, TimeCodec,
+        LEGACY_FORMAT_VERSION, MAGIC,
+    },
+    migrate,
+    simulation::{ControlResponse, SimResult},
+};
+use rocketsim_rs::{
+    glam_ext::glam::{Mat3A, Quat, Vec3A},
+    math::{Angle, RotMat},
+};
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    vec,
+};
+
+/// What a sample started from, enough to simulate it again
+#[derive(Clone, Copy)]
+pub struct InitialConditions {
+    /// Car-relative initial angular velocity
+    pub ang_vel: Vec3A,
+    pub relative_target: Angle,
+    /// The car's world-frame orientation
+    pub orientation: Angle,
+    /// Index in the metadata's `car_configs`
+    pub car_config_id: usize,
+    /// Index in the metadata's `controllers`
+    pub controller_id: usize,
+}
+
+/// A results file with its decompressed records, always in rows
+pub struct ResultsFile {
+    /// The format version the file was written with, `LEGACY_FORMAT_VERSION` for the headerless files of the first versions
+    pub format_version: u32,
+    pub header: Header,
+    /// The name, type, unit, frame and scale of every value of a record, from the header's schema block
+    pub schema: Vec<SchemaField>,
+    pub records: Vec<u8>,
+}
+
+impl ResultsFile {
+    /// Opens a file with an embedded header, the uncompressed records of a file with a `--sidecar-metadata` sidecar,
+    /// or a headerless file of the first versions with the header `migrate` would rewrite it with
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+
+        if !bytes.starts_with(&MAGIC) {
+            let sidecar_path = path.with_extension("json");
+            if !sidecar_path.exists() {
+                let header = migrate::legacy_header();
+                let schema = header.schema();
+                return Ok(Self::new(
+                    LEGACY_FORMAT_VERSION,
+                    header,
+                    schema,
+                    migrate::legacy_records(path, bytes)?,
+                ));
+            }
+
+            let sidecar = Sidecar::read_from(&sidecar_path)?;
+            if bytes.len() as u64 != sidecar.num_records * sidecar.header.record_size() as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} holds {} bytes, but its sidecar says {} records of {} bytes",
+                        path.display(),
+                        bytes.len(),
+                        sidecar.num_records,
+                        sidecar.header.record_size()
+                    ),
+                ));
+            }
+
+            return Ok(Self::new(sidecar.format_version, sidecar.header, sidecar.schema, bytes));
+        }
+
+        Self::from_embedded(path, &bytes)
+    }
+
+    /// Reads the bytes of a file with an embedded header, from the file at `path` or one of its chunks
+    fn from_embedded(path: &Path, mut bytes: &[u8]) -> io::Result<Self> {
+        let (header, schema, format_version) = Header::read_with_schema(&mut bytes)?;
+
+        let (compressed, num_records, checksum) = format::split_footer(bytes)?;
+        let mut records = Vec::new();
+        match header.compression {
+            Compression::Zstd => match header.metadata.zstd_dictionary_id {
+                Some(id) => {
+                    let dictionary = read_dictionary(path, id)?;
+                    zstd::Decoder::with_dictionary(compressed, &dictionary)?.read_to_end(&mut records)?;
+                }
+                None => records = zstd::stream::decode_all(compressed)?,
+            },
+            Compression::None => records.extend(compressed),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                lz4_flex::frame::FrameDecoder::new(compressed).read_to_end(&mut records)?;
+            }
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                flate2::read::GzDecoder::new(compressed).read_to_end(&mut records)?;
+            }
+        }
+
+        let record_size = header.record_size() as u64;
+        if records.len() as u64 != num_records * record_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} holds {} records, but its footer says {num_records}",
+                    path.display(),
+                    records.len() as u64 / record_size
+                ),
+            ));
+        }
+
+        if crc::crc32(CASTAGNOLI, &records) != checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}'s records don't match their checksum, the file is corrupted",
+                    path.display()
+                ),
+            ));
+        }
+
+        Ok(Self::new(format_version, header, schema, records))
+    }
+
+    /// Rearranges the records of `Layout::Columns` files to rows, which every other method expects
+    fn new(format_version: u32, header: Header, schema: Vec<SchemaField>, records: Vec<u8>) -> Self {
+        let records = match header.layout {
+            Layout::Rows => records,
+            Layout::Columns => format::to_rows(&header, &records),
+        };
+
+        Self {
+            format_version,
+            header,
+            schema,
+            records,
+        }
+    }
+
+    /// The time it took every record to reach the target, in seconds
+    pub fn times(&self) -> Vec<f32> {
+        let header = &self.header;
+        // the time follows the base inputs present
+        let time_index = header.num_inputs();
+        let mut ticks = 0;
+
+        self.records
+            .chunks_exact(header.record_size())
+            .map(|record| {
+                let time_offset = time_index * header.precision.size();
+                let time = &record[time_offset..time_offset + header.time_size()];
+
+                match header.time_codec {
+                    TimeCodec::Seconds => self.base_at(record, time_index) * header.time_scale,
+                    TimeCodec::Ticks => header.byte_order.read_u32(time.try_into().unwrap()) as f32 / header.tick_rate,
+                    TimeCodec::DeltaTicks => {
+                        ticks += header.byte_order.read_i32(time.try_into().unwrap());
+                        ticks as f32 / header.tick_rate
+                    }
+                    TimeCodec::Ticks16 => f32::from(header.byte_order.read_u16(time.try_into().unwrap())) / header.tick_rate,
+                }
+            })
+            .collect()
+    }
+
+    /// Every record as the result it was written from, with 0 for the omitted inputs and the optional fields
+    /// the file doesn't have, so it can be written again in another format
+    pub fn results(&self) -> Vec<SimResult> {
+        let header = &self.header;
+        let order = header.byte_order;
+        let offset = |field| header.fields.contains(field).then(|| header.offset(field));
+
+        let response_offset = offset(Fields::RESPONSE);
+        let local_target_offset = offset(Fields::LOCAL_TARGET);
+        let car_config_offset = offset(Fields::CAR_CONFIG_ID);
+        let orientation_offset = offset(Fields::INITIAL_ORIENTATION);
+        let itae_offset = offset(Fields::ITAE);
+        let controller_offset = offset(Fields::CONTROLLER_ID);
+        let world_ang_vel_offset = offset(Fields::WORLD_ANG_VEL);
+        let world_target_offset = offset(Fields::WORLD_TARGET);
+        let world_target_dir_offset = offset(Fields::WORLD_TARGET_DIR);
+        let seed_offset = offset(Fields::SEED);
+
+        self.records
+            .chunks_exact(header.record_size())
+            .zip(self.times())
+            .map(|(record, time)| SimResult {
+                initial_angular_velocity: self.ang_vel_at(record),
+                relative_target: self.relative_target_at(record),
+                relative_rotation: self.relative_rotation_at(record),
+                time,
+                response: response_offset.map_or(
+                    ControlResponse {
+                        control: 0.,
+                        ang_accel: 0.,
+                    },
+                    |offset| ControlResponse {
+                        control: self.f32_at(record, offset),
+                        ang_accel: self.f32_at(record, offset + 4),
+                    },
+                ),
+                local_target: local_target_offset.map_or(Vec3A::ZERO, |offset| self.vec_at(record, offset)),
+                car_config_id: car_config_offset.map_or(0, |offset| record[offset]),
+                controller_id: controller_offset.map_or(0, |offset| record[offset]),
+                initial_orientation: orientation_offset.map_or(Angle::default(), |offset| self.angle_at(record, offset)),
+                world_angular_velocity: world_ang_vel_offset
+                    .map_or(Vec3A::ZERO, |offset| self.vec_at(record, offset) * header.ang_vel_scale),
+                world_target: world_target_offset.map_or(Angle::default(), |offset| Angle {
+                    pitch: self.f32_at(record, offset) * header.angle_scale,
+                    yaw: self.f32_at(record, offset + 4) * header.angle_scale,
+                    roll: 0.,
+                }),
+                world_target_dir: world_target_dir_offset.map_or(Vec3A::ZERO, |offset| self.vec_at(record, offset)),
+                seed: seed_offset.map_or(0, |offset| order.read_u64(record[offset..offset + 8].try_into().unwrap())),
+                itae: itae_offset.map_or(0., |offset| self.f32_at(record, offset)),
+            })
+            .collect()
+    }
+
+    /// The `index`th value of the schema of every record, multiplied by its scale
+    pub fn field_values(&self, index: usize) -> Vec<f64> {
+        let header = &self.header;
+        let order = header.byte_order;
+        let sizes = header.value_sizes();
+        let offset = sizes[..index].iter().sum::<usize>();
+        let field = &self.schema[index];
+
+        self.records
+            .chunks_exact(header.record_size())
+            .map(|record| {
+                let bytes = &record[offset..offset + sizes[index]];
+                let value = match field.value_type.as_str() {
+                    "f32" => f64::from(order.read_f32(bytes.try_into().unwrap())),
+                    "f16" => f64::from(f16::to_f32(order.read_u16(bytes.try_into().unwrap()))),
+                    "i16" => f64::from(order.read_i16(bytes.try_into().unwrap())),
+                    "u16" => f64::from(order.read_u16(bytes.try_into().unwrap())),
+                    "u32" => f64::from(order.read_u32(bytes.try_into().unwrap())),
+                    "i32" => f64::from(order.read_i32(bytes.try_into().unwrap())),
+                    "u64" => order.read_u64(bytes.try_into().unwrap()) as f64,
+                    _ => f64::from(bytes[0]),
+                };
+
+                value * f64::from(field.scale)
+            })
+            .collect()
+    }
+
+    /// The car-relative initial angular velocity and the relative target of every record, 0 where omitted,
+    /// or where the file only has the rotation to the target without the initial orientation
+    pub fn inputs(&self) -> Vec<(Vec3A, Angle)> {
+        self.records
+            .chunks_exact(self.header.record_size())
+            .map(|record| (self.ang_vel_at(record), self.relative_target_at(record)))
+            .collect()
+    }
+
+    /// The initial conditions of every record, `None` if the file doesn't have the initial orientation,
+    /// omits any of the base inputs or only has the rotation to the target, whose pitch, yaw and roll can differ
+    pub fn initial_conditions(&self) -> Option<Vec<InitialConditions>> {
+        let header = &self.header;
+        let fields = header.fields;
+
+        if !fields.contains(Fields::INITIAL_ORIENTATION)
+            || header.omitted_inputs.bits() != 0
+            || header.target_rotation != TargetRotation::Euler
+        {
+            return None;
+        }
+
+        let orientation_offset = self.header.offset(Fields::INITIAL_ORIENTATION);
+        let car_config_offset = fields
+            .contains(Fields::CAR_CONFIG_ID)
+            .then(|| self.header.offset(Fields::CAR_CONFIG_ID));
+        let controller_offset = fields
+            .contains(Fields::CONTROLLER_ID)
+            .then(|| self.header.offset(Fields::CONTROLLER_ID));
+
+        let conditions = self
+            .records
+            .chunks_exact(self.header.record_size())
+            .map(|record| InitialConditions {
+                ang_vel: self.ang_vel_at(record),
+                relative_target: self.relative_target_at(record),
+                orientation: self.angle_at(record, orientation_offset),
+                car_config_id: car_config_offset.map_or(0, |offset| record[offset].into()),
+                controller_id: controller_offset.map_or(0, |offset| record[offset].into()),
+            })
+            .collect();
+
+        Some(conditions)
+    }
+
+    fn f32_at(&self, record: &[u8], offset: usize) -> f32 {
+        self.header
+            .byte_order
+            .read_f32(record[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// The base value at `index` of `record`, in the header's precision
+    fn base_at(&self, record: &[u8], index: usize) -> f32 {
+        let order = self.header.byte_order;
+
+        match self.header.precision {
+            Precision::F32 => order.read_f32(record[index * 4..index * 4 + 4].try_into().unwrap()),
+            Precision::F16 => f16::to_f32(order.read_u16(record[index * 2..index * 2 + 2].try_into().unwrap())),
+            Precision::I16 => {
+                f32::from(order.read_i16(record[index * 2..index * 2 + 2].try_into().unwrap())) / f32::from(i16::MAX)
+            }
+        }
+    }
+
+    /// The base `input` of `record`, 0 if it's omitted
+    fn input_at(&self, record: &[u8], input: Input) -> f32 {
+        self.header
+            .omitted_inputs
+            .position(input)
+            .map_or(0., |index| self.base_at(record, index))
+    }
+
+    /// The initial angular velocity at the start of `record`
+    fn ang_vel_at(&self, record: &[u8]) -> Vec3A {
+        Vec3A::new(
+            self.input_at(record, Input::AngVelX),
+            self.input_at(record, Input::AngVelY),
+            self.input_at(record, Input::AngVelZ),
+        ) * self.header.ang_vel_scale
+    }
+
+    /// The relative target after the initial angular velocity in `record`. The other `TargetRotation`s only hold
+    /// the rotation to the target, which gives the angles with the initial orientation, and 0 without it
+    fn relative_target_at(&self, record: &[u8]) -> Angle {
+        let scale = self.header.angle_scale;
+
+        if self.header.target_rotation == TargetRotation::Euler {
+            return Angle {
+                pitch: self.input_at(record, Input::TargetPitch) * scale,
+                yaw: self.input_at(record, Input::TargetYaw) * scale,
+                roll: self.input_at(record, Input::TargetRoll) * scale,
+            };
+        }
+
+        let Some(orientation) = self.orientation_at(record) else {
+            return Angle::default();
+        };
+        let target = Angle::from_rotmat(RotMat::from(
+            Mat3A::from(orientation.to_rotmat()) * self.relative_rotation_at(record),
+        ));
+
+        Angle {
+            pitch: target.pitch - orientation.pitch,
+            yaw: target.yaw - orientation.yaw,
+            roll: target.roll - orientation.roll,
+        }
+    }
+
+    /// The rotation from the initial orientation to the target in `record`, in the car's initial frame.
+    /// The Euler angles only give it with the initial orientation and every target input, it's `Mat3A::ZERO` without them
+    fn relative_rotation_at(&self, record: &[u8]) -> Mat3A {
+        match self.header.target_rotation {
+            TargetRotation::Euler => {
+                let omitted = self.header.omitted_inputs;
+                let Some(orientation) = self.orientation_at(record).filter(|_| {
+                    [Input::TargetPitch, Input::TargetYaw, Input::TargetRoll]
+                        .iter()
+                        .all(|&input| !omitted.contains(input))
+                }) else {
+                    return Mat3A::ZERO;
+                };
+                let relative_target = self.relative_target_at(record);
+                let target = Angle {
+                    pitch: orientation.pitch + relative_target.pitch,
+                    yaw: orientation.yaw + relative_target.yaw,
+                    roll: orientation.roll + relative_target.roll,
+                };
+
+                Mat3A::from(orientation.to_rotmat()).transpose() * Mat3A::from(target.to_rotmat())
+            }
+            TargetRotation::Quaternion => {
+                // the last base inputs, which are never omitted
+                let start = self.header.num_inputs() - 4;
+                let [w, x, y, z] = std::array::from_fn(|i| self.base_at(record, start + i));
+
+                Mat3A::from_quat(Quat::from_xyzw(x, y, z, w).normalize())
+            }
+            TargetRotation::Rotation6d => {
+                let start = self.header.num_inputs() - 6;
+                let [x, y, z, right_x, right_y, right_z] = std::array::from_fn(|i| self.base_at(record, start + i));
+
+                // Gram-Schmidt, which undoes the rounding of the stored values, and the up axis perpendicular to both
+                let forward = Vec3A::new(x, y, z).normalize();
+                let right = Vec3A::new(right_x, right_y, right_z);
+                let right = (right - forward * forward.dot(right)).normalize();
+
+                Mat3A::from_cols(forward, right, forward.cross(right))
+            }
+        }
+    }
+
+    /// The car's initial world-frame orientation in `record`, `None` if the file doesn't have it
+    fn orientation_at(&self, record: &[u8]) -> Option<Angle> {
+        self.header
+            .fields
+            .contains(Fields::INITIAL_ORIENTATION)
+            .then(|| self.angle_at(record, self.header.offset(Fields::INITIAL_ORIENTATION)))
+    }
+
+    /// The 3 `f32`s starting at `offset` in `record`
+    fn vec_at(&self, record: &[u8], offset: usize) -> Vec3A {
+        Vec3A::new(
+            self.f32_at(record, offset),
+            self.f32_at(record, offset + 4),
+            self.f32_at(record, offset + 8),
+        )
+    }
+
+    /// The pitch, yaw and roll starting at `offset` in `record`
+    fn angle_at(&self, record: &[u8], offset: usize) -> Angle {
+        let scale = self.header.angle_scale;
+
+        Angle {
+            pitch: self.f32_at(record, offset) * scale,
+            yaw: self.f32_at(record, offset + 4) * scale,
+            roll: self.f32_at(record, offset + 8) * scale,
+        }
+    }
+}
+
+/// The `--zstd-dictionary` `id` the file at `path` was compressed with, from the file's folder or the one above it,
+/// like with `--deterministic` or `--split-by-difficulty`
+pub fn read_dictionary(path: &Path, id: u32) -> io::Result<Vec<u8>> {
+    let name = format!("dictionary-{id}.zstd");
+
+    path.ancestors()
+        .skip(1)
+        .take(2)
+        .find_map(|dir| fs::read(dir.join(&name)).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} needs {name}", path.display())))
+}
+
+/// Iterates over the records of a results file, every chunk of a `--single-file` dataset file or every file of a dataset folder
+/// as the results they were written from, with 0 for the omitted inputs and the optional fields a file doesn't have.
+///
+/// Every file is only read once its first record is reached, then decompressed, checked against its footer and checksum,
+/// and decoded with the schema of its own header, so the files of runs with different options can be read together.
+/// The headerless files of the first versions are read with the header `migrate` would rewrite them with,
+/// files of format versions newer than this build fail
+pub struct ResultReader {
+    files: vec::IntoIter<DatasetFile>,
+    /// The header of the file the last result came from
+    header: Option<Header>,
+    results: vec::IntoIter<SimResult>,
+}
+
+impl ResultReader {
+    /// Lists the files at `path`, a results file or a dataset folder with its worker folders, without reading them yet
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            files: files_or_dataset(path.as_ref())?.into_iter(),
+            header: None,
+            results: Vec::new().into_iter(),
+        })
+    }
+
+    /// The header of the file the last result came from, to tell which fields it actually has
+    pub const fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+}
+
+impl Iterator for ResultReader {
+    type Item = io::Result<SimResult>;
+
+    /// The next result, or the error of the file it would have come from, after which the next file is read
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.results.next() {
+                return Some(Ok(result));
+            }
+
+            let dataset_file = self.files.next()?;
+            match dataset_file.open() {
+                Ok(file) => {
+                    self.results = file.results().into_iter();
+                    self.header = Some(file.header);
+                }
+                Err(e) => return Some(Err(io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))),
+            }
+        }
+    }
+}
+
+/// A results file of a dataset folder, or a chunk of a `--single-file` dataset file
+#[derive(Clone)]
+pub enum DatasetFile {
+    File(PathBuf),
+    Chunk { path: PathBuf, offset: u64, len: u64 },
+}
+
+impl DatasetFile {
+    pub fn open(&self) -> io::Result<ResultsFile> {
+        match self {
+            Self::File(path) => ResultsFile::open(path),
+            Self::Chunk { path, offset, len } => {
+                ResultsFile::from_embedded(path, &dataset_file::read_chunk(path, *offset, *len)?)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DatasetFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Chunk { path, offset, .. } => write!(f, "{}@{offset}", path.display()),
+        }
+    }
+}
+
+/// Every `.bin` file in `dir` and its direct subfolders (like the worker folders of `--deterministic`), sorted,
+/// with every chunk of the `--single-file` dataset files in their place
+pub fn dataset_files(dir: &Path) -> io::Result<Vec<DatasetFile>> {
+    let mut files = Vec::new();
+
+    for path in dataset_paths(dir, |path| path.extension().is_some_and(|ext| ext == "bin"))? {
+        files.extend(files_at(&path)?);
+    }
+
+    Ok(files)
+}
+
+/// Every file in `dir` and its direct subfolders that `matches`, sorted
+pub fn dataset_paths(dir: &Path, matches: impl Fn(&Path) -> bool) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+                if matches(&path) {
+                    paths.push(path);
+                }
+            }
+        } else if matches(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Every file of the dataset folder at `path`, or `files_at` it if it's a file
+pub fn files_or_dataset(path: &Path) -> io::Result<Vec<DatasetFile>> {
+    if path.is_dir() {
+        dataset_files(path)
+    } else {
+        files_at(path)
+    }
+}
+
+/// The results file at `path`, or every chunk of it if it's a `--single-file` dataset file
+pub fn files_at(path: &Path) -> io::Result<Vec<DatasetFile>> {
+    if path.file_name().is_none_or(|name| name != dataset_file::DATASET_FILE) {
+        return Ok(vec![DatasetFile::File(path.to_path_buf())]);
+    }
+
+    let chunks = dataset_file::chunks(path)?
+        .into_iter()
+        .map(|(offset, len)| DatasetFile::Chunk {
+            path: path.to_path_buf(),
+            offset,
+            len,
+        })
+        .collect();
+
+    Ok(chunks)
+}
+
+/// The times of every record in the dataset folder `dir`, and the rocketsim_rs version it was gathered with,
+/// `None` if its files don't all have the same known version
+pub fn dataset_times(dir: &Path) -> io::Result<(Vec<f32>, Option<String>)> {
+    let mut times = Vec::new();
+    let mut versions = Vec::new();
+
+    for dataset_file in dataset_files(dir)? {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        times.extend(file.times());
+
+        if !versions.contains(&file.header.metadata.rocketsim_rs_version) {
+            versions.push(file.header.metadata.rocketsim_rs_version);
+        }
+    }
+
+    if versions.len() > 1 {
+        println!(
+            "Warning: {} mixes files from different rocketsim_rs versions: {versions:?}",
+            dir.display()
+        );
+    }
+
+    let version = if versions.len() == 1 { versions.pop().flatten() } else { None };
+    Ok((times, version))
+}
+
+/// Prints every distinct schema of the files in `dir`, with the files that have it,
+/// so a folder that mixes runs with different options can be read file by file
+pub fn print_schemas(dir: &Path) -> io::Result<()> {
+    let mut schemas = Vec::<(Vec<SchemaField>, Vec<String>)>::new();
+
+    for dataset_file in dataset_files(dir)? {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+        match schemas.iter_mut().find(|(schema, _)| *schema == file.schema) {
+            Some((_, files)) => files.push(dataset_file.to_string()),
+            None => schemas.push((file.schema, vec![dataset_file.to_string()])),
+        }
+    }
+
+    for (i, (schema, files)) in schemas.iter().enumerate() {
+        println!(
+            "Schema {i}, {} files from {} to {}:",
+            files.len(),
+            files[0],
+            files[files.len() - 1]
+        );
+        println!("  {:<20} {:<5} {:<8} {:<6} scale", "name", "type", "unit", "frame");
+
+        for field in schema {
+            println!(
+                "  {:<20} {:<5} {:<8} {:<6} {}",
+                field.name, field.value_type, field.unit, field.frame, field.scale
+            );
+        }
+    }
+
+    if schemas.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no results files found in {}", dir.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, simulation::time_to_ticks, writer::write_file, MAX_SIM_SECONDS, TICK_RATE};
+    use clap::Parser;
+    use std::env;
+
+    /// A result that took `num_steps` steps, with every other field 0
+    fn result_with_steps(num_steps: u32) -> SimResult {
+        let zero_angle = Angle {
+            pitch: 0.,
+            yaw: 0.,
+            roll: 0.,
+        };
+
+        SimResult {
+            initial_angular_velocity: Vec3A::ZERO,
+            relative_target: zero_angle,
+            relative_rotation: Mat3A::IDENTITY,
+            time: num_steps as f32 / TICK_RATE,
+            response: ControlResponse {
+                control: 0.,
+                ang_accel: 0.,
+            },
+            local_target: Vec3A::ZERO,
+            car_config_id: 0,
+            controller_id: 0,
+            initial_orientation: zero_angle,
+            world_angular_velocity: Vec3A::ZERO,
+            world_target: zero_angle,
+            world_target_dir: Vec3A::ZERO,
+            seed: 0,
+            itae: 0.,
+        }
+    }
+
+    /// Writes a file of every step count up to one past the cap with `args` and reads the times back as ticks
+    fn round_trip_ticks(name: &str, args: &[&str]) -> Vec<u32> {
+        let config = Config::parse_from(["stat-final-data"].iter().chain(args));
+        let results = (0..=(TICK_RATE * MAX_SIM_SECONDS) as u32 + 1)
+            .map(result_with_steps)
+            .collect::<Vec<_>>();
+
+        let path = temp_path(&format!("{name}.bin"));
+        write_file(path.to_str().unwrap(), &config, &Header::new(&config), &results).unwrap();
+        let file = ResultsFile::open(&path);
+        fs::remove_file(&path).unwrap();
+
+        file.unwrap().times().into_iter().map(time_to_ticks).collect()
+    }
+
+    #[test]
+    fn time_round_trips_through_every_exact_codec() {
+        let combinations: [(&str, &[&str]); 5] = [
+            ("seconds", &[]),
+            ("normalized", &["--normalize"]),
+            ("ticks", &["--time-codec", "ticks"]),
+            ("delta-ticks", &["--time-codec", "delta-ticks"]),
+            ("ticks16", &["--time-codec", "ticks16"]),
+        ];
+
+        for (name, args) in combinations {
+            for (num_steps, ticks) in round_trip_ticks(name, args).into_iter().enumerate() {
+                assert_eq!(ticks, num_steps as u32, "{num_steps} steps don't round trip with {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn i16_time_is_clamped_to_the_cap() {
+        let cap = (TICK_RATE * MAX_SIM_SECONDS) as u32;
+
+        for (num_steps, ticks) in round_trip_ticks("i16", &["--normalize", "--precision", "i16"])
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(
+                ticks,
+                (num_steps as u32).min(cap),
+                "{num_steps} steps don't round trip with i16"
+            );
+        }
+    }
+
+    #[test]
+    fn f16_time_is_only_exact_below_its_documented_limit() {
+        // an f16's spacing is a tick or more from 16 seconds, or 15 seconds normalized to half of MAX_SIM_SECONDS
+        let combinations: [(&str, &[&str], f32); 2] = [
+            ("f16", &["--precision", "f16"], 16.),
+            ("normalized-f16", &["--normalize", "--precision", "f16"], MAX_SIM_SECONDS / 2.),
+        ];
+
+        for (name, args, exact_below) in combinations {
+            for (num_steps, ticks) in round_trip_ticks(name, args).into_iter().enumerate() {
+                let num_steps = num_steps as u32;
+                let max_error = u32::from(num_steps as f32 / TICK_RATE >= exact_below);
+                assert!(
+                    ticks.abs_diff(num_steps) <= max_error,
+                    "{num_steps} steps are read back as {ticks} ticks with {name}"
+                );
+            }
+        }
+    }
+
+    /// The 7 little-endian f32s of a record of the first, headerless versions
+    fn legacy_record(ang_vel: [f32; 3], target: [f32; 3], time: f32) -> Vec<u8> {
+        ang_vel
+            .into_iter()
+            .chain(target)
+            .chain([time])
+            .flat_map(f32::to_le_bytes)
+            .collect()
+    }
+
+    /// A path in the temporary folder unique to this test run
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("stat-final-data-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn headerless_files_are_read_as_migrated() {
+        let mut bytes = legacy_record([1., -2., 3.], [0.5, -1., 2.], 90. / TICK_RATE);
+        bytes.extend(legacy_record([0., 0., 0.], [0., 0., 0.], 1. / TICK_RATE));
+        let compressed = zstd::stream::encode_all(&bytes[..], 0).unwrap();
+
+        for (name, bytes) in [("legacy.bin", bytes), ("legacy-zstd.bin", compressed)] {
+            let path = temp_path(name);
+            fs::write(&path, bytes).unwrap();
+            let file = ResultsFile::open(&path);
+            fs::remove_file(&path).unwrap();
+
+            let file = file.unwrap();
+            assert_eq!(file.format_version, LEGACY_FORMAT_VERSION);
+            assert!(file.header.metadata.migrated);
+
+            let results = file.results();
+            assert_eq!(results.len(), 2, "{name}");
+            assert_eq!(results[0].initial_angular_velocity, Vec3A::new(1., -2., 3.), "{name}");
+            assert_eq!(results[0].relative_target.yaw, -1., "{name}");
+            assert_eq!(time_to_ticks(results[0].time), 90, "{name}");
+            assert_eq!(time_to_ticks(results[1].time), 1, "{name}");
+        }
+    }
+
+    #[test]
+    fn headerless_files_that_dont_parse_are_refused() {
+        let records: [(&str, Vec<u8>); 3] = [
+            ("truncated", legacy_record([0.; 3], [0.; 3], 1. / TICK_RATE)[..20].to_vec()),
+            ("off-grid", legacy_record([0.; 3], [0.; 3], 1.5 / TICK_RATE)),
+            ("not-finite", legacy_record([f32::NAN, 0., 0.], [0.; 3], 1. / TICK_RATE)),
+        ];
+
+        for (name, bytes) in records {
+            let path = temp_path(&format!("{name}.bin"));
+            fs::write(&path, bytes).unwrap();
+            let file = ResultsFile::open(&path);
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(file.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData), "{name}");
+        }
+    }
+
+    #[test]
+    fn newer_format_versions_are_refused() {
+        let config = Config::parse_from(["stat-final-data"]);
+        let header = Header::new(&config);
+        let newer = format!("format version {}", format::FORMAT_VERSION + 1);
+
+        let path = temp_path("newer.bin");
+        write_file(path.to_str().unwrap(), &config, &header, &[result_with_steps(1)]).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&(format::FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let file = ResultsFile::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(file.err().unwrap().to_string().contains(&newer));
+
+        let mut sidecar = serde_json::to_value(Sidecar::new(&header, 0)).unwrap();
+        sidecar["format_version"] = (format::FORMAT_VERSION + 1).into();
+        let path = temp_path("newer.json");
+        fs::write(&path, serde_json::to_vec(&sidecar).unwrap()).unwrap();
+        let read = Sidecar::read_from(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(read.err().unwrap().to_string().contains(&newer));
+    }
+}
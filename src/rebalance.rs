@@ -0,0 +1,130 @@
+use crate::{
+    format::TimeCodec,
+    merge::{self, MergedHeader},
+    writer::write_serialized,
+};
+use fastrand::Rng;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Writes the records of every results file in `inputs`, or in the dataset folders among them, to one file at `output`,
+/// resampled so their times to reach the target are spread uniformly over buckets of `bin_width` seconds up to `max_time`.
+///
+/// The `num_records` records, as many as the inputs by default, are shared evenly by the buckets that have any,
+/// so the fuller buckets are drawn without replacement and the emptier ones repeat their records.
+/// Records slower than `max_time`, the slowest record by default, are dropped
+pub fn rebalance(
+    inputs: &[PathBuf],
+    output: &Path,
+    bin_width: f32,
+    max_time: Option<f32>,
+    num_records: Option<usize>,
+    seed: Option<u64>,
+) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+
+    let mut merged = MergedHeader::default();
+    let mut records = Vec::new();
+    let mut times = Vec::new();
+
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        merged.add(dataset_file, &file)?;
+
+        let file_times = file.times();
+        let mut file_records = file.records;
+        // a delta only means something after the record before it, which is drawn on its own
+        if file.header.time_codec == TimeCodec::DeltaTicks {
+            merge::absolute_ticks(&file.header, &mut file_records);
+        }
+
+        records.extend(file_records);
+        times.extend(file_times);
+    }
+
+    let Some(mut header) = merged.finish() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no results files to rebalance"));
+    };
+    if header.time_codec == TimeCodec::DeltaTicks {
+        header.time_codec = TimeCodec::Ticks;
+    }
+
+    let max_time = max_time.unwrap_or_else(|| times.iter().copied().fold(0., f32::max));
+    let num_buckets = ((max_time / bin_width).floor() as usize + 1).max(1);
+    let mut buckets = vec![Vec::new(); num_buckets];
+    for (i, &time) in times.iter().enumerate() {
+        if time <= max_time {
+            buckets[((time / bin_width) as usize).min(num_buckets - 1)].push(i);
+        }
+    }
+
+    let num_filled = buckets.iter().filter(|bucket| !bucket.is_empty()).count();
+    if num_filled == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no records reach the target within {max_time} s"),
+        ));
+    }
+
+    let record_size = header.record_size();
+    let num_records = num_records.unwrap_or(times.len());
+    let mut rng = seed.map_or_else(Rng::new, Rng::with_seed);
+    let mut drawn = Vec::with_capacity(num_records);
+    // the buckets with records drawn so far
+    let mut filled = 0;
+
+    println!("  {:<17} {:>9} -> drawn", "time (s)", "records");
+    for (bin, bucket) in buckets.iter_mut().enumerate() {
+        if bucket.is_empty() {
+            continue;
+        }
+
+        // the first buckets take the remainder, so the counts add up to `num_records`
+        let num_drawn = num_records / num_filled + usize::from(filled < num_records % num_filled);
+        filled += 1;
+
+        rng.shuffle(bucket);
+        drawn.extend(bucket.iter().take(num_drawn));
+        if num_drawn > bucket.len() {
+            drawn.extend((bucket.len()..num_drawn).map(|_| bucket[rng.usize(..bucket.len())]));
+        }
+
+        let start = bin as f32 * bin_width;
+        println!(
+            "  {:<17} {:>9} -> {num_drawn}",
+            format!("{start:.2}-{:.2}", start + bin_width),
+            bucket.len()
+        );
+    }
+
+    rng.shuffle(&mut drawn);
+    let rebalanced = drawn
+        .iter()
+        .flat_map(|&i| &records[i * record_size..(i + 1) * record_size])
+        .copied()
+        .collect::<Vec<_>>();
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+    write_serialized(
+        &output.to_string_lossy(),
+        &header_bytes,
+        header.compression,
+        &rebalanced,
+        drawn.len(),
+    )?;
+
+    println!(
+        "Rebalanced {} records from {} files into {} records in {num_filled} buckets of {bin_width} s, written to {}",
+        times.len(),
+        files.len(),
+        drawn.len(),
+        output.display()
+    );
+
+    Ok(())
+}
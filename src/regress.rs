@@ -0,0 +1,104 @@
+use crate::{
+    config::Config,
+    simulation::{time_to_ticks, Simulation},
+    ROCKETSIM_RS_VERSION,
+};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// Written to the reference file of `regress`, the ticks every golden sample took with the build that wrote it
+#[derive(Serialize, Deserialize)]
+struct GoldenSamples {
+    rocketsim_rs_version: String,
+    /// The seed of the worker the samples were drawn by
+    seed: u64,
+    samples: Vec<GoldenSample>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct GoldenSample {
+    /// The seed of the sample, for `regenerate` and `trace`
+    seed: u64,
+    /// The ticks it took to reach the target, `None` if it never did
+    ticks: Option<u32>,
+}
+
+/// Draws and simulates `num_samples` samples with the default options, like a worker seeded with `seed`,
+/// and compares the ticks each took with the reference file at `reference`, failing on any difference.
+/// Writes the reference instead if it doesn't exist yet or with `update`
+pub fn regress(reference: &Path, num_samples: usize, seed: u64, update: bool) -> io::Result<()> {
+    let mut simulation = Simulation::new(&Config::parse_from([env!("CARGO_PKG_NAME")]), Some(seed));
+    let samples = (0..num_samples)
+        .map(|_| {
+            let sample_seed = simulation.rng.get_seed();
+            GoldenSample {
+                seed: sample_seed,
+                ticks: simulation.do_random().map(|result| time_to_ticks(result.time)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if update || !reference.exists() {
+        let golden = GoldenSamples {
+            rocketsim_rs_version: ROCKETSIM_RS_VERSION.to_string(),
+            seed,
+            samples,
+        };
+        fs::write(reference, serde_json::to_vec_pretty(&golden)?)?;
+
+        println!(
+            "Wrote the ticks of {num_samples} samples with rocketsim_rs {ROCKETSIM_RS_VERSION} to {}",
+            reference.display()
+        );
+        return Ok(());
+    }
+
+    let golden: GoldenSamples = serde_json::from_slice(&fs::read(reference)?)?;
+    if golden.seed != seed || golden.samples.len() != num_samples {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} holds {} samples of seed {}, pass --samples {} --seed {} to compare with it",
+                reference.display(),
+                golden.samples.len(),
+                golden.seed,
+                golden.samples.len(),
+                golden.seed
+            ),
+        ));
+    }
+
+    let format_ticks = |ticks: Option<u32>| ticks.map_or_else(|| "never".to_string(), |ticks| ticks.to_string());
+    let mut num_diverged = 0;
+    for (i, (golden_sample, sample)) in golden.samples.iter().zip(&samples).enumerate() {
+        if golden_sample != sample {
+            println!(
+                "Sample {i} (seed {}): {} ticks with rocketsim_rs {}, {} ticks now",
+                sample.seed,
+                format_ticks(golden_sample.ticks),
+                golden.rocketsim_rs_version,
+                format_ticks(sample.ticks)
+            );
+            num_diverged += 1;
+        }
+    }
+
+    if num_diverged != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{num_diverged} of {num_samples} samples diverged from rocketsim_rs {} with {ROCKETSIM_RS_VERSION}, \
+                 data gathered with the two shouldn't be mixed",
+                golden.rocketsim_rs_version
+            ),
+        ));
+    }
+
+    println!(
+        "All {num_samples} samples matched rocketsim_rs {} with {ROCKETSIM_RS_VERSION}",
+        golden.rocketsim_rs_version
+    );
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use crate::{
+    config::Config,
+    controller::PdGains,
+    format::Metadata,
+    reader::{self, InitialConditions},
+    simulation::{time_to_ticks, CarPreset, SampleStart, Simulation},
+};
+use clap::Parser;
+use fastrand::Rng;
+use rocketsim_rs::glam_ext::glam::Mat3A;
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+    thread,
+};
+
+/// The generation options of the dataset that change the dynamics, everything else at its default
+fn config_from_metadata(metadata: &Metadata) -> Config {
+    let mut config = Config::parse_from([env!("CARGO_PKG_NAME")]);
+
+    config.arena_mode = metadata.arena_mode;
+    // files from before --car-configs only used the octane
+    config.car_configs = if metadata.car_configs.is_empty() {
+        vec![CarPreset::Octane]
+    } else {
+        metadata.car_configs.clone()
+    };
+    config.up_reference = metadata.up_reference;
+    config.up_direction = metadata.up_direction;
+    config.stop_condition = metadata.stop_condition;
+    if let Some(settle_ang_vel) = metadata.settle_ang_vel {
+        config.settle_ang_vel = settle_ang_vel;
+    }
+    [config.max_rate_x, config.max_rate_y, config.max_rate_z] = metadata.max_rates;
+    // older files were gathered with the defaults
+    if let Some(max_ang_vel) = metadata.max_ang_vel {
+        config.max_ang_vel = max_ang_vel;
+    }
+    if let Some(max_angle) = metadata.max_angle {
+        config.max_angle = max_angle;
+    }
+    if let Some(convergence_threshold) = metadata.convergence_threshold {
+        config.convergence_threshold = convergence_threshold;
+    }
+    if let Some(max_sim_seconds) = metadata.max_sim_seconds {
+        config.max_sim_seconds = max_sim_seconds;
+    }
+
+    config
+}
+
+/// Draws the sample of `seed` again with the sampling options and the dynamics of the dataset folder `dataset`,
+/// like a worker seeded with it would, and prints it
+pub fn regenerate(dataset: &Path, seed: u64) -> io::Result<()> {
+    let mut simulation = dataset_simulation(dataset, Some(seed))?;
+
+    match simulation.do_random() {
+        Some(result) => println!("{result:#?}"),
+        None => println!("The sample of seed {seed} was dropped, so it was never recorded"),
+    }
+
+    Ok(())
+}
+
+/// A simulation with the sampling options, the dynamics and the controllers of the dataset folder `dataset`,
+/// like a worker seeded with `seed`
+pub fn dataset_simulation(dataset: &Path, seed: Option<u64>) -> io::Result<Simulation> {
+    let files = reader::dataset_files(dataset)?;
+    let Some(dataset_file) = files.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no results files found in {}", dataset.display()),
+        ));
+    };
+
+    let file = dataset_file
+        .open()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+    Ok(metadata_simulation(&file.header.metadata, seed))
+}
+
+/// A simulation with the sampling options, the dynamics and the controllers in `metadata`, like a worker seeded with `seed`
+pub fn metadata_simulation(metadata: &Metadata, seed: Option<u64>) -> Simulation {
+    let mut config = config_from_metadata(metadata);
+    config.fix = metadata.fixed.iter().map(|(&dim, &value)| (dim, value)).collect();
+    config.nominal = metadata.nominal;
+    if let Some(perturbation_std) = metadata.perturbation_std {
+        config.perturbation_std = perturbation_std;
+    }
+
+    let mut simulation = Simulation::new(&config, seed);
+    // files from before --controller only used the default gains
+    if !metadata.controllers.is_empty() {
+        simulation.controllers = metadata.controllers.clone();
+    }
+
+    simulation
+}
+
+/// The new time to reach the target of every sample with its controller in `controllers`, `None` if it wasn't reached
+fn simulate(config: &Config, controllers: &[PdGains], conditions: &[InitialConditions]) -> Vec<Option<f32>> {
+    let mut simulation = Simulation::new(config, None);
+    simulation.controllers = controllers.to_vec();
+
+    conditions
+        .iter()
+        .map(|conditions| {
+            let rot = Mat3A::from(conditions.orientation.to_rotmat());
+            let ang_vel = rot * conditions.ang_vel;
+            let target_pitch = conditions.orientation.pitch + conditions.relative_target.pitch;
+            let target_yaw = conditions.orientation.yaw + conditions.relative_target.yaw;
+
+            let start = SampleStart {
+                car_config_id: conditions.car_config_id,
+                controller_id: conditions.controller_id,
+                ang_vel,
+                angle: conditions.orientation,
+                target_pitch,
+                target_yaw,
+            };
+
+            simulation.simulate_to_target(0, start).map(|result| result.time)
+        })
+        .collect()
+}
+
+/// `simulate` with the samples spread over a thread for every core, each with its own arenas
+fn simulate_parallel(
+    config: &Config,
+    controllers: &[PdGains],
+    conditions: &[InitialConditions],
+) -> io::Result<Vec<Option<f32>>> {
+    let num_threads = thread::available_parallelism()?.get();
+    let chunk_size = conditions.len().div_ceil(num_threads).max(1);
+
+    let new_times = thread::scope(|s| {
+        let handles = conditions
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || simulate(config, controllers, chunk)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    Ok(new_times)
+}
+
+/// Simulates every sample in the dataset folder `dataset` again with `gains`,
+/// writing the original and the new time of each to the CSV file `output`
+pub fn replay(dataset: &Path, gains: PdGains, output: &Path) -> io::Result<()> {
+    let files = reader::dataset_files(dataset)?;
+
+    let mut writer = BufWriter::new(fs::File::create(output)?);
+    writeln!(writer, "file,record,original_time,new_time")?;
+
+    let (mut num_records, mut num_reached, mut num_faster) = (0u64, 0u64, 0u64);
+    let (mut total_original, mut total_new) = (0f64, 0f64);
+
+    for dataset_file in files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+        let Some(conditions) = file.initial_conditions() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{dataset_file} doesn't have the initial orientation or every Euler input, \
+                    gather it with --record-initial-orientation and without --omit-inputs or --target-rotation"
+                ),
+            ));
+        };
+
+        // every sample is simulated with `gains` instead of its own controller
+        let conditions = conditions
+            .into_iter()
+            .map(|conditions| InitialConditions {
+                controller_id: 0,
+                ..conditions
+            })
+            .collect::<Vec<_>>();
+
+        let config = config_from_metadata(&file.header.metadata);
+        let new_times = simulate_parallel(&config, &[gains], &conditions)?;
+
+        for (record, (original_time, new_time)) in file.times().into_iter().zip(new_times).enumerate() {
+            num_records += 1;
+            total_original += f64::from(original_time);
+
+            match new_time {
+                Some(new_time) => {
+                    num_reached += 1;
+                    total_new += f64::from(new_time);
+                    num_faster += u64::from(new_time < original_time);
+                    writeln!(writer, "{dataset_file},{record},{original_time},{new_time}")?;
+                }
+                None => writeln!(writer, "{dataset_file},{record},{original_time},")?,
+            }
+        }
+
+        println!("Replayed {dataset_file}");
+    }
+
+    writer.flush()?;
+
+    if num_records == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no records found in {}", dataset.display()),
+        ));
+    }
+
+    println!("Wrote {num_records} paired times to {}", output.display());
+    println!("Original mean time: {:.4} s", total_original / num_records as f64);
+    println!(
+        "New mean time: {:.4} s, {num_reached} reached the target and {num_faster} were faster",
+        total_new / num_reached.max(1) as f64
+    );
+
+    Ok(())
+}
+
+/// Simulates `num_samples` random records of every results file at `input`, a file or a dataset folder,
+/// again from their initial conditions with the controller and dynamics they were gathered with,
+/// and prints every one whose time is more than `tolerance_ticks` off, failing if any is
+pub fn validate(input: &Path, num_samples: usize, tolerance_ticks: u32, seed: Option<u64>) -> io::Result<()> {
+    let mut rng = seed.map_or_else(Rng::new, Rng::with_seed);
+    let (mut num_checked, mut num_mismatched) = (0, 0);
+
+    for dataset_file in reader::files_or_dataset(input)? {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+        let Some(conditions) = file.initial_conditions() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{dataset_file} doesn't have the initial orientation or every Euler input, \
+                    gather it with --record-initial-orientation and without --omit-inputs or --target-rotation"
+                ),
+            ));
+        };
+
+        let mut records = (0..conditions.len()).collect::<Vec<_>>();
+        rng.shuffle(&mut records);
+        records.truncate(num_samples);
+        records.sort_unstable();
+
+        let metadata = &file.header.metadata;
+        let config = config_from_metadata(metadata);
+        // files from before --controller only used the default gains
+        let controllers = if metadata.controllers.is_empty() {
+            config.controllers()
+        } else {
+            metadata.controllers.clone()
+        };
+
+        let sampled = records.iter().map(|&record| conditions[record]).collect::<Vec<_>>();
+        let new_times = simulate_parallel(&config, &controllers, &sampled)?;
+        let times = file.times();
+
+        for (&record, new_time) in records.iter().zip(new_times) {
+            let original_ticks = time_to_ticks(times[record]);
+            num_checked += 1;
+
+            let matched =
+                new_time.is_some_and(|new_time| time_to_ticks(new_time).abs_diff(original_ticks) <= tolerance_ticks);
+            if !matched {
+                num_mismatched += 1;
+                let new_time = new_time.map_or("never reached the target".to_string(), |time| format!("{time} s"));
+                println!(
+                    "{dataset_file} record {record}: stored {} s, simulated {new_time}",
+                    times[record]
+                );
+            }
+        }
+
+        println!("Validated {} records of {dataset_file}", records.len());
+    }
+
+    if num_checked == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no records found in {}", input.display()),
+        ));
+    }
+
+    println!(
+        "{} of {num_checked} records matched their stored time",
+        num_checked - num_mismatched
+    );
+
+    if num_mismatched != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{num_mismatched} of {num_checked} records didn't match, the dataset drifted from this build"),
+        ));
+    }
+
+    Ok(())
+}
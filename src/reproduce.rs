@@ -0,0 +1,165 @@
+use crate::{
+    config::Config,
+    format::{self, Layout},
+    gaps::OccupancyGrid,
+    percentile::PercentileFilter,
+    reader::{self, ResultsFile},
+    replay,
+    writer::write_serialized_with_workers,
+    GAP_GRID, ZSTD_DICTIONARY, ZSTD_LEVEL,
+};
+use clap::Parser;
+use fastrand::Rng;
+use std::{io, path::Path, sync::atomic::Ordering};
+
+/// Simulates the records of the `worker-{id}/{n}.bin` file at `path` of a `--deterministic` run again, like worker `id`
+/// seeded from the run's seed would, and writes them to `path` with the header of an intact file of the run,
+/// compressed at the zstd level and with the workers in its metadata.
+///
+/// An intact file is only compared with the simulated records. The records before the file are counted from the
+/// files before it that are intact, and every other file is assumed to hold the run's `records_per_file`, like the file itself
+pub fn reproduce(path: &Path) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+    let n = path
+        .file_stem()
+        .and_then(|stem| stem.to_str()?.parse::<usize>().ok())
+        .filter(|_| path.extension().is_some_and(|ext| ext == "bin"));
+    let worker_dir = path.parent().unwrap_or(Path::new(""));
+    let worker_id = worker_dir
+        .file_name()
+        .and_then(|name| name.to_str()?.strip_prefix("worker-")?.parse::<usize>().ok());
+    let (Some(n), Some(worker_id), Some(dataset)) = (n, worker_id, worker_dir.parent()) else {
+        return Err(invalid(format!(
+            "{} isn't a worker-{{id}}/{{n}}.bin file of a --deterministic run",
+            path.display()
+        )));
+    };
+
+    // every file of the run has the same header
+    let header = reader::dataset_files(dataset)?
+        .iter()
+        .find_map(|dataset_file| dataset_file.open().ok())
+        .map(|file| file.header)
+        .ok_or_else(|| {
+            invalid(format!(
+                "no intact results file of {} to take the header from",
+                dataset.display()
+            ))
+        })?;
+    let metadata = &header.metadata;
+
+    let (Some(seed), Some(records_per_file)) = (metadata.seed, metadata.records_per_file) else {
+        return Err(invalid(format!(
+            "{} wasn't gathered with --deterministic, so its files can't be reproduced",
+            dataset.display()
+        )));
+    };
+    let records_per_file = records_per_file as usize;
+
+    // the workers' seeds are drawn from the run's seed in order
+    let mut seeds = Rng::with_seed(seed);
+    let worker_seed = (0..=worker_id).map(|_| seeds.u64(..)).last().unwrap();
+
+    let num_before = (0..n)
+        .map(|k| {
+            ResultsFile::open(worker_dir.join(format!("{k}.bin")))
+                .map_or(records_per_file, |file| file.records.len() / file.header.record_size())
+        })
+        .sum::<usize>();
+
+    let mut simulation = replay::metadata_simulation(metadata, Some(worker_seed));
+    if let Some(reference) = &metadata.fill_gaps {
+        let (grid, loaded) = OccupancyGrid::from_dataset(Path::new(&reference.path))?;
+        if loaded.num_records != reference.num_records || loaded.records_hash != reference.records_hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the --fill-gaps reference {} changed since the run", reference.path),
+            ));
+        }
+
+        assert!(GAP_GRID.set(grid).is_ok(), "the gap grid is only built once");
+        simulation.gap_grid = GAP_GRID.get();
+    }
+
+    // an intact file is only checked, and may hold fewer records after a run stopped with Ctrl+C
+    let existing = ResultsFile::open(path).ok();
+    let num_records = existing
+        .as_ref()
+        .map_or(records_per_file, |file| file.records.len() / file.header.record_size());
+
+    let mut record_above = metadata
+        .record_above_percentile
+        .map(|percentile| PercentileFilter::new(percentile, metadata.percentile_warmup.unwrap_or(0)));
+
+    // the worker's samples from the start of the run, only keeping those of the file
+    let mut num_recorded = 0;
+    let mut results = Vec::with_capacity(num_records);
+    while results.len() < num_records {
+        let Some(result) = simulation.do_random() else {
+            continue;
+        };
+
+        if record_above.as_mut().is_none_or(|filter| filter.keep(result.time)) {
+            if num_recorded >= num_before {
+                results.push(result);
+            }
+            num_recorded += 1;
+        }
+    }
+
+    let records = format::serialize(&header, &results, None);
+
+    if let Some(file) = existing {
+        let rows = match header.layout {
+            Layout::Rows => records,
+            Layout::Columns => format::to_rows(&header, &records),
+        };
+
+        if file.records == rows {
+            println!("{} is intact and matches its {num_records} simulated records", path.display());
+            return Ok(());
+        }
+
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is intact but doesn't match its simulated records, it wasn't written by this build or these options",
+                path.display()
+            ),
+        ));
+    }
+
+    if let Some(id) = metadata.zstd_dictionary_id {
+        ZSTD_DICTIONARY.set(reader::read_dictionary(path, id)?).unwrap();
+    }
+
+    let zstd_level = metadata.zstd_level.unwrap_or_else(|| {
+        let default = Config::parse_from([env!("CARGO_PKG_NAME")]).zstd_level;
+        println!(
+            "{} doesn't store its zstd level, compressing at the default {default}, the bytes may differ",
+            dataset.display()
+        );
+        default
+    });
+    ZSTD_LEVEL.store(zstd_level, Ordering::Relaxed);
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+    write_serialized_with_workers(
+        &path.to_string_lossy(),
+        &header_bytes,
+        header.compression,
+        &records,
+        results.len(),
+        metadata.zstd_workers.unwrap_or(0),
+    )?;
+
+    println!(
+        "Reproduced {} with records {num_before} to {} of worker {worker_id}",
+        path.display(),
+        num_before + results.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,1101 @@
+#[cfg(feature = "upload")]
+use crate::upload;
+use crate::{
+    checkpoint::Checkpoint,
+    compare,
+    config::{Command, Config},
+    controller::PdGains,
+    convert, coverage, dedup,
+    format::{self, Header, Manifest, OutputFormat},
+    gaps::OccupancyGrid,
+    inspect, merge, migrate, normalize, outliers, pack,
+    percentile::PercentileFilter,
+    reader, rebalance, regress, replay, reproduce, shuffle,
+    simulation::{time_round_trip_violations, SimResult, Simulation},
+    split, stats, stream, trace, verify,
+    writer::{create_wal, next_file_index, next_file_name, recover_wal, train_dictionary, write_file, WAL_FILE},
+    GAP_GRID, INTERVAL_MILLIS, NUM_RECORDS, NUM_SAMPLES, PAUSED, STOPPING, ZSTD_DICTIONARY, ZSTD_LEVEL,
+};
+use clap::{error::ErrorKind, CommandFactory, ValueEnum};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use fastrand::Rng;
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Write},
+    mem,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How often paused workers check if they should resume
+const PAUSE_POLL_TIME: Duration = Duration::from_millis(100);
+/// How often the `--config` file is checked for changes while running
+const CONFIG_POLL_TIME: Duration = Duration::from_secs(2);
+
+/// Runs the subcommand of `config`, or gathers samples into `--out-dir` until stopped
+pub fn run(config: &Config) {
+    if let Some(command) = &config.command {
+        if let Err(e) = run_command(command) {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    // the class has to fit in a u8
+    if !config.difficulty_thresholds.is_sorted_by(|a, b| a < b) || config.difficulty_thresholds.len() > u8::MAX.into() {
+        Config::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "--difficulty-thresholds must be strictly increasing, with at most 255 of them",
+            )
+            .exit();
+    }
+
+    // the other formats have typed fields and the write-ahead log holds binary records
+    if config.format != OutputFormat::Binary
+        && (config.time_codec != format::TimeCodec::Seconds
+            || config.byte_order != format::ByteOrder::Little
+            || config.layout != format::Layout::Rows
+            || config.precision != format::Precision::F32
+            || config.compression != format::Compression::Zstd
+            || config.zstd_dictionary
+            || config.wal)
+    {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--time-codec, --byte-order, --layout, --precision, --compression, --zstd-dictionary and --wal \
+                only apply to --format binary",
+            )
+            .exit();
+    }
+
+    if config.compression != format::Compression::Zstd && config.zstd_dictionary {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--zstd-dictionary only applies to --compression zstd",
+            )
+            .exit();
+    }
+
+    // the tick codecs store whole numbers
+    if config.precision != format::Precision::F32 && config.time_codec != format::TimeCodec::Seconds {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--precision f16 and i16 only apply to --time-codec seconds",
+            )
+            .exit();
+    }
+
+    if config.format != OutputFormat::Binary && config.sidecar_metadata {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--sidecar-metadata only applies to --format binary",
+            )
+            .exit();
+    }
+
+    // a quaternion has no pitch, yaw or roll to leave out
+    if config.target_rotation != format::TargetRotation::Euler
+        && config.omit_inputs.iter().any(|input| {
+            matches!(
+                input,
+                format::Input::TargetPitch | format::Input::TargetYaw | format::Input::TargetRoll
+            )
+        })
+    {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--omit-inputs target-pitch, target-yaw and target-roll only apply to --target-rotation euler",
+            )
+            .exit();
+    }
+
+    // their schemas hold the Euler angles
+    #[cfg(feature = "protobuf")]
+    let euler_only = matches!(config.format, OutputFormat::Protobuf | OutputFormat::Capnp);
+    #[cfg(not(feature = "protobuf"))]
+    let euler_only = config.format == OutputFormat::Capnp;
+
+    if euler_only && config.target_rotation != format::TargetRotation::Euler {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--format protobuf and capnp only hold --target-rotation euler",
+            )
+            .exit();
+    }
+
+    #[cfg(not(feature = "upload"))]
+    if config.upload_to.is_some() {
+        Config::command()
+            .error(ErrorKind::InvalidValue, "--upload-to needs the upload feature")
+            .exit();
+    }
+
+    // the one HDF5 file is only finished once the run ends
+    #[cfg(feature = "hdf5")]
+    if config.format == OutputFormat::Hdf5 && config.upload_to.is_some() {
+        Config::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--upload-to can't be combined with --format hdf5",
+            )
+            .exit();
+    }
+
+    // the other formats only have f32 and i32 columns
+    if config.format != OutputFormat::Binary && config.record_seed {
+        Config::command()
+            .error(ErrorKind::ArgumentConflict, "--record-seed only applies to --format binary")
+            .exit();
+    }
+
+    if config.format != OutputFormat::Binary && config.single_file {
+        Config::command()
+            .error(ErrorKind::ArgumentConflict, "--single-file only applies to --format binary")
+            .exit();
+    }
+
+    // the id has to fit in a u8
+    if config.controller.len() > u8::MAX.into() {
+        Config::command()
+            .error(ErrorKind::ValueValidation, "at most 255 --controller can be given")
+            .exit();
+    }
+
+    if (1..config.car_configs.len()).any(|i| config.car_configs[..i].contains(&config.car_configs[i])) {
+        Config::command()
+            .error(ErrorKind::ValueValidation, "--car-configs can't list a config more than once")
+            .exit();
+    }
+
+    if let Some(meshes_folder) = config.arena_mode.meshes_folder() {
+        // RocketSim skips a missing folder, then fails to create the arena with no useful message
+        let folder = Path::new(&config.assets_dir).join(meshes_folder);
+        let has_meshes = fs::read_dir(&folder).is_ok_and(|mut entries| {
+            entries.any(|entry| entry.is_ok_and(|entry| entry.path().extension().is_some_and(|ext| ext == "cmf")))
+        });
+
+        if !has_meshes {
+            Config::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "--arena-mode {} needs collision meshes, but no .cmf files were found in {}",
+                        config.arena_mode.to_possible_value().unwrap().get_name(),
+                        folder.display()
+                    ),
+                )
+                .exit();
+        }
+    }
+
+    if config.soak {
+        // every possible step count is checked up front, the rest are only checked as they come up
+        for num_steps in time_round_trip_violations(config.max_sim_seconds) {
+            println!("Soak check failed: the time of {num_steps} steps doesn't round back to {num_steps} ticks");
+        }
+    }
+
+    rocketsim_rs::init(Some(&config.assets_dir));
+
+    let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    fs::create_dir_all(&config.out_dir).unwrap();
+
+    let run_dir = config.per_run_dir.then(|| {
+        let run_dir = format!("{}/run-{started_at}", config.out_dir);
+        fs::create_dir(&run_dir).unwrap();
+        run_dir
+    });
+    let out_dir = run_dir.as_deref().unwrap_or(&config.out_dir);
+
+    let gap_reference = config.fill_gaps.as_ref().map(|dir| {
+        let (grid, reference) = OccupancyGrid::from_dataset(dir).unwrap();
+        println!(
+            "Filling the gaps of {} records from {} files in {}",
+            reference.num_records,
+            reference.num_files,
+            dir.display()
+        );
+
+        assert!(GAP_GRID.set(grid).is_ok(), "the gap grid is only built once");
+        reference
+    });
+
+    let (tx, rx) = unbounded();
+
+    let mut num_threads = config
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().unwrap())
+        .get();
+
+    ctrlc::set_handler(|| {
+        if STOPPING.swap(true, Ordering::Relaxed) {
+            process::exit(130);
+        }
+
+        println!("\nStopping after writing the results gathered so far, press Ctrl+C again to exit without them");
+    })
+    .unwrap();
+
+    INTERVAL_MILLIS.store(interval_millis(config.interval), Ordering::Relaxed);
+    ZSTD_LEVEL.store(config.zstd_level, Ordering::Relaxed);
+    let mut workers = Vec::with_capacity(num_threads);
+
+    let checkpoint = Checkpoint::read_from(Path::new(out_dir)).unwrap().unwrap_or_default();
+    if checkpoint.num_files != 0 {
+        println!(
+            "Resuming from the checkpoint in {out_dir}: {:.2} days simulated, {} records in {} files",
+            checkpoint.total_time / 3600. / 24.,
+            checkpoint.total_records,
+            checkpoint.num_files
+        );
+    }
+
+    NUM_SAMPLES.store(checkpoint.num_samples, Ordering::Relaxed);
+    NUM_RECORDS.store(checkpoint.total_records, Ordering::Relaxed);
+
+    if config
+        .max_samples
+        .is_some_and(|max_samples| checkpoint.total_records >= max_samples)
+    {
+        println!("The dataset in {out_dir} already holds --max-samples records");
+        return;
+    }
+
+    if let Some(max_duration) = config.max_duration {
+        thread::spawn(move || {
+            thread::sleep(max_duration);
+            println!("Reached --max-duration, stopping");
+            STOPPING.store(true, Ordering::Relaxed);
+        });
+    }
+
+    // continuing the RNGs of the last run with the same seed doesn't draw the samples it already wrote again
+    let resumed_rng_states =
+        (config.seed.is_some() && checkpoint.seed == config.seed && checkpoint.rng_states.len() == num_threads)
+            .then_some(&checkpoint.rng_states);
+
+    // each worker gets its own stable seed, derived from a random one without --seed so any file can be reproduced
+    let master_seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+    let mut seeds = Rng::with_seed(master_seed);
+
+    if config.seed.is_none() {
+        println!("Seeding the workers with the random seed {master_seed}");
+    }
+    let mut rng_states = Vec::with_capacity(num_threads);
+
+    // in deterministic mode, which records are written can't depend on which worker is faster,
+    // so every worker gets its own share of --max-samples instead of racing the others for it
+    let worker_records = if !config.deterministic {
+        Vec::new()
+    } else if checkpoint.worker_records.len() == num_threads {
+        checkpoint.worker_records.clone()
+    } else {
+        vec![0; num_threads]
+    };
+    let worker_quotas = (0..num_threads)
+        .map(|id| {
+            let max_samples = config.max_samples.filter(|_| config.deterministic)?;
+            Some(if checkpoint.worker_records.len() == num_threads {
+                worker_share(max_samples, id, num_threads).saturating_sub(worker_records[id])
+            } else {
+                worker_share(max_samples.saturating_sub(checkpoint.total_records), id, num_threads)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for id in 0..num_threads {
+        let tx = tx.clone();
+        let config = config.clone();
+        let seed = seeds.u64(..);
+        let seed = resumed_rng_states.map_or(seed, |states| states[id]);
+        rng_states.push(seed);
+        let quota = worker_quotas[id];
+        let worker = thread::Builder::new().name(format!("worker-{id}")).spawn(move || {
+            let mut simulation = Simulation::new(&config, Some(seed));
+            let mut initial_allocation_num = 4096;
+            let mut samples_since_reset = 0;
+            // kept across batches and recreated simulations, so the warmup only happens once
+            let mut record_above = config
+                .record_above_percentile
+                .map(|percentile| PercentileFilter::new(percentile, config.percentile_warmup));
+            let mut num_records = 0;
+
+            loop {
+                let mut results = Vec::with_capacity(initial_allocation_num);
+                let interval = Duration::from_millis(INTERVAL_MILLIS.load(Ordering::Relaxed));
+                let mut interval_end_time = Instant::now() + interval;
+
+                // deterministic batches depend only on the seed, not on how fast the machine is
+                while !STOPPING.load(Ordering::Relaxed)
+                    && if config.deterministic {
+                        results.len() < config.deterministic_records_per_file() as usize
+                            && quota.is_none_or(|quota| num_records < quota)
+                    } else {
+                        Instant::now() < interval_end_time
+                    }
+                {
+                    if PAUSED.load(Ordering::Relaxed) {
+                        // time spent paused doesn't count towards the interval
+                        thread::sleep(PAUSE_POLL_TIME);
+                        interval_end_time += PAUSE_POLL_TIME;
+                        continue;
+                    }
+
+                    if config
+                        .reset_every
+                        .is_some_and(|reset_every| samples_since_reset >= reset_every)
+                    {
+                        // continuing the RNG keeps the samples the same as without resets
+                        simulation = Simulation::new(&config, Some(simulation.rng.get_seed()));
+                        samples_since_reset = 0;
+                    }
+
+                    // a panic (e.g. from RocketSim) only loses the current sample
+                    let sample = panic::catch_unwind(AssertUnwindSafe(|| simulation.do_random()));
+                    NUM_SAMPLES.fetch_add(1, Ordering::Relaxed);
+                    samples_since_reset += 1;
+
+                    match sample {
+                        Ok(Some(result)) => {
+                            if !record_above.as_mut().is_none_or(|filter| filter.keep(result.time)) {
+                                continue;
+                            }
+
+                            if config.deterministic {
+                                results.push(result);
+                                num_records += 1;
+
+                                if quota == Some(num_records) {
+                                    println!("worker-{id} reached its share of --max-samples");
+                                }
+                                continue;
+                            }
+
+                            let num_records = NUM_RECORDS.fetch_add(1, Ordering::Relaxed) + 1;
+
+                            // the other workers can still finish a sample before they stop
+                            if config.max_samples.is_none_or(|max_samples| num_records <= max_samples) {
+                                results.push(result);
+                            }
+
+                            if config.max_samples == Some(num_records) {
+                                println!("Reached --max-samples, stopping");
+                                STOPPING.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(_) => {
+                            println!("worker-{id} panicked, recreating its simulation");
+                            simulation = Simulation::new(&config, Some(simulation.rng.get_seed()));
+                        }
+                    }
+                }
+
+                initial_allocation_num = results.capacity();
+                tx.send((id, results, simulation.rng.get_seed())).unwrap();
+
+                if STOPPING.load(Ordering::Relaxed) || quota == Some(num_records) {
+                    break;
+                }
+            }
+        });
+
+        workers.push(worker.unwrap());
+    }
+
+    drop(tx);
+
+    thread::spawn({
+        let config = config.clone();
+        move || read_commands(&config)
+    });
+
+    if let Some(path) = config.config.clone() {
+        let config = config.clone();
+        thread::spawn(move || watch_config(&config, &path));
+    }
+
+    let start_time = Instant::now();
+
+    if let Some(interval) = config.heartbeat {
+        thread::spawn(move || heartbeat(Duration::from_secs_f32(interval), start_time));
+    }
+
+    let mut header = Header::new(config);
+    header.metadata.fill_gaps = gap_reference;
+    header.metadata.seed = Some(master_seed);
+
+    if config.per_run_dir {
+        Manifest::new(&header, started_at).write_to(Path::new(out_dir)).unwrap();
+        println!("Writing this run to {out_dir}");
+    }
+
+    // in deterministic mode, every worker writes to its own folder
+    let worker_folders = (0..num_threads)
+        .map(|id| format!("{out_dir}/worker-{id}"))
+        .collect::<Vec<_>>();
+    let mut worker_num_iters = Vec::new();
+
+    // with --split-by-difficulty, every difficulty band has its own folder
+    let num_bands = config.difficulty_thresholds.len() + 1;
+    let band_folders = (0..num_bands)
+        .map(|class| format!("{out_dir}/{}", band_name(class, num_bands)))
+        .collect::<Vec<_>>();
+    let mut band_num_iters = Vec::new();
+
+    // files can be deleted, but never overwritten
+    let mut num_iters = next_file_index(out_dir).unwrap().max(checkpoint.next_file);
+
+    let file_name = format!("{out_dir}/{num_iters}.bin");
+    if let Some(num_records) = recover_wal(out_dir, &file_name).unwrap() {
+        println!("Recovered {num_records} records from {out_dir}/{WAL_FILE} into {file_name}");
+        num_iters += 1;
+    }
+
+    let mut wal = config.wal.then(|| create_wal(out_dir, &header).unwrap());
+
+    if config.deterministic {
+        for folder in &worker_folders {
+            fs::create_dir_all(folder).unwrap();
+            worker_num_iters.push(next_file_index(folder).unwrap());
+        }
+
+        println!("Each worker is writing to its own folder in {out_dir}");
+    } else if config.split_by_difficulty {
+        for folder in &band_folders {
+            fs::create_dir_all(folder).unwrap();
+            band_num_iters.push(next_file_index(folder).unwrap());
+        }
+
+        println!("Each difficulty band is written to its own folder in {out_dir}");
+    } else {
+        println!("Starting with the name {num_iters}.bin for the next file");
+    }
+
+    // with --records-per-file or --max-file-size every file holds the same number of records, but the last
+    let records_per_file = if config.deterministic {
+        None
+    } else {
+        config
+            .records_per_file
+            .map(|records| records as usize)
+            .or_else(|| config.max_file_size.map(|size| (size as usize / header.record_size()).max(1)))
+    };
+
+    let mut current_threads = 0;
+    // counted in the checkpoint's totals, but left for the next file by the last run
+    let mut current_results = checkpoint.read_pending(Path::new(out_dir)).unwrap();
+    if !current_results.is_empty() {
+        println!(
+            "Restored the {} records the last run left for its next file",
+            current_results.len()
+        );
+    }
+    let mut total_time = checkpoint.total_time;
+    let mut stats = RunStats {
+        num_files: checkpoint.num_files,
+        total_records: checkpoint.total_records,
+        total_uncompressed_size: checkpoint.total_uncompressed_size,
+        total_compressed_size: checkpoint.total_compressed_size,
+        resumed_time: checkpoint.total_time,
+        worker_records,
+        seed: config.seed,
+        pending_file: checkpoint.pending_file.clone(),
+        status_tx: config.status_file.clone().map(|path| {
+            // only the latest status matters, so a stalled reader of a named pipe just makes it skip some
+            let (status_tx, status_rx) = bounded(1);
+            thread::spawn(move || write_status_file(&path, status_rx));
+            status_tx
+        }),
+        ..Default::default()
+    };
+
+    #[cfg(feature = "upload")]
+    let uploader = config.upload_to.as_ref().map(|url| {
+        let (upload_tx, uploader) = upload::spawn(config, url).unwrap();
+        stats.upload_tx = Some(upload_tx);
+        println!("Uploading every file to {url}");
+        uploader
+    });
+
+    let streamer = config.stream.map(|addr| {
+        let (stream_tx, streamer) = stream::spawn(addr, &header, config.stream_byte_order).unwrap();
+        println!("Streaming every batch to the clients connected to {addr}");
+        (stream_tx, streamer)
+    });
+
+    // once every worker has stopped, `None` writes the last, partial batch
+    for message in rx.iter().map(Some).chain([None]) {
+        let last_batch = message.is_none();
+
+        // a reload changes it from the next file
+        if let Some(zstd_level) = &mut header.metadata.zstd_level {
+            *zstd_level = ZSTD_LEVEL.load(Ordering::Relaxed);
+        }
+
+        if let Some((worker_id, results, rng_state)) = message {
+            rng_states[worker_id] = rng_state;
+
+            // before any file is written with the header
+            if config.zstd_dictionary && ZSTD_DICTIONARY.get().is_none() && !results.is_empty() {
+                match train_dictionary(out_dir, &header, &results) {
+                    Ok((id, dictionary)) => {
+                        println!("Compressing every file with the zstd dictionary {out_dir}/dictionary-{id}.zstd");
+                        header.metadata.zstd_dictionary_id = Some(id);
+                        stats.upload(&format!("{out_dir}/dictionary-{id}.zstd"));
+                        ZSTD_DICTIONARY.set(dictionary).unwrap();
+                    }
+                    Err(e) => println!("Couldn't train a zstd dictionary on the first batch, compressing without one: {e}"),
+                }
+            }
+
+            // besides Ctrl+C and its share of --max-samples, a worker can only stop by panicking outside of a sample
+            if !STOPPING.load(Ordering::Relaxed) {
+                let (finished, running) = mem::take(&mut workers)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|worker| worker.is_finished());
+                workers = running;
+
+                for worker in finished {
+                    let name = worker.thread().name().unwrap().to_string();
+                    if worker.join().is_err() {
+                        println!("{name} died, continuing without it");
+                    }
+                }
+                num_threads = workers.len();
+            }
+
+            total_time += results.iter().map(|r| r.time).sum::<f32>();
+
+            if let Some((stream_tx, _)) = &streamer {
+                if !results.is_empty() {
+                    stream_tx.send(results.clone()).unwrap();
+                }
+            }
+
+            if config.deterministic {
+                // only after Ctrl+C, a full batch always has records
+                if results.is_empty() {
+                    continue;
+                }
+
+                let file_name = next_file_name(&worker_folders[worker_id], &mut worker_num_iters[worker_id], config);
+
+                let (uncompressed_size, compressed_size) = write_file(&file_name, config, &header, &results).unwrap();
+                stats.file_written(&file_name, results.len(), uncompressed_size, compressed_size);
+                stats.worker_records[worker_id] += results.len() as u64;
+                stats.print_status(total_time, start_time);
+                stats.save_checkpoint(out_dir, &header, total_time, &rng_states, num_iters, &current_results);
+                continue;
+            }
+
+            if let Some(wal) = &mut wal {
+                // must be on disk before the records are only kept in memory
+                wal.write_all(&format::serialize(&header, &results, current_results.last()))
+                    .unwrap();
+                wal.sync_data().unwrap();
+            }
+
+            current_threads += 1;
+            current_results.extend(results);
+        } else if current_results.is_empty() {
+            break;
+        }
+
+        if current_threads >= num_threads || last_batch {
+            current_threads = 0;
+
+            if config.split_by_difficulty {
+                for (class, folder) in band_folders.iter().enumerate() {
+                    let band_results = current_results
+                        .iter()
+                        .filter(|result| usize::from(result.difficulty_class(&config.difficulty_thresholds)) == class)
+                        .copied()
+                        .collect::<Vec<_>>();
+
+                    if band_results.is_empty() {
+                        continue;
+                    }
+
+                    let file_name = next_file_name(folder, &mut band_num_iters[class], config);
+
+                    let mut band_header = header.clone();
+                    band_header.metadata.difficulty_band = Some(class as u8);
+
+                    let (uncompressed_size, compressed_size) =
+                        write_file(&file_name, config, &band_header, &band_results).unwrap();
+                    stats.file_written(&file_name, band_results.len(), uncompressed_size, compressed_size);
+                }
+
+                // no band gets a file when nothing was recorded
+                if stats.num_files != 0 {
+                    stats.print_status(total_time, start_time);
+                }
+
+                current_results.clear();
+            } else if let Some(records_per_file) = records_per_file {
+                let num_files = stats.num_files;
+
+                // the rest waits for the next batches, unless it's the last
+                while current_results.len() >= records_per_file || (last_batch && !current_results.is_empty()) {
+                    let file_results = current_results
+                        .drain(..records_per_file.min(current_results.len()))
+                        .collect::<Vec<_>>();
+                    let file_name = next_file_name(out_dir, &mut num_iters, config);
+
+                    let (uncompressed_size, compressed_size) =
+                        write_file(&file_name, config, &header, &file_results).unwrap();
+                    stats.file_written(&file_name, file_results.len(), uncompressed_size, compressed_size);
+                }
+
+                if stats.num_files != num_files {
+                    stats.print_status(total_time, start_time);
+                }
+            } else {
+                let file_name = next_file_name(out_dir, &mut num_iters, config);
+
+                // write current_results to file
+                let (uncompressed_size, compressed_size) =
+                    write_file(&file_name, config, &header, &current_results).unwrap();
+                stats.file_written(&file_name, current_results.len(), uncompressed_size, compressed_size);
+                stats.print_status(total_time, start_time);
+                current_results.clear();
+            }
+            stats.save_checkpoint(out_dir, &header, total_time, &rng_states, num_iters, &current_results);
+
+            // the records are safely in the file now
+            if wal.is_some() {
+                wal = Some(create_wal(out_dir, &header).unwrap());
+            }
+        }
+    }
+
+    stats.print_summary(total_time, start_time);
+
+    if let Some((stream_tx, streamer)) = streamer {
+        // the clients still get the last batches
+        drop(stream_tx);
+        streamer.join().unwrap();
+    }
+
+    #[cfg(feature = "upload")]
+    if let Some(uploader) = uploader {
+        // the last files are still being uploaded
+        stats.upload_tx = None;
+        println!("Waiting for the uploads to finish");
+        uploader.join().unwrap();
+    }
+}
+
+/// Runs the subcommand `command`
+fn run_command(command: &Command) -> io::Result<()> {
+    match command {
+        Command::CompareDatasets { a, b, bin_width } => compare::compare_datasets(a, b, *bin_width),
+        Command::Replay {
+            dataset,
+            gains,
+            output,
+            assets_dir,
+        } => {
+            rocketsim_rs::init(Some(assets_dir));
+            replay::replay(dataset, PdGains::from_array(*gains), output)
+        }
+        Command::Validate {
+            input,
+            samples,
+            tolerance_ticks,
+            seed,
+            assets_dir,
+        } => {
+            rocketsim_rs::init(Some(assets_dir));
+            replay::validate(input, *samples, *tolerance_ticks, *seed)
+        }
+        Command::Regress {
+            reference,
+            samples,
+            seed,
+            update,
+            assets_dir,
+        } => {
+            rocketsim_rs::init(Some(assets_dir));
+            regress::regress(reference, *samples, *seed, *update)
+        }
+        Command::Stats { inputs, bin_width } => stats::print_stats(inputs, *bin_width),
+        Command::Normalize {
+            inputs,
+            output,
+            scaled,
+            scaling,
+        } => normalize::normalize(inputs, output, scaled.as_deref(), *scaling),
+        Command::Coverage {
+            dataset,
+            min_fraction,
+            top,
+        } => coverage::print_coverage(dataset, *min_fraction, *top),
+        Command::Outliers {
+            inputs,
+            output,
+            iqr_factor,
+        } => outliers::outliers(inputs, output, *iqr_factor),
+        Command::Convert { input, to } => convert::convert(input, *to),
+        Command::Inspect { file } => inspect::inspect(file),
+        Command::Shuffle {
+            inputs,
+            output,
+            records_per_file,
+            seed,
+        } => shuffle::shuffle(inputs, output, *records_per_file as usize, *seed),
+        Command::Split {
+            inputs,
+            output,
+            ratios,
+            kfold,
+            seed,
+        } => {
+            let partitions = kfold.map_or(split::Partitions::Ratios(*ratios), split::Partitions::KFold);
+            split::split(inputs, output, partitions, *seed)
+        }
+        Command::Dedup { inputs, output, epsilon } => dedup::dedup(inputs, output, *epsilon),
+        Command::Pack { dataset, output } => {
+            let output = output.clone().unwrap_or_else(|| {
+                let mut output = dataset.as_os_str().to_owned();
+                output.push(".tar.zst");
+                output.into()
+            });
+            pack::pack(dataset, &output)
+        }
+        Command::Unpack { archive, output } => pack::unpack(archive, output),
+        Command::Verify { dataset } => verify::verify(dataset),
+        Command::Rebalance {
+            inputs,
+            output,
+            bin_width,
+            max_time,
+            records,
+            seed,
+        } => rebalance::rebalance(
+            inputs,
+            output,
+            *bin_width,
+            *max_time,
+            records.map(|records| records as usize),
+            *seed,
+        ),
+        Command::Merge { inputs, output } => merge::merge(inputs, output),
+        Command::Migrate { dataset } => migrate::migrate(dataset),
+        Command::Schema { dataset } => reader::print_schemas(dataset),
+        Command::Trace {
+            dataset,
+            seed,
+            start,
+            output,
+            assets_dir,
+        } => {
+            rocketsim_rs::init(Some(assets_dir));
+            trace::trace(dataset.as_deref(), *seed, *start, output.as_deref())
+        }
+        Command::Reproduce { file, assets_dir } => {
+            rocketsim_rs::init(Some(assets_dir));
+            reproduce::reproduce(file)
+        }
+        Command::Regenerate {
+            dataset,
+            seed,
+            assets_dir,
+        } => {
+            rocketsim_rs::init(Some(assets_dir));
+            replay::regenerate(dataset, *seed)
+        }
+    }
+}
+
+/// The share of `total` records of worker `id` out of `num_workers`, the first ones taking the remainder
+fn worker_share(total: u64, id: usize, num_workers: usize) -> u64 {
+    total / num_workers as u64 + u64::from((id as u64) < total % num_workers as u64)
+}
+
+/// The folder of a difficulty band, easy, medium and hard when there are 3 of them
+fn band_name(class: usize, num_bands: usize) -> String {
+    match (num_bands, class) {
+        (2, 0) | (3, 0) => "easy".to_string(),
+        (3, 1) => "medium".to_string(),
+        (2, 1) | (3, 2) => "hard".to_string(),
+        _ => format!("class-{class}"),
+    }
+}
+
+/// Totals over the files written by this run
+#[derive(Default)]
+struct RunStats {
+    num_files: u64,
+    total_records: u64,
+    total_uncompressed_size: u64,
+    total_compressed_size: u64,
+    /// Seconds simulated by the runs before this one, resumed from the checkpoint
+    resumed_time: f32,
+    /// The records every worker has written in deterministic mode
+    worker_records: Vec<u64>,
+    /// The `--seed` of the run, for the checkpoint
+    seed: Option<u64>,
+    /// The pending file of the last checkpoint
+    pending_file: Option<String>,
+    /// Length of the last status line
+    status_len: usize,
+    /// Sends every status to the thread writing `--status-file`
+    status_tx: Option<Sender<String>>,
+    /// Sends every finished file to the thread uploading them to `--upload-to`
+    upload_tx: Option<Sender<PathBuf>>,
+}
+
+/// The status written to `--status-file`
+#[derive(Serialize)]
+struct Status {
+    elapsed_seconds: f32,
+    days_simulated: f32,
+    hours_per_second: f32,
+    num_files: u64,
+    total_records: u64,
+    total_compressed_size: u64,
+    compression_ratio: f64,
+}
+
+impl RunStats {
+    fn file_written(&mut self, file_name: &str, num_records: usize, uncompressed_size: u64, compressed_size: u64) {
+        self.num_files += 1;
+        self.total_records += num_records as u64;
+        self.total_uncompressed_size += uncompressed_size;
+        self.total_compressed_size += compressed_size;
+
+        // pad to fully overwrite the last status line
+        let file_info = format!(
+            "Wrote {file_name}: {num_records} records, {uncompressed_size} -> {compressed_size} bytes ({:.2}x)",
+            uncompressed_size as f64 / compressed_size as f64
+        );
+        println!("{file_info:<width$}", width = self.status_len);
+
+        self.upload(file_name);
+    }
+
+    /// Queues the finished file for upload with `--upload-to`
+    fn upload(&self, file_name: &str) {
+        if let Some(upload_tx) = &self.upload_tx {
+            upload_tx.send(PathBuf::from(file_name)).unwrap();
+        }
+    }
+
+    /// Saves the totals so far and the workers' RNG states to the checkpoint of `out_dir`
+    fn save_checkpoint(
+        &mut self,
+        out_dir: &str,
+        header: &Header,
+        total_time: f32,
+        rng_states: &[u64],
+        next_file: usize,
+        pending: &[SimResult],
+    ) {
+        let out_path = Path::new(out_dir);
+        let mut checkpoint = Checkpoint {
+            total_time,
+            num_samples: NUM_SAMPLES.load(Ordering::Relaxed),
+            num_files: self.num_files,
+            total_records: self.total_records,
+            total_uncompressed_size: self.total_uncompressed_size,
+            total_compressed_size: self.total_compressed_size,
+            seed: self.seed,
+            rng_states: rng_states.to_vec(),
+            worker_records: self.worker_records.clone(),
+            next_file,
+            pending_file: self.pending_file.clone(),
+        };
+
+        // the RNG states are past the records that didn't fill a file yet, so they're saved with them
+        if let Err(e) = checkpoint
+            .set_pending(out_path, header, pending)
+            .and_then(|()| checkpoint.write_to(out_path))
+        {
+            println!("Failed to write the checkpoint to {out_dir}: {e}");
+            return;
+        }
+
+        // the last checkpoint's pending file is only replaced once the new checkpoint is in place
+        if let Some(old_pending) = self
+            .pending_file
+            .take()
+            .filter(|old| checkpoint.pending_file.as_ref() != Some(old))
+        {
+            let _ = fs::remove_file(out_path.join(old_pending));
+        }
+        self.pending_file = checkpoint.pending_file;
+    }
+
+    /// Print the totals of the run once it's over
+    fn print_summary(&self, total_time: f32, start_time: Instant) {
+        println!(
+            "\nStopped after {:.0} seconds: {:.2} days simulated, {} records in {} files, {} -> {} bytes",
+            start_time.elapsed().as_secs_f32(),
+            total_time / 3600. / 24.,
+            self.total_records,
+            self.num_files,
+            self.total_uncompressed_size,
+            self.total_compressed_size
+        );
+    }
+
+    /// Print a quick performance update
+    fn print_status(&mut self, total_time: f32, start_time: Instant) {
+        let hours_gathered = total_time / 3600.;
+        let hours_per_second = (total_time - self.resumed_time) / 3600. / start_time.elapsed().as_secs_f32();
+        let status = format!(
+            "Total time simulated: {:.2} days ({hours_per_second:.1} hps), {} records and {} bytes per file ({:.2}x)",
+            hours_gathered / 24.,
+            self.total_records / self.num_files,
+            self.total_compressed_size / self.num_files,
+            self.total_uncompressed_size as f64 / self.total_compressed_size as f64
+        );
+        self.status_len = status.len();
+        print!("{status}\r");
+        io::stdout().flush().unwrap();
+
+        if let Some(status_tx) = &self.status_tx {
+            let status = Status {
+                elapsed_seconds: start_time.elapsed().as_secs_f32(),
+                days_simulated: hours_gathered / 24.,
+                hours_per_second,
+                num_files: self.num_files,
+                total_records: self.total_records,
+                total_compressed_size: self.total_compressed_size,
+                compression_ratio: self.total_uncompressed_size as f64 / self.total_compressed_size as f64,
+            };
+
+            let _ = status_tx.try_send(serde_json::to_string(&status).unwrap() + "\n");
+        }
+    }
+}
+
+/// Writes every status received to `path` until the run ends
+fn write_status_file(path: &Path, rx: Receiver<String>) {
+    for status in rx {
+        if let Err(e) = write_status(path, &status) {
+            println!("Failed to write the status to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Replaces the contents of the status file with `status`
+fn write_status(path: &Path, status: &str) -> io::Result<()> {
+    // a named pipe can't be replaced, the write blocks until something reads it
+    if fs::metadata(path).is_ok_and(|metadata| !metadata.is_file()) {
+        return fs::OpenOptions::new().write(true).open(path)?.write_all(status.as_bytes());
+    }
+
+    // renaming over the old file means a monitor never reads half a status
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    fs::write(&tmp_path, status)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Logs that the process is alive every `interval`, however rarely anything else is printed
+fn heartbeat(interval: Duration, start_time: Instant) {
+    loop {
+        thread::sleep(interval);
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let uptime = start_time.elapsed().as_secs();
+        println!(
+            "Heartbeat at {timestamp} (up {}:{:02}:{:02}): {} samples so far{}",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60,
+            NUM_SAMPLES.load(Ordering::Relaxed),
+            if PAUSED.load(Ordering::Relaxed) { ", paused" } else { "" }
+        );
+    }
+}
+
+/// Handles the commands typed into stdin until it's closed
+fn read_commands(config: &Config) {
+    for line in io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        match line.trim() {
+            "pause" => {
+                PAUSED.store(true, Ordering::Relaxed);
+                println!("Paused, type \"resume\" to continue");
+            }
+            "resume" => {
+                PAUSED.store(false, Ordering::Relaxed);
+                println!("Resumed");
+            }
+            "reload" => reload_config(config),
+            "" => {}
+            command => println!("Unknown command {command:?}, expected \"pause\", \"resume\" or \"reload\""),
+        }
+    }
+}
+
+/// Reloads the `--config` file every time it's modified
+fn watch_config(config: &Config, path: &Path) {
+    let modified = || fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut last_modified = modified();
+
+    loop {
+        thread::sleep(CONFIG_POLL_TIME);
+
+        let modified = modified();
+        if modified != last_modified {
+            last_modified = modified;
+            reload_config(config);
+        }
+    }
+}
+
+/// Applies the options that are safe to change while running, `--interval` from the next batch and `--zstd-level` from the next file.
+/// `config` is the one the run started with
+fn reload_config(config: &Config) {
+    let new_config = match Config::try_load() {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            println!("Couldn't reload the options, keeping the current ones: {e}");
+            return;
+        }
+    };
+
+    let interval_millis = interval_millis(new_config.interval);
+    if INTERVAL_MILLIS.swap(interval_millis, Ordering::Relaxed) != interval_millis {
+        println!("The interval is now {}s, from the next batch", new_config.interval);
+    }
+
+    if ZSTD_LEVEL.swap(new_config.zstd_level, Ordering::Relaxed) != new_config.zstd_level {
+        println!("The zstd level is now {}, from the next file", new_config.zstd_level);
+    }
+
+    let mut reloadable = new_config;
+    reloadable.interval = config.interval;
+    reloadable.zstd_level = config.zstd_level;
+
+    if reloadable != *config {
+        println!("Only --interval and --zstd-level can change while running, restart to apply the other changes");
+    }
+}
+
+fn interval_millis(interval: f32) -> u64 {
+    Duration::from_secs_f32(interval).as_millis() as u64
+}
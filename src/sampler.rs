@@ -0,0 +1,155 @@
+use crate::simulation::{SampleStart, SimResult, Simulation};
+use clap::ValueEnum;
+use rocketsim_rs::{
+    glam_ext::glam::{Mat3A, Vec3A},
+    math::Angle,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+impl Simulation {
+    /// Uniform in `[0, max_angle)`, or normally distributed around the nominal angle if there is one
+    fn sample_angle(&mut self, nominal: Option<f32>) -> f32 {
+        match nominal {
+            // Box-Muller transform, `1 - f32()` is never 0
+            Some(center) => {
+                let gaussian = (-2. * (1. - self.rng.f32()).ln()).sqrt() * (2. * PI * self.rng.f32()).cos();
+                center + gaussian * self.perturbation_std
+            }
+            None => self.rng.f32() * self.max_angle,
+        }
+    }
+
+    /// Draws the next sample from the RNG and simulates it, `None` if it was dropped or abandoned
+    pub fn do_random(&mut self) -> Option<SimResult> {
+        // `Rng::with_seed(seed)` replays this sample
+        let seed = self.rng.get_seed();
+
+        // only drawn with several configs, so a single config samples the same as before they could be mixed
+        let car_config_id = if self.arenas.len() > 1 {
+            self.rng.usize(..self.arenas.len())
+        } else {
+            0
+        };
+
+        let controller_id = if self.controllers.len() > 1 {
+            self.rng.usize(..self.controllers.len())
+        } else {
+            0
+        };
+
+        // random initial angular velocity
+        let mut ang_vel = Vec3A::new(self.rng.f32(), self.rng.f32(), self.rng.f32());
+        ang_vel = ang_vel.normalize() * self.rng.f32() * self.max_ang_vel;
+
+        // random initial orientation
+        let mut angle = Angle {
+            pitch: self.sample_angle(self.nominal.map(|n| n[0])),
+            yaw: self.sample_angle(self.nominal.map(|n| n[1])),
+            roll: self.sample_angle(self.nominal.map(|n| n[2])),
+        };
+
+        let mut target_pitch = self.sample_angle(self.nominal.map(|n| n[3]));
+        let mut target_yaw = self.sample_angle(self.nominal.map(|n| n[4]));
+
+        // the fixed dimensions are still drawn, so the others get the same values as without `--fix`
+        for &(dim, value) in &self.fixed {
+            match dim {
+                SampledDim::AngVelX => ang_vel.x = value,
+                SampledDim::AngVelY => ang_vel.y = value,
+                SampledDim::AngVelZ => ang_vel.z = value,
+                SampledDim::Pitch => angle.pitch = value,
+                SampledDim::Yaw => angle.yaw = value,
+                SampledDim::Roll => angle.roll = value,
+                SampledDim::TargetPitch => target_pitch = value,
+                SampledDim::TargetYaw => target_yaw = value,
+            }
+        }
+
+        // the rates are limited about the car's own axes, after the direction and magnitude were sampled
+        if let Some(max_rates) = self.max_rates {
+            ang_vel = clamp_rates(ang_vel, Mat3A::from(angle.to_rotmat()), max_rates);
+        }
+
+        if let Some(grid) = self.gap_grid {
+            let relative_ang_vel = Mat3A::from(angle.to_rotmat()).transpose() * ang_vel;
+            let relative_target = Angle {
+                pitch: target_pitch - angle.pitch,
+                yaw: target_yaw - angle.yaw,
+                roll: 0. - angle.roll,
+            };
+
+            // the worker will draw a new sample, more often in the cells the reference already covers well
+            if self.rng.f32() >= grid.acceptance(relative_ang_vel, relative_target) {
+                return None;
+            }
+        }
+
+        let start = SampleStart {
+            car_config_id,
+            controller_id,
+            ang_vel,
+            angle,
+            target_pitch,
+            target_yaw,
+        };
+
+        self.simulate_to_target(seed, start)
+    }
+}
+
+/// `ang_vel` with its components about the axes of the car at `rot` clamped to `max_rates`, in the world frame
+fn clamp_rates(ang_vel: Vec3A, rot: Mat3A, max_rates: Vec3A) -> Vec3A {
+    rot * (rot.transpose() * ang_vel).clamp(-max_rates, max_rates)
+}
+
+/// A quantity `do_random` samples, for `--fix`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SampledDim {
+    /// World-frame initial angular velocity (rad/s) about x
+    AngVelX,
+    AngVelY,
+    AngVelZ,
+    /// Initial orientation (rad)
+    Pitch,
+    Yaw,
+    Roll,
+    /// Direction of the target (rad)
+    TargetPitch,
+    TargetYaw,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_ANG_VEL;
+    use fastrand::Rng;
+    use rocketsim_rs::glam_ext::glam::EulerRot;
+
+    #[test]
+    fn clamped_rates_stay_within_the_limits() {
+        let mut rng = Rng::with_seed(0);
+        let max_rates = Vec3A::new(5.5, 2., 0.5);
+
+        for _ in 0..10_000 {
+            let ang_vel = (Vec3A::new(rng.f32(), rng.f32(), rng.f32()) * 2. - 1.) * MAX_ANG_VEL;
+            let rot = Mat3A::from_euler(EulerRot::ZYX, rng.f32() * PI, rng.f32() * PI, rng.f32() * PI);
+
+            let relative_ang_vel = rot.transpose() * clamp_rates(ang_vel, rot, max_rates);
+            // only f32 rounding of the rotation back and forth
+            assert!(
+                relative_ang_vel.abs().cmple(max_rates + 1e-5).all(),
+                "{relative_ang_vel} exceeds {max_rates}"
+            );
+        }
+    }
+
+    #[test]
+    fn rates_within_the_limits_are_kept() {
+        let ang_vel = Vec3A::new(0.3, -0.2, 0.1);
+        let clamped = clamp_rates(ang_vel, Mat3A::IDENTITY, Vec3A::new(5.5, 2., 0.5));
+
+        assert!(clamped.abs_diff_eq(ang_vel, 1e-6), "{clamped} isn't {ang_vel}");
+    }
+}
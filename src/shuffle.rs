@@ -0,0 +1,108 @@
+use crate::{
+    format::TimeCodec,
+    merge::{self, MergedHeader},
+    writer::write_serialized,
+};
+use fastrand::Rng;
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes the records of every results file in `inputs`, or in the dataset folders among them, to `output`
+/// as `{n}.bin` files of about `records_per_file` records each, in a random order.
+///
+/// Every record is first appended to a random one of the uncompressed `bucket-{n}.tmp` files, one for every output file,
+/// and every bucket is then shuffled on its own, so only one input file and one bucket are ever in memory
+pub fn shuffle(inputs: &[PathBuf], output: &Path, records_per_file: usize, seed: Option<u64>) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+
+    // only counts the records and checks the files can be written together
+    let mut merged = MergedHeader::default();
+    let mut num_records = 0;
+    for dataset_file in &files {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+        merged.add(dataset_file, &file)?;
+        num_records += file.records.len() / file.header.record_size();
+    }
+
+    let Some(mut header) = merged.finish() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no results files to shuffle"));
+    };
+
+    // a delta only means something after the record before it
+    let delta_ticks = header.time_codec == TimeCodec::DeltaTicks;
+    if delta_ticks {
+        header.time_codec = TimeCodec::Ticks;
+    }
+
+    fs::create_dir_all(output)?;
+    if fs::read_dir(output)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} isn't empty", output.display()),
+        ));
+    }
+
+    let mut rng = seed.map_or_else(Rng::new, Rng::with_seed);
+    let record_size = header.record_size();
+    let num_buckets = num_records.div_ceil(records_per_file).max(1);
+    let bucket_paths = (0..num_buckets)
+        .map(|bucket| output.join(format!("bucket-{bucket}.tmp")))
+        .collect::<Vec<_>>();
+
+    {
+        let mut buckets = bucket_paths
+            .iter()
+            .map(|path| fs::File::create(path).map(BufWriter::new))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        for dataset_file in &files {
+            let file = dataset_file.open()?;
+            let mut records = file.records;
+            if delta_ticks {
+                merge::absolute_ticks(&file.header, &mut records);
+            }
+
+            for record in records.chunks_exact(record_size) {
+                buckets[rng.usize(..num_buckets)].write_all(record)?;
+            }
+        }
+
+        for bucket in &mut buckets {
+            bucket.flush()?;
+        }
+    }
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    for (n, bucket_path) in bucket_paths.iter().enumerate() {
+        let bucket = fs::read(bucket_path)?;
+        let mut records = bucket.chunks_exact(record_size).collect::<Vec<_>>();
+        rng.shuffle(&mut records);
+
+        let file_name = output.join(format!("{n}.bin"));
+        write_serialized(
+            &file_name.to_string_lossy(),
+            &header_bytes,
+            header.compression,
+            &records.concat(),
+            records.len(),
+        )?;
+        fs::remove_file(bucket_path)?;
+
+        println!("Wrote {} with {} records", file_name.display(), records.len());
+    }
+
+    println!(
+        "Shuffled {num_records} records from {} files into {num_buckets} files in {}",
+        files.len(),
+        output.display()
+    );
+
+    Ok(())
+}
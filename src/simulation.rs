@@ -0,0 +1,601 @@
+use crate::{
+    config::Config,
+    controller::{default_pd, PdGains},
+    gaps::OccupancyGrid,
+    sampler::SampledDim,
+    trace, ARENA_MEM_WEIGHT_MODE, GAP_GRID, TICK_RATE,
+};
+use clap::ValueEnum;
+use fastrand::Rng;
+use rocketsim_rs::{
+    autocxx::WithinUniquePtr,
+    cxx::UniquePtr,
+    glam_ext::glam::{Mat3A, Quat, Vec3A},
+    math::{Angle, Vec3},
+    sim::{Arena, CarConfig, CarState, GameMode, Team},
+};
+use serde::{Deserialize, Serialize};
+
+/// The step at which the controller's open-loop response is measured
+const RESPONSE_STEP: u32 = 10;
+/// Height of the car at the start of every sample in the arenas with collision, about halfway to the ceiling
+const ARENA_SPAWN_HEIGHT: f32 = 1000.;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimResult {
+    pub initial_angular_velocity: Vec3A,
+    pub relative_target: Angle,
+    /// The rotation from the car's initial orientation to the target's, `R_initial^T * R_target`,
+    /// in the car's initial local frame
+    pub relative_rotation: Mat3A,
+    pub time: f32,
+    pub response: ControlResponse,
+    /// Unit direction to the target in the car's initial local frame
+    pub local_target: Vec3A,
+    /// Index of the car's config in `--car-configs`
+    pub car_config_id: u8,
+    /// Index of the controller's gains in `--controller`
+    pub controller_id: u8,
+    /// The car's world-frame orientation at the start
+    pub initial_orientation: Angle,
+    /// The car's world-frame angular velocity at the start
+    pub world_angular_velocity: Vec3A,
+    /// The target's world-frame orientation, its roll is always 0
+    pub world_target: Angle,
+    /// Unit direction to the target in the world frame
+    pub world_target_dir: Vec3A,
+    /// The RNG state the sample was drawn from, `Rng::with_seed(seed)` draws it again
+    pub seed: u64,
+    /// Sum of the step index times the angle to the target (rad) over every step,
+    /// penalizing slow settling more than a fast approach of the same total time
+    pub itae: f32,
+}
+
+/// How the car reacted to the controls during the step at `RESPONSE_STEP`
+#[derive(Clone, Copy, Debug)]
+pub struct ControlResponse {
+    /// Magnitude of the commanded (pitch, yaw, roll)
+    pub control: f32,
+    /// Magnitude of the change in angular velocity over the step, in rad/s^2
+    pub ang_accel: f32,
+}
+
+impl SimResult {
+    /// The exact number of ticks it took to reach the target
+    pub fn ticks(&self) -> u32 {
+        time_to_ticks(self.time)
+    }
+
+    /// The number of ascending `thresholds` the time is at or above, 0 for the easiest samples
+    pub fn difficulty_class(&self, thresholds: &[f32]) -> u8 {
+        thresholds.partition_point(|&threshold| threshold <= self.time) as u8
+    }
+
+    /// The response fields are allowed to be `NaN`, but never infinite
+    pub fn is_finite(&self) -> bool {
+        self.initial_angular_velocity.is_finite()
+            && self.relative_target.pitch.is_finite()
+            && self.relative_target.yaw.is_finite()
+            && self.relative_target.roll.is_finite()
+            && self.relative_rotation.is_finite()
+            && self.time.is_finite()
+            && !self.response.control.is_infinite()
+            && !self.response.ang_accel.is_infinite()
+            && self.local_target.is_finite()
+            && self.initial_orientation.pitch.is_finite()
+            && self.initial_orientation.yaw.is_finite()
+            && self.initial_orientation.roll.is_finite()
+            && self.world_angular_velocity.is_finite()
+            && self.world_target.pitch.is_finite()
+            && self.world_target.yaw.is_finite()
+            && self.world_target_dir.is_finite()
+            && self.itae.is_finite()
+    }
+}
+
+impl ControlResponse {
+    /// Used when the target was reached before `RESPONSE_STEP`
+    pub const NONE: Self = Self {
+        control: f32::NAN,
+        ang_accel: f32::NAN,
+    };
+}
+
+/// What a sample starts from
+#[derive(Clone, Copy, Debug)]
+pub struct SampleStart {
+    /// Index of the arena with the car's config
+    pub car_config_id: usize,
+    /// Index of the controller's gains
+    pub controller_id: usize,
+    /// World-frame angular velocity
+    pub ang_vel: Vec3A,
+    /// World-frame orientation
+    pub angle: Angle,
+    pub target_pitch: f32,
+    pub target_yaw: f32,
+}
+
+/// The full state of the car of one of the arenas, to branch samples from the same state
+#[derive(Clone, Copy, Debug)]
+pub struct CarSnapshot {
+    /// Index of the arena with the car's config
+    pub car_config_id: usize,
+    pub car_state: CarState,
+}
+
+pub struct Simulation {
+    /// An arena for every `--car-configs` entry, in order, with the id of its only car
+    pub arenas: Vec<(UniquePtr<Arena>, u32)>,
+    pub rng: Rng,
+    /// Check invariants during every sample
+    pub soak: bool,
+    /// Tolerance of the invariant checks
+    pub epsilon: f32,
+    pub up_reference: UpReference,
+    /// World-frame up direction for `UpReference::Body`
+    pub up_direction: Vec3A,
+    /// Samples that would take more steps than this are rejected
+    pub max_budget_steps: Option<u32>,
+    /// Where the car starts every sample
+    pub spawn_pos: Vec3,
+    pub stop_condition: StopCondition,
+    /// Max angular velocity of `StopCondition::Settled`
+    pub settle_ang_vel: f32,
+    /// Max absolute car-relative angular velocity of each axis, infinite for the unlimited ones, `None` if none are
+    pub max_rates: Option<Vec3A>,
+    /// Largest magnitude of the sampled initial angular velocity
+    pub max_ang_vel: f32,
+    /// The orientation and the target are sampled in `[0, max_angle)`
+    pub max_angle: f32,
+    /// The target is reached once the car's forward axis is within this many radians of it
+    pub convergence_threshold: f32,
+    /// Simulated seconds after which a sample is abandoned
+    pub max_sim_seconds: f32,
+    /// Pitch, yaw, roll, target pitch and target yaw to sample around
+    pub nominal: Option<[f32; 5]>,
+    pub perturbation_std: f32,
+    /// Sampled dimensions overridden with a constant, in the order they were passed
+    pub fixed: Vec<(SampledDim, f32)>,
+    /// The gains of every `--controller`, one is picked at random for every sample
+    pub controllers: Vec<PdGains>,
+    /// Samples are kept with the acceptance of their cell, with `--fill-gaps`
+    pub gap_grid: Option<&'static OccupancyGrid>,
+    /// Every step of the samples simulated since it was set, for `trace`
+    pub trace: Option<Vec<trace::TraceTick>>,
+}
+
+impl Simulation {
+    pub fn new(config: &Config, seed: Option<u64>) -> Self {
+        let arenas = config
+            .car_configs
+            .iter()
+            .map(|preset| {
+                let mut arena =
+                    Arena::new(config.arena_mode.game_mode(), ARENA_MEM_WEIGHT_MODE.0, TICK_RATE).within_unique_ptr();
+
+                let mut mutators = arena.get_mutator_config();
+                mutators.gravity.z = -f32::EPSILON;
+
+                arena.pin_mut().set_mutator_config(mutators);
+
+                let car_id = arena.pin_mut().add_car(Team::BLUE, preset.car_config());
+                (arena, car_id)
+            })
+            .collect();
+
+        Self {
+            arenas,
+            rng: seed.map_or_else(Rng::new, Rng::with_seed),
+            soak: config.soak,
+            epsilon: config.validation_epsilon,
+            up_reference: config.up_reference,
+            up_direction: Vec3A::from_array(config.up_direction).normalize(),
+            max_budget_steps: config.time_budget.map(|budget| (budget * TICK_RATE) as u32),
+            stop_condition: config.stop_condition,
+            settle_ang_vel: config.settle_ang_vel,
+            max_rates: config
+                .max_rates()
+                .iter()
+                .any(Option::is_some)
+                .then(|| Vec3A::from_array(config.max_rates().map(|max_rate| max_rate.unwrap_or(f32::INFINITY)))),
+            max_ang_vel: config.max_ang_vel,
+            max_angle: config.max_angle,
+            convergence_threshold: config.convergence_threshold,
+            max_sim_seconds: config.max_sim_seconds,
+            nominal: config.nominal,
+            perturbation_std: config.perturbation_std,
+            fixed: config.fix.clone(),
+            controllers: config.controllers(),
+            gap_grid: config.fill_gaps.as_ref().and_then(|_| GAP_GRID.get()),
+            trace: None,
+            // away from the floor, walls and ceiling of the arenas with collision
+            spawn_pos: if config.arena_mode == ArenaMode::Void {
+                Vec3::ZERO
+            } else {
+                Vec3::new(0., 0., ARENA_SPAWN_HEIGHT)
+            },
+        }
+    }
+
+    /// Flies the car from `start` until it reaches the target.
+    ///
+    /// `seed` is stored in the result and logged when a soak check fails.
+    pub fn simulate_to_target(&mut self, seed: u64, start: SampleStart) -> Option<SimResult> {
+        self.simulate(seed, start, None)
+    }
+
+    /// The full state of the car of the `car_config_id`th arena, like where the last sample in it ended
+    pub fn snapshot(&mut self, car_config_id: usize) -> CarSnapshot {
+        let (arena, car_id) = &mut self.arenas[car_config_id];
+
+        CarSnapshot {
+            car_config_id,
+            car_state: arena.pin_mut().get_car(*car_id),
+        }
+    }
+
+    /// Puts the car of the snapshot's arena back in the state of `snapshot`
+    pub fn restore(&mut self, snapshot: &CarSnapshot) {
+        let (arena, car_id) = &mut self.arenas[snapshot.car_config_id];
+        arena.pin_mut().set_car(*car_id, snapshot.car_state).unwrap();
+    }
+
+    /// Restores `snapshot` and flies the car from it until it reaches the target at `target_pitch` and `target_yaw`
+    /// with the `controller_id`th controller, so the same state can be branched into samples of several controllers
+    /// or targets. Unlike `simulate_to_target`, the car keeps every value of its state, even an angular velocity
+    /// beyond `--max-rate-x/y/z`
+    pub fn simulate_from(
+        &mut self,
+        seed: u64,
+        snapshot: &CarSnapshot,
+        controller_id: usize,
+        target_pitch: f32,
+        target_yaw: f32,
+    ) -> Option<SimResult> {
+        let car_state = snapshot.car_state;
+        let start = SampleStart {
+            car_config_id: snapshot.car_config_id,
+            controller_id,
+            ang_vel: car_state.ang_vel.into(),
+            angle: Angle::from_rotmat(car_state.rot_mat),
+            target_pitch,
+            target_yaw,
+        };
+
+        self.simulate(seed, start, Some(car_state))
+    }
+
+    /// Flies the car from `start`, or from the state `from` whose orientation and angular velocity `start` holds
+    fn simulate(&mut self, seed: u64, start: SampleStart, from: Option<CarState>) -> Option<SimResult> {
+        let SampleStart {
+            car_config_id,
+            controller_id,
+            ang_vel,
+            angle,
+            target_pitch,
+            target_yaw,
+        } = start;
+        let gains = self.controllers[controller_id];
+
+        let (arena, car_id) = &mut self.arenas[car_config_id];
+        let car_id = *car_id;
+
+        let mut ball_state = arena.pin_mut().get_ball();
+        ball_state.pos.z = -1000.;
+        arena.pin_mut().as_mut().set_ball(ball_state);
+
+        let mut car_state = from.unwrap_or_else(|| {
+            let mut car_state = arena.pin_mut().as_mut().get_car(car_id);
+            car_state.pos = self.spawn_pos;
+            car_state.vel = Vec3::ZERO;
+            car_state.rot_mat = angle.to_rotmat();
+            car_state
+        });
+        let initial_rot = Mat3A::from(car_state.rot_mat);
+
+        if self.soak && !is_orthonormal(initial_rot, self.epsilon) {
+            return soak_violation(seed, "initial rotation is not orthonormal");
+        }
+
+        let relative_ang_vel = initial_rot.transpose() * ang_vel;
+        car_state.ang_vel = ang_vel.into();
+
+        arena.pin_mut().set_car(car_id, car_state).unwrap();
+
+        // target angles relative to car angle
+        let rel_target_angles = Angle {
+            pitch: target_pitch - angle.pitch,
+            yaw: target_yaw - angle.yaw,
+            roll: 0. - angle.roll,
+        };
+
+        let target_angle = Angle {
+            pitch: target_pitch,
+            yaw: target_yaw,
+            roll: 0.,
+        };
+        let rel_target_rot = initial_rot.transpose() * Mat3A::from(target_angle.to_rotmat());
+
+        // angles to target
+        // x = forward, y = right, z = up
+        let target = Vec3A::new(
+            target_pitch.cos() * target_yaw.cos(),
+            target_pitch.sin(),
+            target_pitch.cos() * target_yaw.sin(),
+        ) * 1000.;
+
+        let target_dir = target.normalize();
+        let local_target_dir = initial_rot.transpose() * target_dir;
+
+        if self.soak {
+            if !target_dir.is_finite() || (target_dir.length() - 1.).abs() > self.epsilon {
+                return soak_violation(seed, "target direction is not a finite unit vector");
+            }
+
+            // the relative angles have to describe the rotation to the target, computed from the car's state
+            let start = Quat::from_mat3a(&Mat3A::from(angle.to_rotmat()));
+            let end = Quat::from_mat3a(&Mat3A::from(add_angles(angle, rel_target_angles).to_rotmat()));
+            if (start.inverse() * end).angle_between(Quat::from_mat3a(&rel_target_rot)) > self.epsilon {
+                return soak_violation(
+                    seed,
+                    "relative target doesn't lead from the initial orientation to the target",
+                );
+            }
+        }
+
+        let start_tick = arena.get_tick_count();
+        let mut num_steps = 0;
+        let mut response = ControlResponse::NONE;
+        let mut itae = 0.;
+        loop {
+            let car_state = arena.pin_mut().get_car(car_id);
+
+            // check if the angle is below the convergence threshold
+            let rot = Mat3A::from(car_state.rot_mat);
+
+            if self.soak {
+                if !is_orthonormal(rot, self.epsilon) {
+                    return soak_violation(seed, "rotation is not orthonormal");
+                }
+
+                if arena.get_tick_count() != start_tick + u64::from(num_steps) {
+                    return soak_violation(seed, "tick count did not advance by one per step");
+                }
+            }
+
+            let forward = rot * Vec3A::X;
+            let angle = forward.dot(target_dir).clamp(-1., 1.).acos();
+            itae += num_steps as f32 * angle;
+
+            if let Some(trace) = &mut self.trace {
+                trace.push(trace::TraceTick {
+                    tick: num_steps,
+                    orientation: Angle::from_rotmat(car_state.rot_mat),
+                    local_ang_vel: rot.transpose() * Vec3A::from(car_state.ang_vel),
+                    controls: None,
+                    angle_to_target: angle,
+                });
+            }
+
+            if angle < self.convergence_threshold
+                && (self.stop_condition == StopCondition::Aligned
+                    || Vec3A::from(car_state.ang_vel).length() < self.settle_ang_vel)
+            {
+                break;
+            }
+
+            if self.max_budget_steps.is_some_and(|max_steps| num_steps >= max_steps) {
+                // it would take longer than the time budget, so the worker will draw a new sample
+                return None;
+            }
+
+            if num_steps as f32 > TICK_RATE * self.max_sim_seconds {
+                // this doesn't happen but just in case
+                println!("Failed to reach target?");
+                return None;
+            }
+
+            let local_target = rot.transpose() * target;
+            let local_ang_vel = rot.transpose() * Vec3A::from(car_state.ang_vel);
+            let local_up = match self.up_reference {
+                UpReference::World => rot * Vec3A::Z,
+                UpReference::Body => rot.transpose() * self.up_direction,
+            };
+
+            let controls = default_pd(local_target, local_ang_vel, local_up, gains);
+            arena.pin_mut().set_car_controls(car_id, controls).unwrap();
+
+            if let Some(tick) = self.trace.as_mut().and_then(|trace| trace.last_mut()) {
+                tick.controls = Some([controls.pitch, controls.yaw, controls.roll]);
+            }
+
+            arena.pin_mut().step(1);
+
+            if num_steps == RESPONSE_STEP {
+                let next_ang_vel = Vec3A::from(arena.pin_mut().get_car(car_id).ang_vel);
+                response = ControlResponse {
+                    control: Vec3A::new(controls.pitch, controls.yaw, controls.roll).length(),
+                    ang_accel: (next_ang_vel - Vec3A::from(car_state.ang_vel)).length() * TICK_RATE,
+                };
+            }
+
+            num_steps += 1;
+        }
+
+        let time = num_steps as f32 / TICK_RATE;
+        let result = SimResult {
+            initial_angular_velocity: relative_ang_vel,
+            relative_target: rel_target_angles,
+            relative_rotation: rel_target_rot,
+            time,
+            response,
+            local_target: local_target_dir,
+            car_config_id: car_config_id as u8,
+            controller_id: controller_id as u8,
+            initial_orientation: angle,
+            world_angular_velocity: ang_vel,
+            world_target: target_angle,
+            world_target_dir: target_dir,
+            seed,
+            itae,
+        };
+
+        if self.soak
+            && from.is_none()
+            && self
+                .max_rates
+                .is_some_and(|max_rates| result.initial_angular_velocity.abs().cmpgt(max_rates + self.epsilon).any())
+        {
+            return soak_violation(seed, "initial angular velocity exceeds --max-rate-x/y/z");
+        }
+
+        if self.soak && result.ticks() != num_steps {
+            return soak_violation(seed, "time doesn't round to the number of steps");
+        }
+
+        if self.soak && !result.is_finite() {
+            return soak_violation(seed, "result is not finite");
+        }
+
+        Some(result)
+    }
+}
+
+pub fn time_to_ticks(time: f32) -> u32 {
+    (time * TICK_RATE).round() as u32
+}
+
+/// The step counts a sample can end with, from 0 up to one past the cap of `max_sim_seconds`,
+/// whose time doesn't round back to exactly the same number of ticks
+pub fn time_round_trip_violations(max_sim_seconds: f32) -> Vec<u32> {
+    let max_steps = (TICK_RATE * max_sim_seconds) as u32 + 1;
+
+    (0..=max_steps)
+        .filter(|&num_steps| time_to_ticks(num_steps as f32 / TICK_RATE) != num_steps)
+        .collect()
+}
+
+fn is_orthonormal(rot: Mat3A, epsilon: f32) -> bool {
+    (rot.transpose() * rot).abs_diff_eq(Mat3A::IDENTITY, epsilon)
+}
+
+fn add_angles(a: Angle, b: Angle) -> Angle {
+    Angle {
+        pitch: a.pitch + b.pitch,
+        yaw: a.yaw + b.yaw,
+        roll: a.roll + b.roll,
+    }
+}
+
+/// Logs the broken invariant along with the seed that reproduces it, and drops the sample
+fn soak_violation(seed: u64, invariant: &str) -> Option<SimResult> {
+    println!("Soak check failed with seed {seed}: {invariant}");
+    None
+}
+
+/// The RocketSim game mode of every arena
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArenaMode {
+    /// No arena geometry, doesn't need any assets
+    #[default]
+    Void,
+    Soccar,
+    Hoops,
+    Heatseeker,
+    Snowday,
+}
+
+impl ArenaMode {
+    pub fn game_mode(self) -> GameMode {
+        match self {
+            Self::Void => GameMode::THE_VOID,
+            Self::Soccar => GameMode::SOCCAR,
+            Self::Hoops => GameMode::HOOPS,
+            Self::Heatseeker => GameMode::HEATSEEKER,
+            Self::Snowday => GameMode::SNOWDAY,
+        }
+    }
+
+    /// The folder in the assets directory with this mode's collision meshes
+    pub fn meshes_folder(self) -> Option<&'static str> {
+        match self {
+            Self::Void => None,
+            Self::Hoops => Some("hoops"),
+            Self::Soccar | Self::Heatseeker | Self::Snowday => Some("soccar"),
+        }
+    }
+}
+
+/// The RocketSim car presets, one for each hitbox
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CarPreset {
+    #[default]
+    Octane,
+    Dominus,
+    Plank,
+    Breakout,
+    Hybrid,
+    Merc,
+}
+
+impl CarPreset {
+    pub fn car_config(self) -> &'static CarConfig {
+        match self {
+            Self::Octane => CarConfig::octane(),
+            Self::Dominus => CarConfig::dominus(),
+            Self::Plank => CarConfig::plank(),
+            Self::Breakout => CarConfig::breakout(),
+            Self::Hybrid => CarConfig::hybrid(),
+            Self::Merc => CarConfig::merc(),
+        }
+    }
+}
+
+/// When a sample has reached the target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StopCondition {
+    /// The car's forward axis is within `--convergence-threshold` of the target
+    #[default]
+    Aligned,
+    /// Aligned, and the angular velocity is below `--settle-ang-vel`
+    Settled,
+}
+
+/// The up direction the roll controller tries to align the car with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpReference {
+    /// The car's up axis in world coordinates
+    #[default]
+    World,
+    /// `--up-direction` as seen from the car
+    Body,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_SIM_SECONDS;
+
+    fn round_trips(num_steps: u32) -> bool {
+        time_to_ticks(num_steps as f32 / TICK_RATE) == num_steps
+    }
+
+    #[test]
+    fn time_round_trips_to_num_steps() {
+        assert_eq!(time_round_trip_violations(MAX_SIM_SECONDS), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn time_round_trips_at_the_edges() {
+        let cap = (TICK_RATE * MAX_SIM_SECONDS) as u32;
+
+        for num_steps in [0, 1, cap - 1, cap, cap + 1] {
+            assert!(round_trips(num_steps), "{num_steps} steps don't round trip");
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    format::{Fields, Layout, TimeCodec},
+    merge,
+    writer::write_serialized,
+};
+use fastrand::Rng;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The folders of the partitions drawn with ratios, in the order of the ratios
+const SPLITS: [&str; 3] = ["train", "val", "test"];
+
+/// How the records are assigned to partitions
+#[derive(Clone, Copy)]
+pub enum Partitions {
+    /// Drawn for train, val and test with these probabilities
+    Ratios([f32; 3]),
+    /// Assigned to one of this many `fold-{i}` folders by a hash of the record
+    KFold(u32),
+}
+
+impl Partitions {
+    fn names(self) -> Vec<String> {
+        match self {
+            Self::Ratios(_) => SPLITS.map(String::from).to_vec(),
+            Self::KFold(k) => (0..k).map(|fold| format!("fold-{fold}")).collect(),
+        }
+    }
+}
+
+/// Written as `split.json` in the output folder, recording how the partitions were made so they can be made again
+#[derive(Serialize)]
+struct SplitManifest {
+    seed: u64,
+    /// The fraction of the records drawn for train, val and test, without `--kfold`
+    ratios: Option<[f32; 3]>,
+    /// The number of folds with `--kfold`
+    kfold: Option<u32>,
+    /// Every input file, in the order they were split
+    sources: Vec<String>,
+    splits: BTreeMap<String, SplitFiles>,
+}
+
+#[derive(Clone, Default, Serialize)]
+struct SplitFiles {
+    num_records: u64,
+    files: Vec<String>,
+}
+
+/// Written as `fold-{i}.json` in the output folder with `--kfold`, the files to validate on and to train on for fold `i`
+#[derive(Serialize)]
+struct FoldManifest {
+    fold: u32,
+    train: SplitFiles,
+    val: SplitFiles,
+}
+
+/// Assigns every record of the results files in `inputs`, or in the dataset folders among them, to one of `partitions`,
+/// writing the records of every input file assigned to a partition to `{partition}/{n}.bin` in `output`,
+/// with `n` the index of the input file, and `split.json` describing them.
+/// The same inputs and `seed` always make the same partitions, and a record is only ever in one of them
+pub fn split(inputs: &[PathBuf], output: &Path, partitions: Partitions, seed: u64) -> io::Result<()> {
+    let files = merge::input_files(inputs)?;
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no results files to split"));
+    }
+
+    if output.join("split.json").exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already holds a split", output.display()),
+        ));
+    }
+
+    let names = partitions.names();
+    for name in &names {
+        fs::create_dir_all(output.join(name))?;
+    }
+
+    let mut rng = Rng::with_seed(seed);
+    let mut manifest = SplitManifest {
+        seed,
+        ratios: None,
+        kfold: None,
+        sources: Vec::with_capacity(files.len()),
+        splits: names.iter().map(|name| (name.clone(), SplitFiles::default())).collect(),
+    };
+
+    // the draws from 0 to 1 below which a record is in train, and in val
+    let (mut train_below, mut val_below) = (1., 1.);
+    match partitions {
+        Partitions::Ratios(ratios) => {
+            let total = ratios.iter().sum::<f32>();
+            train_below = ratios[0] / total;
+            val_below = (ratios[0] + ratios[1]) / total;
+            manifest.ratios = Some(ratios.map(|ratio| ratio / total));
+        }
+        Partitions::KFold(k) => manifest.kfold = Some(k),
+    }
+
+    for (n, dataset_file) in files.iter().enumerate() {
+        let file = dataset_file
+            .open()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+        let mut header = file.header.clone();
+        let mut records = file.records;
+        // a delta only means something after the record before it, which can be in another partition
+        if header.time_codec == TimeCodec::DeltaTicks {
+            merge::absolute_ticks(&header, &mut records);
+            header.time_codec = TimeCodec::Ticks;
+        }
+
+        // the records were read into rows, and are compressed again without the dictionary
+        header.layout = Layout::Rows;
+        header.metadata.zstd_dictionary_id = None;
+
+        let mut header_bytes = Vec::new();
+        header.write_to(&mut header_bytes)?;
+
+        let seed_offset = header.fields.contains(Fields::SEED).then(|| header.offset(Fields::SEED));
+        let mut partitioned = vec![Vec::new(); names.len()];
+        for record in records.chunks_exact(header.record_size()) {
+            let partition = match partitions {
+                Partitions::Ratios(_) => {
+                    let draw = rng.f32();
+                    if draw < train_below {
+                        0
+                    } else if draw < val_below {
+                        1
+                    } else {
+                        2
+                    }
+                }
+                // by the sample's seed if the file has it, otherwise by the whole record,
+                // so the fold of a sample never depends on the files or their order, and duplicates share a fold
+                Partitions::KFold(k) => {
+                    let key = seed_offset.map_or(record, |offset| &record[offset..offset + 8]);
+                    (fnv1a(&[&seed.to_le_bytes(), key]) % u64::from(k)) as usize
+                }
+            };
+
+            partitioned[partition].extend_from_slice(record);
+        }
+
+        for (name, records) in names.iter().zip(partitioned) {
+            if records.is_empty() {
+                continue;
+            }
+
+            let num_records = records.len() / header.record_size();
+            let file_name = output.join(name).join(format!("{n}.bin"));
+            write_serialized(
+                &file_name.to_string_lossy(),
+                &header_bytes,
+                header.compression,
+                &records,
+                num_records,
+            )?;
+
+            let split_files = manifest.splits.get_mut(name).unwrap();
+            split_files.num_records += num_records as u64;
+            split_files.files.push(format!("{name}/{n}.bin"));
+        }
+
+        manifest.sources.push(dataset_file.to_string());
+    }
+
+    if let Partitions::KFold(k) = partitions {
+        for fold in 0..k {
+            let mut train = SplitFiles::default();
+            for (name, split_files) in &manifest.splits {
+                if *name != names[fold as usize] {
+                    train.num_records += split_files.num_records;
+                    train.files.extend(split_files.files.iter().cloned());
+                }
+            }
+
+            let fold_manifest = FoldManifest {
+                fold,
+                train,
+                val: manifest.splits[&names[fold as usize]].clone(),
+            };
+            fs::write(
+                output.join(format!("fold-{fold}.json")),
+                serde_json::to_vec_pretty(&fold_manifest)?,
+            )?;
+        }
+    }
+
+    // written last, so a folder with a split.json holds the whole split
+    fs::write(output.join("split.json"), serde_json::to_vec_pretty(&manifest)?)?;
+
+    for (name, split_files) in &manifest.splits {
+        println!(
+            "{name}: {} records in {} files",
+            split_files.num_records,
+            split_files.files.len()
+        );
+    }
+    println!("Wrote the split of {} files to {}", files.len(), output.display());
+
+    Ok(())
+}
+
+/// 64-bit FNV-1a hash of `parts` one after the other
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+
+    for &byte in parts.iter().copied().flatten() {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash
+}
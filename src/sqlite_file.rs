@@ -0,0 +1,58 @@
+use crate::{format::Header, simulation::SimResult, table};
+use rusqlite::{params_from_iter, types::Value, Connection, Result};
+use std::{fs, path::Path};
+
+/// Writes a SQLite database with a `samples` table of every `table::columns` and a `header` table of the header as JSON,
+/// returning the size of the values and of the database
+pub fn write(path: &Path, header: &Header, results: &[SimResult]) -> Result<(u64, u64)> {
+    let columns = table::columns(header);
+    let column_defs = columns
+        .iter()
+        .map(|column| {
+            let sql_type = if column.integer { "INTEGER" } else { "REAL" };
+            format!("{} {sql_type} NOT NULL", column.name)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch(&format!(
+        "PRAGMA journal_mode = OFF;
+        CREATE TABLE header (json TEXT NOT NULL);
+        CREATE TABLE samples ({column_defs});"
+    ))?;
+    connection.execute("INSERT INTO header VALUES (?1)", [serde_json::to_string(header).unwrap()])?;
+
+    let transaction = connection.transaction()?;
+
+    {
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = transaction.prepare(&format!("INSERT INTO samples VALUES ({placeholders})"))?;
+
+        for result in results {
+            let values = columns.iter().zip(table::row(header, result)).map(|(column, value)| {
+                if column.integer {
+                    Value::Integer(value as i64)
+                } else {
+                    Value::Real(value.into())
+                }
+            });
+
+            insert.execute(params_from_iter(values))?;
+        }
+    }
+
+    // built after inserting every row, which is faster than keeping them up to date,
+    // on the time and the ids and classes that samples are usually filtered by
+    for column in columns.iter().filter(|column| column.integer || column.name == "time") {
+        transaction.execute(&format!("CREATE INDEX {0}_index ON samples ({0})", column.name), [])?;
+    }
+
+    transaction.commit()?;
+    connection.close().map_err(|(_, e)| e)?;
+
+    let uncompressed_size = (results.len() * columns.len() * 4) as u64;
+    let size = fs::metadata(path).map_or(0, |metadata| metadata.len());
+
+    Ok((uncompressed_size, size))
+}
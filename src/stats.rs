@@ -0,0 +1,96 @@
+use crate::{
+    compare::{self, Histogram},
+    reader,
+};
+use std::{f32::consts::PI, io, path::PathBuf};
+
+/// Width of the buckets of the angular velocity magnitude, in rad/s
+const ANG_VEL_BIN_WIDTH: f32 = 0.5;
+/// Buckets the target angles are spread over, from -π to π
+const ANGLE_BINS: usize = 8;
+/// Width of the bar of the fullest bucket
+const BAR_WIDTH: usize = 40;
+
+/// Prints the distribution of the time to reach the target of every record in `inputs`, results files or dataset folders,
+/// in buckets of `bin_width` seconds, and of the magnitude of the car-relative initial angular velocity
+/// and the relative target angles, to check how well the samples cover them
+pub fn print_stats(inputs: &[PathBuf], bin_width: f32) -> io::Result<()> {
+    let mut num_files = 0;
+    let mut times = Vec::new();
+    let mut ang_vels = Vec::new();
+    let mut target_angles = [Vec::new(), Vec::new(), Vec::new()];
+
+    for input in inputs {
+        for dataset_file in reader::files_or_dataset(input)? {
+            let file = dataset_file
+                .open()
+                .map_err(|e| io::Error::new(e.kind(), format!("failed to read {dataset_file}: {e}")))?;
+
+            times.extend(file.times());
+            for (ang_vel, target) in file.inputs() {
+                ang_vels.push(ang_vel.length());
+                target_angles[0].push(target.pitch);
+                target_angles[1].push(target.yaw);
+                target_angles[2].push(target.roll);
+            }
+
+            num_files += 1;
+        }
+    }
+
+    if times.is_empty() {
+        let inputs = inputs.iter().map(|input| input.display().to_string()).collect::<Vec<_>>();
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no records found in {}", inputs.join(", ")),
+        ));
+    }
+
+    println!("{} records from {num_files} files", times.len());
+    println!();
+
+    print_distribution("Time", "s", &mut times, 0., bin_width);
+    print_distribution("Angular velocity magnitude", "rad/s", &mut ang_vels, 0., ANG_VEL_BIN_WIDTH);
+
+    let angle_bin_width = 2. * PI / ANGLE_BINS as f32;
+    for (name, angles) in ["Target pitch", "Target yaw", "Target roll"]
+        .into_iter()
+        .zip(&mut target_angles)
+    {
+        print_distribution(name, "rad", angles, -PI, angle_bin_width);
+    }
+
+    Ok(())
+}
+
+/// Prints the mean, percentiles and max of `values`, and a histogram of them in buckets of `bin_width` from `min`
+fn print_distribution(name: &str, unit: &str, values: &mut [f32], min: f32, bin_width: f32) {
+    values.sort_by(f32::total_cmp);
+    let percentile = |p: f32| values[((values.len() - 1) as f32 * p).round() as usize];
+
+    println!(
+        "{name} ({unit}): mean {:.4}, p50 {:.4}, p90 {:.4}, p99 {:.4}, max {:.4}",
+        compare::mean(values),
+        percentile(0.5),
+        percentile(0.9),
+        percentile(0.99),
+        values[values.len() - 1]
+    );
+
+    let offsets = values.iter().map(|value| value - min).collect::<Vec<_>>();
+    let histogram = Histogram::new(&offsets, bin_width);
+    let max_count = histogram.counts.iter().copied().max().unwrap_or(0).max(1);
+
+    for (bin, &count) in histogram.counts.iter().enumerate() {
+        let start = min + bin as f32 * bin_width;
+
+        println!(
+            "  {:<15} {:>7.2}% {}",
+            format!("{start:.2}-{:.2}", start + bin_width),
+            histogram.fraction(bin) * 100.,
+            "#".repeat((count * BAR_WIDTH as u64 / max_count) as usize)
+        );
+    }
+
+    println!();
+}
@@ -0,0 +1,91 @@
+use crate::{
+    format::{self, ByteOrder, Compression, Header, Layout},
+    simulation::SimResult,
+};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use std::{
+    io::{self, ErrorKind, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+};
+
+/// Batches waiting for the clients before the gather loop has to wait for them
+const QUEUED_BATCHES: usize = 16;
+
+/// Starts the thread sending every batch of records sent to it to the clients connected to `addr`,
+/// with the record fields in `byte_order`. Once the sender is dropped, joining the thread waits for the batches left
+pub fn spawn(
+    addr: SocketAddr,
+    header: &Header,
+    byte_order: ByteOrder,
+) -> io::Result<(Sender<Vec<SimResult>>, JoinHandle<()>)> {
+    let listener = TcpListener::bind(addr)?;
+    // new clients are only accepted between batches
+    listener.set_nonblocking(true)?;
+
+    // every frame is a whole number of records, uncompressed and record by record
+    let mut header = header.clone();
+    header.byte_order = byte_order;
+    header.layout = Layout::Rows;
+    header.compression = Compression::None;
+
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+
+    let (tx, rx) = bounded(QUEUED_BATCHES);
+    let streamer = thread::Builder::new()
+        .name("streamer".to_string())
+        .spawn(move || stream_batches(&listener, &header, &header_bytes, &rx))?;
+
+    Ok((tx, streamer))
+}
+
+/// Sends every batch received to the clients connected so far until the run ends
+fn stream_batches(listener: &TcpListener, header: &Header, header_bytes: &[u8], rx: &Receiver<Vec<SimResult>>) {
+    let mut clients: Vec<(SocketAddr, TcpStream)> = Vec::new();
+
+    for results in rx {
+        loop {
+            match listener.accept() {
+                Ok((mut client, addr)) => {
+                    // the header first, so the client knows the layout of every frame after it
+                    if let Err(e) = client.set_nonblocking(false).and_then(|()| client.write_all(header_bytes)) {
+                        println!("Failed to send the header to {addr}: {e}");
+                        continue;
+                    }
+
+                    println!("Streaming the records to {addr}");
+                    clients.push((addr, client));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    println!("Failed to accept a client: {e}");
+                    break;
+                }
+            }
+        }
+
+        if clients.is_empty() {
+            continue;
+        }
+
+        let frame = frame(header, &results);
+        clients.retain_mut(|(addr, client)| match client.write_all(&frame) {
+            Ok(()) => true,
+            Err(e) => {
+                println!("Stopped streaming to {addr}: {e}");
+                false
+            }
+        });
+    }
+}
+
+/// The records of a batch, after their length in bytes as a `u32` in the header's byte order
+fn frame(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let records = format::serialize(header, results, None);
+
+    let mut frame = header.byte_order.u32(records.len() as u32).to_vec();
+    frame.extend(records);
+
+    frame
+}
@@ -0,0 +1,158 @@
+use crate::{
+    format::{Fields, Header, Input, TargetRotation},
+    simulation::SimResult,
+};
+
+/// A column of the tabular output formats
+#[derive(Clone, Copy, Debug)]
+pub struct Column {
+    pub name: &'static str,
+    /// An id or a class, always a whole number
+    pub integer: bool,
+}
+
+impl Column {
+    const fn float(name: &'static str) -> Self {
+        Self { name, integer: false }
+    }
+
+    const fn integer(name: &'static str) -> Self {
+        Self { name, integer: true }
+    }
+}
+
+/// The columns of the base inputs present and the time, followed by the columns of the optional fields present,
+/// named like the fields of `proto/results.proto`
+pub fn columns(header: &Header) -> Vec<Column> {
+    let fields = header.fields;
+    let present = |names: [(Input, &'static str); 3]| {
+        names
+            .into_iter()
+            .filter(|&(input, _)| !header.omitted_inputs.contains(input))
+            .map(|(_, name)| Column::float(name))
+    };
+
+    let mut columns = present([
+        (Input::AngVelX, "ang_vel_x"),
+        (Input::AngVelY, "ang_vel_y"),
+        (Input::AngVelZ, "ang_vel_z"),
+    ])
+    .collect::<Vec<_>>();
+
+    match header.target_rotation {
+        TargetRotation::Euler => columns.extend(present([
+            (Input::TargetPitch, "target_pitch"),
+            (Input::TargetYaw, "target_yaw"),
+            (Input::TargetRoll, "target_roll"),
+        ])),
+        TargetRotation::Quaternion => {
+            columns.extend(["target_qw", "target_qx", "target_qy", "target_qz"].map(Column::float));
+        }
+        TargetRotation::Rotation6d => columns.extend(
+            [
+                "target_forward_x",
+                "target_forward_y",
+                "target_forward_z",
+                "target_right_x",
+                "target_right_y",
+                "target_right_z",
+            ]
+            .map(Column::float),
+        ),
+    }
+
+    columns.push(Column::float("time"));
+
+    if fields.contains(Fields::RESPONSE) {
+        columns.extend(["response_control", "response_ang_accel"].map(Column::float));
+    }
+
+    if fields.contains(Fields::LOCAL_TARGET) {
+        columns.extend(["local_target_x", "local_target_y", "local_target_z"].map(Column::float));
+    }
+
+    if fields.contains(Fields::DIFFICULTY_CLASS) {
+        columns.push(Column::integer("difficulty_class"));
+    }
+
+    if fields.contains(Fields::CAR_CONFIG_ID) {
+        columns.push(Column::integer("car_config_id"));
+    }
+
+    if fields.contains(Fields::INITIAL_ORIENTATION) {
+        columns.extend(["initial_pitch", "initial_yaw", "initial_roll"].map(Column::float));
+    }
+
+    if fields.contains(Fields::ITAE) {
+        columns.push(Column::float("itae"));
+    }
+
+    if fields.contains(Fields::CONTROLLER_ID) {
+        columns.push(Column::integer("controller_id"));
+    }
+
+    if fields.contains(Fields::WORLD_ANG_VEL) {
+        columns.extend(["world_ang_vel_x", "world_ang_vel_y", "world_ang_vel_z"].map(Column::float));
+    }
+
+    if fields.contains(Fields::WORLD_TARGET) {
+        columns.extend(["world_target_pitch", "world_target_yaw"].map(Column::float));
+    }
+
+    if fields.contains(Fields::WORLD_TARGET_DIR) {
+        columns.extend(["world_target_x", "world_target_y", "world_target_z"].map(Column::float));
+    }
+
+    columns
+}
+
+/// The value of every column of `result`, scaled like `format::serialize` with the seconds time codec
+pub fn row(header: &Header, result: &SimResult) -> Vec<f32> {
+    let fields = header.fields;
+    let mut row = header.inputs(result);
+    row.push(result.time / header.time_scale);
+
+    if fields.contains(Fields::RESPONSE) {
+        row.extend([result.response.control, result.response.ang_accel]);
+    }
+
+    if fields.contains(Fields::LOCAL_TARGET) {
+        row.extend(result.local_target.to_array());
+    }
+
+    if fields.contains(Fields::DIFFICULTY_CLASS) {
+        row.push(result.difficulty_class(&header.metadata.difficulty_thresholds).into());
+    }
+
+    if fields.contains(Fields::CAR_CONFIG_ID) {
+        row.push(result.car_config_id.into());
+    }
+
+    if fields.contains(Fields::INITIAL_ORIENTATION) {
+        let io = result.initial_orientation;
+        row.extend([io.pitch, io.yaw, io.roll].map(|angle| angle / header.angle_scale));
+    }
+
+    if fields.contains(Fields::ITAE) {
+        row.push(result.itae);
+    }
+
+    if fields.contains(Fields::CONTROLLER_ID) {
+        row.push(result.controller_id.into());
+    }
+
+    if fields.contains(Fields::WORLD_ANG_VEL) {
+        row.extend((result.world_angular_velocity / header.ang_vel_scale).to_array());
+    }
+
+    if fields.contains(Fields::WORLD_TARGET) {
+        let wt = result.world_target;
+        row.extend([wt.pitch, wt.yaw].map(|angle| angle / header.angle_scale));
+    }
+
+    if fields.contains(Fields::WORLD_TARGET_DIR) {
+        row.extend(result.world_target_dir.to_array());
+    }
+
+    row
+}
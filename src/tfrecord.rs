@@ -0,0 +1,66 @@
+use crate::{
+    crc::{self, CASTAGNOLI},
+    format::Header,
+    simulation::SimResult,
+    table,
+};
+
+/// A TFRecord file of a `tf.train.Example` for every result, with a feature for every `table::columns`,
+/// a `float_list` or, for the ids and classes, an `int64_list` of one value
+pub fn serialize(header: &Header, results: &[SimResult]) -> Vec<u8> {
+    let columns = table::columns(header);
+    let mut bytes = Vec::new();
+
+    for result in results {
+        let mut features = Vec::new();
+
+        for (column, value) in columns.iter().zip(table::row(header, result)) {
+            // Feature { float_list = 2 | int64_list = 3 { value = 1 (packed) } }
+            let feature = if column.integer {
+                let mut int64_list = Vec::new();
+                write_varint(&mut int64_list, value as i64 as u64);
+                message_field(3, &message_field(1, &int64_list))
+            } else {
+                message_field(2, &message_field(1, &value.to_le_bytes()))
+            };
+
+            // map<string, Feature> feature = 1, an entry of key = 1 and value = 2
+            let entry = [message_field(1, column.name.as_bytes()), message_field(2, &feature)].concat();
+            features.extend(message_field(1, &entry));
+        }
+
+        // Example { Features features = 1 }
+        let example = message_field(1, &features);
+
+        let length = (example.len() as u64).to_le_bytes();
+        bytes.extend(length);
+        bytes.extend(masked_crc(&length).to_le_bytes());
+        bytes.extend(&example);
+        bytes.extend(masked_crc(&example).to_le_bytes());
+    }
+
+    bytes
+}
+
+/// A length-delimited protobuf field
+fn message_field(number: u64, value: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.len() + 4);
+    write_varint(&mut bytes, number << 3 | 2);
+    write_varint(&mut bytes, value.len() as u64);
+    bytes.extend(value);
+    bytes
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+/// The CRC-32C of `data`, masked like TFRecord does so the CRC of data holding CRCs doesn't degrade
+fn masked_crc(data: &[u8]) -> u32 {
+    crc::crc32(CASTAGNOLI, data).rotate_right(15).wrapping_add(0xA282_EAD8)
+}
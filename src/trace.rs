@@ -0,0 +1,94 @@
+use crate::{
+    config::Config,
+    replay,
+    simulation::{SampleStart, Simulation},
+};
+use clap::Parser;
+use rocketsim_rs::{glam_ext::glam::Vec3A, math::Angle};
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The state of the car at the start of a step of a sample
+pub struct TraceTick {
+    /// Steps since the start of the sample
+    pub tick: u32,
+    /// World-frame orientation
+    pub orientation: Angle,
+    /// Car-relative angular velocity
+    pub local_ang_vel: Vec3A,
+    /// The pitch, yaw and roll the controller set for the step, `None` on the step the target was reached
+    pub controls: Option<[f32; 3]>,
+    /// Angle between the car's forward axis and the direction of the target, in rad
+    pub angle_to_target: f32,
+}
+
+/// Simulates one sample step by step, from `start`, the world-frame angular velocity, orientation and target pitch and yaw,
+/// or drawn from `seed` like a worker seeded with it, with the options of the dataset folder `dataset` or the defaults,
+/// and writes every step to the CSV file `output`, or prints it
+pub fn trace(dataset: Option<&Path>, seed: Option<u64>, start: Option<[f32; 8]>, output: Option<&Path>) -> io::Result<()> {
+    let mut simulation = match dataset {
+        Some(dataset) => replay::dataset_simulation(dataset, seed)?,
+        None => Simulation::new(&Config::parse_from([env!("CARGO_PKG_NAME")]), seed),
+    };
+    simulation.trace = Some(Vec::new());
+
+    let result = match start {
+        Some([x, y, z, pitch, yaw, roll, target_pitch, target_yaw]) => {
+            let start = SampleStart {
+                car_config_id: 0,
+                controller_id: 0,
+                ang_vel: Vec3A::new(x, y, z),
+                angle: Angle { pitch, yaw, roll },
+                target_pitch,
+                target_yaw,
+            };
+
+            simulation.simulate_to_target(seed.unwrap_or(0), start)
+        }
+        None => simulation.do_random(),
+    };
+
+    let trace = simulation.trace.take().unwrap();
+    let mut writer: Box<dyn Write> = match output {
+        Some(output) => Box::new(BufWriter::new(fs::File::create(output)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    writeln!(
+        writer,
+        "tick,pitch,yaw,roll,ang_vel_x,ang_vel_y,ang_vel_z,control_pitch,control_yaw,control_roll,angle_to_target"
+    )?;
+    for tick in &trace {
+        let [control_pitch, control_yaw, control_roll] = tick.controls.map_or_else(
+            || [String::new(), String::new(), String::new()],
+            |controls| controls.map(|control| control.to_string()),
+        );
+
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{control_pitch},{control_yaw},{control_roll},{}",
+            tick.tick,
+            tick.orientation.pitch,
+            tick.orientation.yaw,
+            tick.orientation.roll,
+            tick.local_ang_vel.x,
+            tick.local_ang_vel.y,
+            tick.local_ang_vel.z,
+            tick.angle_to_target
+        )?;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    match result {
+        Some(result) => println!("Reached the target after {} steps, {} s", trace.len() - 1, result.time),
+        // a sample rejected by --fill-gaps never steps
+        None if trace.is_empty() => println!("The sample was dropped before it was simulated"),
+        None => println!("The sample was abandoned after {} steps", trace.len()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::{
+    config::Config,
+    crc::{self, CASTAGNOLI},
+};
+use crossbeam_channel::{unbounded, Sender};
+use object_store::{
+    aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, path::Path as ObjectPath, ObjectStore, PutPayload,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tokio::runtime::Runtime;
+
+/// Seconds before retrying a failed upload, doubled for every retry after it
+const FIRST_RETRY_DELAY: f32 = 1.;
+
+/// Uploads the files of a run to `--upload-to`
+struct Uploader {
+    store: Box<dyn ObjectStore>,
+    runtime: Runtime,
+    /// `s3://{bucket}` or `gs://{bucket}`, for the messages
+    bucket_url: String,
+    /// The objects' path is their path in `--out-dir` under this prefix
+    prefix: String,
+    out_dir: PathBuf,
+    attempts: u32,
+    delete_local: bool,
+}
+
+/// Starts the thread uploading every finished file sent to it along with its sidecar, if it has one.
+/// Once the sender is dropped, joining the thread waits for the files left to upload
+pub fn spawn(config: &Config, url: &str) -> io::Result<(Sender<PathBuf>, JoinHandle<()>)> {
+    // checked by the --upload-to parser
+    let (scheme, location) = url.split_once("://").unwrap();
+    let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+
+    let store: Box<dyn ObjectStore> = match scheme {
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()
+                .map_err(io::Error::other)?,
+        ),
+        _ => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_bucket_name(bucket)
+                .build()
+                .map_err(io::Error::other)?,
+        ),
+    };
+
+    let uploader = Uploader {
+        store,
+        runtime: tokio::runtime::Builder::new_current_thread().enable_all().build()?,
+        bucket_url: format!("{scheme}://{bucket}"),
+        prefix: prefix.trim_matches('/').to_string(),
+        out_dir: PathBuf::from(&config.out_dir),
+        attempts: config.upload_attempts,
+        delete_local: config.upload_delete_local,
+    };
+
+    // files keep being written while one uploads, so the queue can't block the writer
+    let (tx, rx) = unbounded::<PathBuf>();
+    let handle = thread::Builder::new().name("uploader".to_string()).spawn(move || {
+        for path in rx {
+            uploader.upload_file(&path);
+        }
+    })?;
+
+    Ok((tx, handle))
+}
+
+impl Uploader {
+    /// Uploads `path` and its sidecar, deleting both once they're verified with `--upload-delete-local`
+    fn upload_file(&self, path: &Path) {
+        let mut paths = vec![path.to_path_buf()];
+
+        let sidecar = path.with_extension("json");
+        if sidecar.exists() {
+            paths.push(sidecar);
+        }
+
+        for path in &paths {
+            if !self.upload_with_retries(path) {
+                return;
+            }
+        }
+
+        if self.delete_local {
+            for path in &paths {
+                if let Err(e) = fs::remove_file(path) {
+                    println!("Failed to delete {} after uploading it: {e}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Uploads `path` with `upload` up to `--upload-attempts` times, returning whether it's uploaded
+    fn upload_with_retries(&self, path: &Path) -> bool {
+        let mut delay = FIRST_RETRY_DELAY;
+
+        for attempt in 1..=self.attempts {
+            match self.upload(path) {
+                Ok(location) => {
+                    println!("Uploaded {} to {}/{location}", path.display(), self.bucket_url);
+                    return true;
+                }
+                Err(e) if attempt < self.attempts => {
+                    println!(
+                        "Failed to upload {} (attempt {attempt} of {}), retrying in {delay} seconds: {e}",
+                        path.display(),
+                        self.attempts
+                    );
+                    thread::sleep(Duration::from_secs_f32(delay));
+                    delay *= 2.;
+                }
+                Err(e) => println!("Failed to upload {}, only keeping it locally: {e}", path.display()),
+            }
+        }
+
+        false
+    }
+
+    /// Uploads `path` and reads the object back to compare its CRC-32C with the file's, returning the object's path
+    fn upload(&self, path: &Path) -> io::Result<ObjectPath> {
+        let bytes = fs::read(path)?;
+        let checksum = crc::crc32(CASTAGNOLI, &bytes);
+
+        let relative = path.strip_prefix(&self.out_dir).unwrap_or(path);
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        // empty parts are dropped, so an empty prefix doesn't start the path with a slash
+        let location = ObjectPath::from(format!("{}/{relative}", self.prefix));
+
+        let uploaded = self
+            .runtime
+            .block_on(async {
+                self.store.put(&location, PutPayload::from(bytes)).await?;
+                self.store.get(&location).await?.bytes().await
+            })
+            .map_err(io::Error::other)?;
+
+        let uploaded_checksum = crc::crc32(CASTAGNOLI, &uploaded);
+        if uploaded_checksum != checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the uploaded object's CRC-32C {uploaded_checksum:08x} isn't the file's {checksum:08x}"),
+            ));
+        }
+
+        Ok(location)
+    }
+}
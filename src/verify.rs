@@ -0,0 +1,54 @@
+use crate::reader;
+use std::{io, path::Path};
+
+/// Decompresses every results file in the dataset folder `dir` and its worker folders, and every chunk of its `dataset.bin`,
+/// checking its record count and checksum against its footer, or its size against its sidecar,
+/// and prints every file that's corrupt or truncated, and every `.tmp` file left by a write that never finished
+pub fn verify(dir: &Path) -> io::Result<()> {
+    let mut num_checked = 0;
+    let mut num_records = 0;
+    let mut bad = Vec::new();
+
+    for path in reader::dataset_paths(dir, |path| path.extension().is_some_and(|ext| ext == "bin"))? {
+        let files = match reader::files_at(&path) {
+            Ok(files) => files,
+            Err(e) => {
+                num_checked += 1;
+                bad.push(format!("{}: {e}", path.display()));
+                continue;
+            }
+        };
+
+        for dataset_file in files {
+            num_checked += 1;
+
+            match dataset_file.open() {
+                Ok(file) => num_records += file.records.len() / file.header.record_size(),
+                Err(e) => bad.push(format!("{dataset_file}: {e}")),
+            }
+        }
+    }
+
+    let leftovers = reader::dataset_paths(dir, |path| path.extension().is_some_and(|ext| ext == "tmp"))?;
+    for path in &leftovers {
+        bad.push(format!("{}: the write never finished", path.display()));
+    }
+
+    for report in &bad {
+        println!("{report}");
+    }
+
+    println!(
+        "{} of {num_checked} files are intact with {num_records} records",
+        num_checked + leftovers.len() - bad.len()
+    );
+
+    if !bad.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} files in {} are corrupt or truncated", bad.len(), dir.display()),
+        ));
+    }
+
+    Ok(())
+}
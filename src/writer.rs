@@ -0,0 +1,339 @@
+#[cfg(feature = "arrow")]
+use crate::arrow_file;
+#[cfg(feature = "hdf5")]
+use crate::hdf5_file;
+#[cfg(feature = "parquet")]
+use crate::parquet_file;
+#[cfg(feature = "protobuf")]
+use crate::proto;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_file;
+use crate::{
+    capnp,
+    config::Config,
+    crc::{self, CASTAGNOLI},
+    csv, dataset_file,
+    format::{self, Compression, Header, OutputFormat, Sidecar},
+    jsonl, npz,
+    simulation::SimResult,
+    tfrecord, ZSTD_DICTIONARY, ZSTD_LEVEL,
+};
+use std::{
+    fs,
+    io::{self, Seek, Write},
+    path::Path,
+    sync::atomic::Ordering,
+};
+
+/// Holds the records received since the last file was written, when using `--wal`, in the folder the files go to
+pub const WAL_FILE: &str = "results.wal";
+/// Records in every sample a `--zstd-dictionary` is trained on
+const DICTIONARY_SAMPLE_RECORDS: usize = 64;
+/// Max size in bytes of a `--zstd-dictionary`
+const DICTIONARY_SIZE: usize = 1 << 16;
+
+/// One past the largest `n` of the `{n}.*` files in `dir`, so a deleted file never makes a new one overwrite another
+pub fn next_file_index(dir: &str) -> io::Result<usize> {
+    let mut next_file = 0;
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let index = name.to_str().and_then(|name| name.split('.').next()?.parse::<usize>().ok());
+
+        if let Some(index) = index {
+            next_file = next_file.max(index + 1);
+        }
+    }
+
+    Ok(next_file)
+}
+
+/// The next `{n}.{extension}` file in `dir`, skipping the ones another run in the same folder wrote since,
+/// or the one file every batch is appended to with `--format hdf5` or `--single-file`
+pub fn next_file_name(dir: &str, next_file: &mut usize, config: &Config) -> String {
+    #[cfg(feature = "hdf5")]
+    if config.format == OutputFormat::Hdf5 {
+        return format!("{dir}/results.h5");
+    }
+
+    if config.single_file {
+        return format!("{dir}/{}", dataset_file::DATASET_FILE);
+    }
+
+    let format = config.format;
+
+    loop {
+        let file_name = format!("{dir}/{next_file}.{}", format.extension());
+        *next_file += 1;
+
+        if !Path::new(&file_name).exists() {
+            return file_name;
+        }
+    }
+}
+
+/// Creates an empty write-ahead log in `dir`, replacing the old one
+pub fn create_wal(dir: &str, header: &Header) -> io::Result<fs::File> {
+    let mut wal = fs::File::create(format!("{dir}/{WAL_FILE}"))?;
+    header.write_to(&mut wal)?;
+    wal.sync_data()?;
+
+    Ok(wal)
+}
+
+/// Writes the records left in the write-ahead log in `dir` by a crashed run to `file_name`, with the header of that run,
+/// returning how many there were
+pub fn recover_wal(dir: &str, file_name: &str) -> io::Result<Option<usize>> {
+    let wal_path = format!("{dir}/{WAL_FILE}");
+    let bytes = match fs::read(&wal_path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    // a crash while writing the header leaves nothing to recover
+    let Some((header, records, num_records)) = format::split_records(&bytes) else {
+        println!("{wal_path} doesn't start with a header of this format version, ignoring it");
+        return Ok(None);
+    };
+
+    if num_records != 0 {
+        let compression = Header::read_from(&mut &header[..])?.compression;
+        write_serialized(file_name, header, compression, records, num_records)?;
+    }
+
+    fs::remove_file(wal_path)?;
+
+    Ok((num_records != 0).then_some(num_records))
+}
+
+/// Writes a results file of the serialized `header` and `records` to `{file_name}.tmp`, compressing the records
+/// with `compression` on this thread, and renames it once it's on disk
+pub fn write_serialized(
+    file_name: &str,
+    header: &[u8],
+    compression: Compression,
+    records: &[u8],
+    num_records: usize,
+) -> io::Result<()> {
+    write_serialized_with_workers(file_name, header, compression, records, num_records, 0)
+}
+
+/// `write_serialized`, with zstd compressing the records on `zstd_workers` threads if there are any,
+/// which changes the compressed bytes
+pub fn write_serialized_with_workers(
+    file_name: &str,
+    header: &[u8],
+    compression: Compression,
+    records: &[u8],
+    num_records: usize,
+    zstd_workers: u32,
+) -> io::Result<()> {
+    let tmp_name = format!("{file_name}.tmp");
+    let mut file = fs::File::create(&tmp_name)?;
+    file.write_all(header)?;
+    compress_records(compression, &mut file, zstd_workers, |encoder| encoder.write_all(records))?;
+    format::write_footer(&mut file, num_records, crc::crc32(CASTAGNOLI, records))?;
+    file.sync_all()?;
+    fs::rename(&tmp_name, file_name)
+}
+
+/// Writes the file to `{file_name}.tmp` and renames it once it's on disk, so a crash never leaves a truncated file,
+/// returning the size of the records before and after compression
+pub fn write_file(file_name: &str, config: &Config, header: &Header, results: &[SimResult]) -> io::Result<(u64, u64)> {
+    let tmp_name = format!("{file_name}.tmp");
+
+    // every batch is appended to the same file, so it can't be renamed into place
+    #[cfg(feature = "hdf5")]
+    if config.format == OutputFormat::Hdf5 {
+        return hdf5_file::append(Path::new(file_name), header, results).map_err(io::Error::other);
+    }
+
+    // SQLite writes and syncs the database through its own connection
+    #[cfg(feature = "sqlite")]
+    if config.format == OutputFormat::Sqlite {
+        // left by a crash, SQLite would try to open it
+        let _ = fs::remove_file(&tmp_name);
+        let sizes = sqlite_file::write(Path::new(&tmp_name), header, results).map_err(io::Error::other)?;
+        fs::rename(&tmp_name, file_name)?;
+
+        return Ok(sizes);
+    }
+
+    // appended as a whole chunk and synced by dataset_file, which drops a chunk a crash cut short
+    if config.single_file {
+        let mut chunk = io::Cursor::new(Vec::new());
+        let sizes = write_records(&mut chunk, config, header, results)?;
+        dataset_file::append(Path::new(file_name), chunk.get_ref())?;
+
+        return Ok(sizes);
+    }
+
+    let mut file = fs::File::create(&tmp_name)?;
+    let sizes = write_records(&mut file, config, header, results)?;
+    file.sync_all()?;
+    fs::rename(&tmp_name, file_name)?;
+
+    if config.sidecar_metadata {
+        // written last, so a file with a sidecar is always complete
+        let sidecar = serde_json::to_vec_pretty(&Sidecar::new(header, results.len()))?;
+        fs::write(Path::new(file_name).with_extension("json"), sidecar)?;
+    }
+
+    Ok(sizes)
+}
+
+/// Writes the header and the zstd compressed records, or only the uncompressed records with `--sidecar-metadata`,
+/// returning the size of the records before and after compression
+pub fn write_records<W: Write + Seek + Send>(
+    file: &mut W,
+    config: &Config,
+    header: &Header,
+    results: &[SimResult],
+) -> io::Result<(u64, u64)> {
+    match config.format {
+        OutputFormat::Binary => {}
+        #[cfg(feature = "protobuf")]
+        OutputFormat::Protobuf => {
+            // the header is part of the message
+            let bytes = proto::serialize(header, results);
+            compress(&bytes, &mut *file, config.zstd_workers)?;
+
+            return Ok((bytes.len() as u64, file.stream_position()?));
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => {
+            let uncompressed_size = parquet_file::write(&mut *file, header, results, ZSTD_LEVEL.load(Ordering::Relaxed))
+                .map_err(io::Error::other)?;
+
+            return Ok((uncompressed_size, file.stream_position()?));
+        }
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => {
+            let uncompressed_size = arrow_file::write(&mut *file, header, results).map_err(io::Error::other)?;
+            return Ok((uncompressed_size, file.stream_position()?));
+        }
+        #[cfg(feature = "sqlite")]
+        OutputFormat::Sqlite => unreachable!("written by write_file"),
+        #[cfg(feature = "hdf5")]
+        OutputFormat::Hdf5 => unreachable!("appended by write_file"),
+        OutputFormat::Npz => {
+            let bytes = npz::serialize(header, results)?;
+            file.write_all(&bytes)?;
+
+            return Ok((bytes.len() as u64, bytes.len() as u64));
+        }
+        OutputFormat::Jsonl => {
+            let bytes = jsonl::serialize(header, results);
+            file.write_all(&bytes)?;
+
+            return Ok((bytes.len() as u64, bytes.len() as u64));
+        }
+        OutputFormat::Tfrecord => {
+            let bytes = tfrecord::serialize(header, results);
+            file.write_all(&bytes)?;
+
+            return Ok((bytes.len() as u64, bytes.len() as u64));
+        }
+        OutputFormat::Capnp => {
+            let bytes = capnp::serialize(header, results);
+            file.write_all(&bytes)?;
+
+            return Ok((bytes.len() as u64, bytes.len() as u64));
+        }
+        OutputFormat::Csv => {
+            let bytes = csv::serialize(header, results);
+            compress(&bytes, &mut *file, config.zstd_workers)?;
+
+            return Ok((bytes.len() as u64, file.stream_position()?));
+        }
+    }
+
+    let uncompressed_size = (results.len() * header.record_size()) as u64;
+
+    if config.sidecar_metadata {
+        format::serialize_to(&mut *file, header, results)?;
+        return Ok((uncompressed_size, uncompressed_size));
+    }
+
+    header.write_to(file)?;
+    let header_size = file.stream_position()?;
+    let mut checksum = 0;
+    compress_records(header.compression, &mut *file, config.zstd_workers, |encoder| {
+        checksum = format::serialize_to(encoder, header, results)?;
+        Ok(())
+    })?;
+    let compressed_size = file.stream_position()? - header_size;
+    format::write_footer(file, results.len(), checksum)?;
+
+    Ok((uncompressed_size, compressed_size))
+}
+
+/// Compresses the records of a binary results file that `write` writes to the encoder into `writer` with `compression`,
+/// so they never have to be in memory all at once
+pub fn compress_records<W: Write>(
+    compression: Compression,
+    mut writer: W,
+    zstd_workers: u32,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    match compression {
+        Compression::Zstd => {
+            let mut encoder = zstd_encoder(writer, zstd_workers)?;
+            write(&mut encoder)?;
+            encoder.finish()?;
+        }
+        Compression::None => write(&mut writer)?,
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(writer);
+            write(&mut encoder)?;
+            encoder.finish().map_err(io::Error::other)?;
+        }
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+            write(&mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Compresses `bytes` into `writer` with `zstd_encoder`
+pub fn compress<W: Write>(bytes: &[u8], writer: W, workers: u32) -> io::Result<()> {
+    let mut encoder = zstd_encoder(writer, workers)?;
+    encoder.write_all(bytes)?;
+    encoder.finish()?;
+
+    Ok(())
+}
+
+/// A zstd encoder at the current zstd level, spread over `workers` threads if there are any,
+/// and with the `--zstd-dictionary` once it's trained
+pub fn zstd_encoder<W: Write>(writer: W, workers: u32) -> io::Result<zstd::Encoder<'static, W>> {
+    let level = ZSTD_LEVEL.load(Ordering::Relaxed);
+    let mut encoder = match ZSTD_DICTIONARY.get() {
+        Some(dictionary) => zstd::Encoder::with_dictionary(writer, level, dictionary)?,
+        None => zstd::Encoder::new(writer, level)?,
+    };
+    encoder.multithread(workers)?;
+
+    Ok(encoder)
+}
+
+/// Trains a zstd dictionary on the records of `results`, in samples of `DICTIONARY_SAMPLE_RECORDS`,
+/// and writes it to `{out_dir}/dictionary-{id}.zstd`, returning its id and the dictionary
+pub fn train_dictionary(out_dir: &str, header: &Header, results: &[SimResult]) -> io::Result<(u32, Vec<u8>)> {
+    let records = format::serialize(header, results, None);
+    let sample_size = header.record_size() * DICTIONARY_SAMPLE_RECORDS;
+    let sample_sizes = vec![sample_size; records.len() / sample_size];
+
+    let dictionary =
+        zstd::dict::from_continuous(&records[..sample_sizes.len() * sample_size], &sample_sizes, DICTIONARY_SIZE)?;
+    let id = zstd::zstd_safe::get_dict_id_from_dict(&dictionary).map_or(0, |id| id.get());
+    fs::write(format!("{out_dir}/dictionary-{id}.zstd"), &dictionary)?;
+
+    Ok((id, dictionary))
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Runs the generator in deterministic mode into a fresh `name` folder until every worker wrote its share of the samples
+fn run_deterministic(name: &str) -> PathBuf {
+    let out_dir = env::temp_dir().join(format!("stat-final-data-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&out_dir);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_stat-final-data"))
+        .args(["--deterministic", "--seed", "7", "--threads", "3"])
+        .args(["--records-per-file", "40", "--max-samples", "250", "--out-dir"])
+        .arg(&out_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "the run failed with {status}");
+
+    out_dir
+}
+
+/// The bytes of every file in the worker folders of `out_dir`, by their path in it
+fn worker_files(out_dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    let mut files = BTreeMap::new();
+
+    for worker in fs::read_dir(out_dir).unwrap() {
+        let worker = worker.unwrap().path();
+        if !worker.is_dir() {
+            continue;
+        }
+
+        for file in fs::read_dir(&worker).unwrap() {
+            let file = file.unwrap().path();
+            files.insert(file.strip_prefix(out_dir).unwrap().to_path_buf(), fs::read(&file).unwrap());
+        }
+    }
+
+    files
+}
+
+#[test]
+fn same_seed_writes_the_same_bytes() {
+    let first = run_deterministic("first");
+    let second = run_deterministic("second");
+
+    let first_files = worker_files(&first);
+    let second_files = worker_files(&second);
+    fs::remove_dir_all(first).unwrap();
+    fs::remove_dir_all(second).unwrap();
+
+    // 84, 83 and 83 records, in files of 40
+    assert_eq!(first_files.len(), 9);
+    assert_eq!(
+        first_files.keys().collect::<Vec<_>>(),
+        second_files.keys().collect::<Vec<_>>()
+    );
+
+    for (path, bytes) in &first_files {
+        assert!(bytes == &second_files[path], "{} differs between the runs", path.display());
+    }
+}